@@ -0,0 +1,285 @@
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use {Error, Read, Seek, SeekFrom, Write};
+
+/// Read bytes asynchronously.
+///
+/// This trait is analogous to the [`Read::read`] method, but integrates with
+/// the asynchronous task system. In particular, the [`poll_read`] method,
+/// unlike [`Read::read`], will automatically queue the current task for
+/// wakeup and return if data is not yet available, rather than blocking the
+/// calling thread.
+///
+/// [`Read::read`]: trait.Read.html#method.read
+/// [`poll_read`]: #method.poll_read
+pub trait AsyncRead {
+    /// The type that represents the set of all errors that can occur during
+    /// reading.
+    type Error: Error;
+
+    /// Attempt to read from the `AsyncRead` into `buf`.
+    ///
+    /// On success, returns `Poll::Ready(Ok(num_bytes_read))`.
+    ///
+    /// If no data is available for reading, the method returns
+    /// `Poll::Pending` and arranges for the current task (via
+    /// `cx.waker().wake_by_ref()`) to receive a notification when the object
+    /// becomes readable or is closed.
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, Self::Error>>;
+}
+
+/// Write bytes asynchronously.
+///
+/// This trait is analogous to the [`Write::write`] method, but integrates with
+/// the asynchronous task system. In particular, the [`poll_write`] method,
+/// unlike [`Write::write`], will automatically queue the current task for
+/// wakeup and return if the writer cannot take more data, rather than
+/// blocking the calling thread.
+///
+/// [`Write::write`]: trait.Write.html#method.write
+/// [`poll_write`]: #method.poll_write
+pub trait AsyncWrite {
+    /// The type that represents the set of all errors that can occur during
+    /// writing.
+    type Error: Error;
+
+    /// Attempt to write bytes from `buf` into the object.
+    ///
+    /// On success, returns `Poll::Ready(Ok(num_bytes_written))`.
+    ///
+    /// If the object is not ready for writing, the method returns
+    /// `Poll::Pending` and arranges for the current task (via
+    /// `cx.waker().wake_by_ref()`) to receive a notification when the object
+    /// becomes writable or is closed.
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &[u8],
+    ) -> Poll<Result<usize, Self::Error>>;
+
+    /// Attempt to flush the object, ensuring that any buffered data reach
+    /// their destination.
+    ///
+    /// On success, returns `Poll::Ready(Ok(()))`.
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<(), Self::Error>>;
+
+    /// Attempt to close the object.
+    ///
+    /// On success, returns `Poll::Ready(Ok(()))`.
+    fn poll_close(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<(), Self::Error>>;
+}
+
+/// Seek bytes asynchronously.
+///
+/// This trait is analogous to the [`Seek::seek`] method, but integrates with
+/// the asynchronous task system.
+///
+/// [`Seek::seek`]: trait.Seek.html#method.seek
+pub trait AsyncSeek {
+    /// The type that represents the set of all errors that can occur during
+    /// seeking.
+    type Error: Error;
+
+    /// Attempt to seek to an offset, in bytes, in a stream.
+    ///
+    /// If the seek operation completed successfully, this method returns the
+    /// new position from the start of the stream.
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        pos: SeekFrom,
+    ) -> Poll<Result<u64, Self::Error>>;
+}
+
+/// An adapter exposing a synchronous [`Read`], [`Write`] or [`Seek`] object,
+/// like a [`File`], through the asynchronous traits.
+///
+/// Every operation is forwarded to the wrapped file and completes
+/// immediately, so the returned `Poll` is always `Poll::Ready`.
+///
+/// [`Read`]: trait.Read.html
+/// [`Write`]: trait.Write.html
+/// [`Seek`]: trait.Seek.html
+/// [`File`]: trait.File.html
+#[derive(Debug, Clone, PartialEq, Eq, Default, Hash)]
+pub struct AsyncFile<F> {
+    inner: F,
+}
+
+impl<F> AsyncFile<F> {
+    /// Wraps a synchronous file.
+    pub fn new(inner: F) -> Self {
+        AsyncFile { inner }
+    }
+
+    /// Gets a reference to the underlying file.
+    pub fn get_ref(&self) -> &F {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying file.
+    pub fn get_mut(&mut self) -> &mut F {
+        &mut self.inner
+    }
+
+    /// Unwraps this `AsyncFile`, returning the underlying file.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F: Read + Unpin> AsyncRead for AsyncFile<F> {
+    type Error = F::Error;
+
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, Self::Error>> {
+        Poll::Ready(self.inner.read(buf))
+    }
+}
+
+impl<F: Write + Unpin> AsyncWrite for AsyncFile<F> {
+    type Error = F::Error;
+
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context,
+        buf: &[u8],
+    ) -> Poll<Result<usize, Self::Error>> {
+        Poll::Ready(self.get_mut().inner.write(buf))
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        _cx: &mut Context,
+    ) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(self.get_mut().inner.flush())
+    }
+
+    fn poll_close(
+        self: Pin<&mut Self>,
+        _cx: &mut Context,
+    ) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(self.get_mut().inner.flush())
+    }
+}
+
+impl<F: Seek + Unpin> AsyncSeek for AsyncFile<F> {
+    type Error = F::Error;
+
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        _cx: &mut Context,
+        pos: SeekFrom,
+    ) -> Poll<Result<u64, Self::Error>> {
+        Poll::Ready(self.get_mut().inner.seek(pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::pin::Pin;
+    use core::ptr;
+    use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+    use std::vec::Vec;
+
+    use super::*;
+    use mock::Cursor;
+    use ErrorKind;
+
+    /// A reader which yields one byte per ready poll, and is pending on
+    /// every other poll.
+    struct Trickle {
+        data: &'static [u8],
+        ready: bool,
+    }
+
+    impl AsyncRead for Trickle {
+        type Error = ErrorKind;
+
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context,
+            buf: &mut [u8],
+        ) -> Poll<Result<usize, ErrorKind>> {
+            self.ready = !self.ready;
+            if !self.ready {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let len = buf.len().min(self.data.len()).min(1);
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Poll::Ready(Ok(len))
+        }
+    }
+
+    /// Returns a waker which does nothing when woken.
+    fn noop_waker() -> Waker {
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(ptr::null(), &VTABLE)
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable =
+            RawWakerVTable::new(clone, noop, noop, noop);
+        // the vtable ignores the data pointer, so any pointer is sound
+        unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) }
+    }
+
+    fn read_to_end<R: AsyncRead + Unpin>(
+        mut reader: R,
+    ) -> Result<Vec<u8>, R::Error> {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut out = Vec::new();
+        let mut buf = [0; 4];
+        loop {
+            match Pin::new(&mut reader).poll_read(&mut cx, &mut buf) {
+                Poll::Ready(Ok(0)) => return Ok(out),
+                Poll::Ready(Ok(n)) => out.extend_from_slice(&buf[..n]),
+                Poll::Ready(Err(e)) => return Err(e),
+                Poll::Pending => {}
+            }
+        }
+    }
+
+    #[test]
+    fn mock_reader_completes_with_noop_waker() {
+        let reader = Trickle {
+            data: b"hello",
+            ready: false,
+        };
+        assert_eq!(read_to_end(reader).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn async_file_forwards_to_inner() {
+        let file = AsyncFile::new(Cursor::new(b"0123456789"));
+        assert_eq!(read_to_end(file).unwrap(), b"0123456789");
+
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut file = AsyncFile::new(Cursor::new(b""));
+        let poll = Pin::new(&mut file).poll_write(&mut cx, b"abc");
+        assert_eq!(poll, Poll::Ready(Ok(3)));
+        let poll = Pin::new(&mut file).poll_seek(&mut cx, SeekFrom::Start(1));
+        assert_eq!(poll, Poll::Ready(Ok(1)));
+        let poll = Pin::new(&mut file).poll_close(&mut cx);
+        assert_eq!(poll, Poll::Ready(Ok(())));
+        assert_eq!(file.get_ref().data(), b"abc");
+        assert_eq!(file.get_ref().flushes, 1);
+    }
+}
@@ -0,0 +1,520 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::cmp;
+
+use {skip_data, Error, ErrorKind, ErrorType, Read, Seek, SeekFrom, Write};
+
+/// The default capacity of the buffer of a [`BufReader`].
+///
+/// [`BufReader`]: struct.BufReader.html
+const DEFAULT_BUF_SIZE: usize = 8 * 1024;
+
+/// The default capacity of the buffer of a [`LineWriter`].
+///
+/// [`LineWriter`]: struct.LineWriter.html
+const DEFAULT_LINE_BUF_SIZE: usize = 1024;
+
+/// Adds buffering to any reader.
+///
+/// Reading from a `BufReader` reads large, infrequent chunks from the wrapped
+/// reader, which can improve the speed of programs that make small and
+/// repeated calls to [`read`] on the same file.
+///
+/// Seeking always discards the buffer, except for [`seek_relative`], which
+/// stays within the buffered data when possible.
+///
+/// [`read`]: trait.Read.html#method.read
+/// [`seek_relative`]: #method.seek_relative
+#[derive(Debug)]
+pub struct BufReader<R> {
+    inner: R,
+    buf: RefCell<Box<[u8]>>,
+    pos: Cell<usize>,
+    filled: Cell<usize>,
+}
+
+impl<R: Read> BufReader<R> {
+    /// Creates a new `BufReader` with a default buffer capacity of 8 KiB.
+    pub fn new(inner: R) -> Self {
+        BufReader::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    /// Creates a new `BufReader` with the specified buffer capacity.
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        BufReader {
+            inner,
+            buf: RefCell::new(vec![0; capacity].into_boxed_slice()),
+            pos: Cell::new(0),
+            filled: Cell::new(0),
+        }
+    }
+
+    /// Returns the contents of the internal buffer, filling it with more data
+    /// from the wrapped reader if it is empty.
+    ///
+    /// The returned bytes are not consumed, call [`consume`] once they've been
+    /// processed. An empty slice means that the wrapped reader reached EOF.
+    ///
+    /// [`consume`]: #method.consume
+    ///
+    /// # Errors
+    ///
+    /// This function will return any error returned by the wrapped reader.
+    pub fn fill_buf(&mut self) -> Result<&[u8], R::Error> {
+        let buf = self.buf.get_mut();
+        if self.pos.get() >= self.filled.get() {
+            let n = self.inner.read(buf)?;
+            self.pos.set(0);
+            self.filled.set(n);
+        }
+        Ok(&buf[self.pos.get()..self.filled.get()])
+    }
+
+    /// Marks `amt` bytes returned by [`fill_buf`] as consumed, so they won't
+    /// be returned again.
+    ///
+    /// `amt` is clamped to the number of buffered bytes.
+    ///
+    /// [`fill_buf`]: #method.fill_buf
+    pub fn consume(&mut self, amt: usize) {
+        let pos = cmp::min(self.pos.get() + amt, self.filled.get());
+        self.pos.set(pos);
+    }
+
+    /// Copies the next bytes into `buf` without consuming them, returning how
+    /// many were copied.
+    ///
+    /// The internal buffer is filled from the wrapped reader until it holds
+    /// `buf.len()` bytes or EOF is reached. Repeated calls return the same
+    /// bytes until they are consumed by [`read`] or [`consume`]. Since only
+    /// buffered data can be peeked, at most [`capacity`] bytes are copied.
+    ///
+    /// [`read`]: trait.Read.html#method.read
+    /// [`consume`]: #method.consume
+    /// [`capacity`]: #method.capacity
+    ///
+    /// # Errors
+    ///
+    /// This function will return any error returned by the wrapped reader.
+    /// Bytes buffered before the error occurred are kept.
+    pub fn peek(&mut self, buf: &mut [u8]) -> Result<usize, R::Error> {
+        let inner_buf = self.buf.get_mut();
+        let want = cmp::min(buf.len(), inner_buf.len());
+        let (mut pos, mut filled) = (self.pos.get(), self.filled.get());
+        if filled - pos < want {
+            // make room for the missing bytes at the end of the buffer
+            inner_buf.copy_within(pos..filled, 0);
+            filled -= pos;
+            pos = 0;
+            self.pos.set(pos);
+            self.filled.set(filled);
+            while filled < want {
+                match self.inner.read(&mut inner_buf[filled..])? {
+                    0 => break,
+                    n => filled += n,
+                }
+                self.filled.set(filled);
+            }
+        }
+
+        let n = cmp::min(want, filled - pos);
+        buf[..n].copy_from_slice(&inner_buf[pos..pos + n]);
+        Ok(n)
+    }
+
+    /// Returns the number of bytes the internal buffer can hold at once.
+    pub fn capacity(&self) -> usize {
+        self.buf.borrow().len()
+    }
+
+    /// Gets a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the wrapped reader.
+    ///
+    /// Reading directly from the wrapped reader skips over the buffered data.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `BufReader`, returning the wrapped reader.
+    ///
+    /// Any buffered data is lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn discard_buffer(&self) {
+        self.pos.set(0);
+        self.filled.set(0);
+    }
+}
+
+impl<R: Read + Seek> BufReader<R> {
+    /// Seeks relative to the current position, without discarding the buffer
+    /// if the new position is within it.
+    ///
+    /// Unlike [`seek`] with [`SeekFrom::Current`], this doesn't return the new
+    /// position, which allows it to skip calling [`seek`] on the wrapped reader
+    /// entirely for small offsets.
+    ///
+    /// [`seek`]: trait.Seek.html#method.seek
+    /// [`SeekFrom::Current`]: enum.SeekFrom.html#variant.Current
+    ///
+    /// # Errors
+    ///
+    /// This function will return any error returned by seeking the wrapped
+    /// reader.
+    pub fn seek_relative(&mut self, offset: i64) -> Result<(), R::Error> {
+        let pos = self.pos.get() as i64;
+        if let Some(new_pos) = pos.checked_add(offset) {
+            if 0 <= new_pos && new_pos <= self.filled.get() as i64 {
+                self.pos.set(new_pos as usize);
+                return Ok(());
+            }
+        }
+        self.seek(SeekFrom::Current(offset)).map(|_| ())
+    }
+
+    /// Skips up to `n` bytes by seeking instead of reading, returning how
+    /// many bytes were skipped.
+    ///
+    /// This is the seekable counterpart of [`skip`], which can only read and
+    /// discard the data since it takes `&self`. Bytes which are already
+    /// buffered are consumed without touching the wrapped reader, otherwise
+    /// the wrapped reader is seeked, first to its end to find out how much
+    /// data is left. Like [`skip`], fewer than `n` bytes are only skipped at
+    /// "end of file".
+    ///
+    /// [`skip`]: trait.Read.html#method.skip
+    ///
+    /// # Errors
+    ///
+    /// This function will return any error returned by seeking the wrapped
+    /// reader.
+    pub fn skip_seek(&mut self, n: u64) -> Result<u64, R::Error> {
+        let buffered = (self.filled.get() - self.pos.get()) as u64;
+        if n <= buffered {
+            self.consume(n as usize);
+            return Ok(n);
+        }
+        let pos = self.seek(SeekFrom::Current(0))?;
+        let end = self.inner.seek(SeekFrom::End(0))?;
+        let skipped = cmp::min(n, end.saturating_sub(pos));
+        self.inner.seek(SeekFrom::Start(pos + skipped))?;
+        Ok(skipped)
+    }
+}
+
+impl<R: ErrorType> ErrorType for BufReader<R> {
+    type Error = R::Error;
+}
+
+impl<R: Read> Read for BufReader<R> {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let mut inner_buf = self.buf.borrow_mut();
+        if self.pos.get() >= self.filled.get() {
+            // bypass the buffer for reads at least as large as it
+            if buf.len() >= inner_buf.len() {
+                return self.inner.read(buf);
+            }
+            let n = self.inner.read(&mut inner_buf)?;
+            self.pos.set(0);
+            self.filled.set(n);
+        }
+
+        let available = &inner_buf[self.pos.get()..self.filled.get()];
+        let n = cmp::min(buf.len(), available.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.pos.set(self.pos.get() + n);
+        Ok(n)
+    }
+
+    fn skip(&self, n: u64) -> Result<u64, Self::Error> {
+        let buffered = (self.filled.get() - self.pos.get()) as u64;
+        let from_buf = cmp::min(n, buffered);
+        self.pos.set(self.pos.get() + from_buf as usize);
+        if from_buf == n {
+            return Ok(n);
+        }
+        Ok(from_buf + skip_data(self, n - from_buf)?)
+    }
+}
+
+impl<R: Read + Seek> Seek for BufReader<R> {
+    /// Seeks to an offset, in bytes, in the wrapped reader, discarding the
+    /// buffer.
+    ///
+    /// The position used by [`SeekFrom::Current`] is the position of the
+    /// `BufReader`, which lags behind the wrapped reader by the number of
+    /// buffered bytes that weren't read yet.
+    ///
+    /// [`SeekFrom::Current`]: enum.SeekFrom.html#variant.Current
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        let result = match pos {
+            SeekFrom::Current(offset) => {
+                let remainder = (self.filled.get() - self.pos.get()) as i64;
+                match offset.checked_sub(remainder) {
+                    Some(offset) => self.inner.seek(SeekFrom::Current(offset)),
+                    None => {
+                        // seek in two steps to avoid overflowing
+                        self.inner.seek(SeekFrom::Current(-remainder))?;
+                        self.discard_buffer();
+                        self.inner.seek(SeekFrom::Current(offset))
+                    }
+                }
+            }
+            pos => self.inner.seek(pos),
+        }?;
+        self.discard_buffer();
+        Ok(result)
+    }
+}
+
+/// Adds line buffering to any writer.
+///
+/// Data written to a `LineWriter` is buffered until a newline (`\n`) is
+/// written. The buffered data up to and including the last newline of a
+/// write is then passed on to the wrapped writer, while the rest of the line
+/// stays buffered. This gives timely output of complete lines, e.g. for logs,
+/// without a call to the wrapped writer for every few bytes. Like
+/// `std::io::LineWriter`, passing data on doesn't [`flush`] the wrapped
+/// writer, and an incomplete line which doesn't fit into the buffer is
+/// written out as well.
+///
+/// Data which is still buffered when the `LineWriter` is dropped is lost,
+/// call [`flush`] or use a [`flush_guard`] to make sure it is written.
+///
+/// [`flush`]: trait.Write.html#method.flush
+/// [`flush_guard`]: trait.Write.html#method.flush_guard
+#[derive(Debug)]
+pub struct LineWriter<W> {
+    inner: W,
+    buf: Vec<u8>,
+    capacity: usize,
+}
+
+impl<W: Write> LineWriter<W> {
+    /// Creates a new `LineWriter` with a default buffer capacity of 1 KiB.
+    pub fn new(inner: W) -> Self {
+        LineWriter::with_capacity(DEFAULT_LINE_BUF_SIZE, inner)
+    }
+
+    /// Creates a new `LineWriter` with the specified buffer capacity.
+    pub fn with_capacity(capacity: usize, inner: W) -> Self {
+        LineWriter {
+            inner,
+            buf: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Returns the data which is buffered, but not written yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Returns the number of bytes the internal buffer can hold at once.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Gets a reference to the wrapped writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the wrapped writer.
+    ///
+    /// Writing directly to the wrapped writer bypasses the buffered data.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwraps this `LineWriter`, returning the wrapped writer.
+    ///
+    /// Any buffered data is lost, call [`flush`] before to write it.
+    ///
+    /// [`flush`]: trait.Write.html#method.flush
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Writes all buffered data to the wrapped writer, keeping the data which
+    /// couldn't be written if an error occurs.
+    fn flush_buf(&mut self) -> Result<(), W::Error> {
+        let mut written = 0;
+        let result = loop {
+            if written == self.buf.len() {
+                break Ok(());
+            }
+            match self.inner.write(&self.buf[written..]) {
+                Ok(0) => break Err(ErrorKind::WriteZero.into()),
+                Ok(n) => written += n,
+                Err(ref err) if err.is_interrupted() => {}
+                Err(err) => break Err(err),
+            }
+        };
+        self.buf.drain(..written);
+        result
+    }
+
+    /// Buffers as much of `data` as fits, writing it to the wrapped writer
+    /// directly if it is too large for the buffer.
+    fn write_to_buf(&mut self, data: &[u8]) -> Result<usize, W::Error> {
+        if self.buf.len() + data.len() > self.capacity {
+            self.flush_buf()?;
+        }
+        if data.len() >= self.capacity {
+            return self.inner.write(data);
+        }
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+}
+
+impl<W: ErrorType> ErrorType for LineWriter<W> {
+    type Error = W::Error;
+}
+
+impl<W: Write> Write for LineWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let newline = match buf.iter().rposition(|&b| b == b'\n') {
+            Some(newline) => newline,
+            None => {
+                // a complete line is left over from a failed write
+                if self.buf.last() == Some(&b'\n') {
+                    self.flush_buf()?;
+                }
+                return self.write_to_buf(buf);
+            }
+        };
+
+        self.flush_buf()?;
+        let (lines, tail) = buf.split_at(newline + 1);
+        let n = self.inner.write(lines)?;
+        if n < lines.len() {
+            return Ok(n);
+        }
+        let buffered = cmp::min(tail.len(), self.capacity);
+        self.buf.extend_from_slice(&tail[..buffered]);
+        Ok(n + buffered)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.flush_buf()?;
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock::Cursor;
+
+    fn digits() -> BufReader<Cursor> {
+        BufReader::with_capacity(4, Cursor::new(b"0123456789"))
+    }
+
+    #[test]
+    fn small_seek_relative_stays_in_buffer() {
+        let mut reader = digits();
+        let mut buf = [0; 1];
+        reader.read_exact(&mut buf).unwrap();
+        reader.seek_relative(2).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"3");
+        reader.seek_relative(-3).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"1");
+        assert_eq!(reader.get_ref().seeks, 0);
+        assert_eq!(reader.get_ref().reads.get(), 1);
+    }
+
+    #[test]
+    fn large_seek_relative_seeks_inner() {
+        let mut reader = digits();
+        let mut buf = [0; 1];
+        reader.read_exact(&mut buf).unwrap();
+        reader.seek_relative(5).unwrap();
+        assert_eq!(reader.get_ref().seeks, 1);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"6");
+
+        reader.seek_relative(-7).unwrap();
+        assert_eq!(reader.get_ref().seeks, 2);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"0");
+    }
+
+    #[test]
+    fn skip_seek_seeks_past_buffer() {
+        let mut reader = digits();
+        let mut buf = [0; 1];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(reader.skip_seek(2).unwrap(), 2);
+        assert_eq!(reader.get_ref().seeks, 0);
+        assert_eq!(reader.skip_seek(4).unwrap(), 4);
+        assert_eq!(reader.get_ref().reads.get(), 1);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"7");
+
+        assert_eq!(reader.skip_seek(100).unwrap(), 2);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn peek_is_idempotent_until_read() {
+        let reader = Cursor::new(b"0123456789abcdef").chunked(3);
+        let mut reader = BufReader::with_capacity(8, reader);
+        let mut buf = [0; 4];
+        assert_eq!(reader.peek(&mut buf).unwrap(), 4);
+        assert_eq!(&buf, b"0123");
+        buf = [0; 4];
+        assert_eq!(reader.peek(&mut buf).unwrap(), 4);
+        assert_eq!(&buf, b"0123");
+
+        buf = [0; 4];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"0123");
+        assert_eq!(reader.get_ref().reads.get(), 2);
+
+        // peeking is bounded by the capacity
+        let mut large = [0; 16];
+        assert_eq!(reader.peek(&mut large).unwrap(), 8);
+        assert_eq!(&large[..8], b"456789ab");
+    }
+
+    #[test]
+    fn line_writer_writes_complete_lines() {
+        let mut writer = LineWriter::new(Cursor::new(b""));
+        writer.write_all(b"par").unwrap();
+        assert_eq!(writer.get_ref().writes, 0);
+        assert_eq!(writer.buffer(), b"par");
+
+        writer.write_all(b"tial\nnext").unwrap();
+        assert_eq!(writer.get_ref().writes, 2);
+        assert_eq!(writer.get_ref().data(), b"partial\n");
+        assert_eq!(writer.buffer(), b"next");
+
+        // several lines in one write are passed on together
+        writer.write_all(b" line\nsecond\nthi").unwrap();
+        assert_eq!(writer.get_ref().writes, 4);
+        assert_eq!(writer.get_ref().data(), b"partial\nnext line\nsecond\n");
+        assert_eq!(writer.buffer(), b"thi");
+
+        writer.write_all(b"rd, still no newline").unwrap();
+        assert_eq!(writer.get_ref().writes, 4);
+        writer.flush().unwrap();
+        let inner = writer.into_inner();
+        assert_eq!((inner.writes, inner.flushes), (5, 1));
+        assert_eq!(
+            inner.data(),
+            b"partial\nnext line\nsecond\nthird, still no newline"
+        );
+    }
+}
@@ -0,0 +1,406 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use {
+    DirOptions, FileTimes, Fs, LinkKind, MountInfo, OpenOptions, Path,
+    SpecialKind,
+};
+
+type Cache<M> = RefCell<BTreeMap<Vec<u8>, M>>;
+
+/// A filesystem wrapper which memoizes the results of [`metadata`] and
+/// [`symlink_metadata`].
+///
+/// The first successful query of a path is forwarded to the wrapped
+/// filesystem, later queries of the same path are answered from the cache.
+/// Errors are not cached. This is an opt-in performance layer for backends
+/// where querying metadata is slow.
+///
+/// Paths are cached by their bytes as given, so different spellings of the
+/// same path are cached separately. Operations through the wrapper which
+/// modify a path, including opening it for writing, invalidate the cached
+/// metadata of that path, everything below it and its parent directory. The
+/// cache can still go stale, e.g. when a file is modified through another
+/// hard link or symbolic link, through an open file, or through [`get_mut`].
+/// Use [`invalidate`] or [`clear_cache`] in these cases.
+///
+/// [`metadata`]: trait.Fs.html#method.metadata
+/// [`symlink_metadata`]: trait.Fs.html#method.symlink_metadata
+/// [`get_mut`]: #method.get_mut
+/// [`invalidate`]: #method.invalidate
+/// [`clear_cache`]: #method.clear_cache
+pub struct CacheFs<F: Fs> {
+    inner: F,
+    metadata: Cache<F::Metadata>,
+    symlink_metadata: Cache<F::Metadata>,
+}
+
+impl<F: Fs> CacheFs<F>
+where
+    F::Metadata: Clone,
+{
+    /// Wraps a filesystem, with nothing cached yet.
+    pub fn new(inner: F) -> Self {
+        CacheFs {
+            inner,
+            metadata: RefCell::new(BTreeMap::new()),
+            symlink_metadata: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// Removes the cached metadata of `path`, everything below it and its
+    /// parent directory.
+    pub fn invalidate(&mut self, path: &F::Path) {
+        self.forget(path);
+    }
+
+    /// Removes all cached metadata.
+    pub fn clear_cache(&mut self) {
+        self.metadata.get_mut().clear();
+        self.symlink_metadata.get_mut().clear();
+    }
+
+    /// Gets a reference to the underlying filesystem.
+    pub fn get_ref(&self) -> &F {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying filesystem.
+    ///
+    /// Modifications made through this reference don't invalidate the cache.
+    pub fn get_mut(&mut self) -> &mut F {
+        &mut self.inner
+    }
+
+    /// Unwraps this `CacheFs`, returning the underlying filesystem.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    fn forget(&self, path: &F::Path) {
+        let parent = path.parent().map(Path::as_bytes);
+        let stale = |key: &[u8]| {
+            <F::Path as Path>::from_bytes(key).starts_with(path)
+                || Some(key) == parent
+        };
+        self.metadata.borrow_mut().retain(|key, _| !stale(key));
+        self.symlink_metadata
+            .borrow_mut()
+            .retain(|key, _| !stale(key));
+    }
+}
+
+fn cached<M, E, G>(cache: &Cache<M>, path: &[u8], f: G) -> Result<M, E>
+where
+    M: Clone,
+    G: FnOnce() -> Result<M, E>,
+{
+    if let Some(metadata) = cache.borrow().get(path) {
+        return Ok(metadata.clone());
+    }
+    let metadata = f()?;
+    cache.borrow_mut().insert(path.to_vec(), metadata.clone());
+    Ok(metadata)
+}
+
+impl<F: Fs> Fs for CacheFs<F>
+where
+    F::Metadata: Clone,
+{
+    type Path = F::Path;
+    type PathOwned = F::PathOwned;
+    type File = F::File;
+    type Dir = F::Dir;
+    type DirEntry = F::DirEntry;
+    type Metadata = F::Metadata;
+    type Permissions = F::Permissions;
+    type Error = F::Error;
+
+    fn open(
+        &self,
+        path: &Self::Path,
+        options: &OpenOptions<Self::Permissions>,
+    ) -> Result<Self::File, Self::Error> {
+        if options.write
+            || options.append
+            || options.create
+            || options.create_new
+            || options.truncate
+        {
+            self.forget(path);
+        }
+        self.inner.open(path, options)
+    }
+
+    fn open_anonymous(
+        &mut self,
+        dir: &Self::Path,
+        options: &OpenOptions<Self::Permissions>,
+    ) -> Result<Self::File, Self::Error> {
+        self.inner.open_anonymous(dir, options)
+    }
+
+    fn remove_file(&mut self, path: &Self::Path) -> Result<(), Self::Error> {
+        self.forget(path);
+        self.inner.remove_file(path)
+    }
+
+    fn metadata(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::Metadata, Self::Error> {
+        cached(&self.metadata, path.as_bytes(), || {
+            self.inner.metadata(path)
+        })
+    }
+
+    fn symlink_metadata(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::Metadata, Self::Error> {
+        cached(&self.symlink_metadata, path.as_bytes(), || {
+            self.inner.symlink_metadata(path)
+        })
+    }
+
+    fn rename(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.forget(from);
+        self.forget(to);
+        self.inner.rename(from, to)
+    }
+
+    fn copy(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<u64, Self::Error> {
+        self.forget(to);
+        self.inner.copy(from, to)
+    }
+
+    fn reflink(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.forget(to);
+        self.inner.reflink(from, to)
+    }
+
+    fn hard_link(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.forget(src);
+        self.forget(dst);
+        self.inner.hard_link(src, dst)
+    }
+
+    fn hard_link_checked(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<LinkKind, Self::Error> {
+        self.forget(src);
+        self.forget(dst);
+        self.inner.hard_link_checked(src, dst)
+    }
+
+    fn symlink(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.forget(dst);
+        self.inner.symlink(src, dst)
+    }
+
+    fn symlink_file(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.forget(dst);
+        self.inner.symlink_file(src, dst)
+    }
+
+    fn symlink_dir(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.forget(dst);
+        self.inner.symlink_dir(src, dst)
+    }
+
+    fn mknod(
+        &mut self,
+        path: &Self::Path,
+        mode: Self::Permissions,
+        kind: SpecialKind,
+        dev: u64,
+    ) -> Result<(), Self::Error> {
+        self.forget(path);
+        self.inner.mknod(path, mode, kind, dev)
+    }
+
+    fn read_link(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::PathOwned, Self::Error> {
+        self.inner.read_link(path)
+    }
+
+    fn canonicalize(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::PathOwned, Self::Error> {
+        self.inner.canonicalize(path)
+    }
+
+    fn canonicalize_into(
+        &self,
+        path: &Self::Path,
+        out: &mut Self::PathOwned,
+    ) -> Result<(), Self::Error> {
+        self.inner.canonicalize_into(path, out)
+    }
+
+    fn create_dir(
+        &mut self,
+        path: &Self::Path,
+        options: &DirOptions<Self::Permissions>,
+    ) -> Result<(), Self::Error> {
+        self.forget(path);
+        self.inner.create_dir(path, options)
+    }
+
+    fn remove_dir(&mut self, path: &Self::Path) -> Result<(), Self::Error> {
+        self.forget(path);
+        self.inner.remove_dir(path)
+    }
+
+    fn remove_dir_all(&mut self, path: &Self::Path) -> Result<(), Self::Error> {
+        self.forget(path);
+        self.inner.remove_dir_all(path)
+    }
+
+    fn read_dir(&self, path: &Self::Path) -> Result<Self::Dir, Self::Error> {
+        self.inner.read_dir(path)
+    }
+
+    fn set_permissions(
+        &mut self,
+        path: &Self::Path,
+        perm: Self::Permissions,
+    ) -> Result<(), Self::Error> {
+        self.forget(path);
+        self.inner.set_permissions(path, perm)
+    }
+
+    fn set_permissions_nofollow(
+        &mut self,
+        path: &Self::Path,
+        perm: Self::Permissions,
+    ) -> Result<(), Self::Error> {
+        self.forget(path);
+        self.inner.set_permissions_nofollow(path, perm)
+    }
+
+    fn set_times(
+        &mut self,
+        path: &Self::Path,
+        times: FileTimes,
+    ) -> Result<(), Self::Error> {
+        self.forget(path);
+        self.inner.set_times(path, times)
+    }
+
+    fn current_dir(&self) -> Result<Self::PathOwned, Self::Error> {
+        self.inner.current_dir()
+    }
+
+    /// Changes the current working directory of the wrapped filesystem.
+    ///
+    /// Since cached relative paths may refer to other files afterwards, this
+    /// clears the whole cache.
+    fn set_current_dir(
+        &mut self,
+        path: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.clear_cache();
+        self.inner.set_current_dir(path)
+    }
+
+    fn available_space(&self, path: &Self::Path) -> Result<u64, Self::Error> {
+        self.inner.available_space(path)
+    }
+
+    fn mount_points(
+        &self,
+    ) -> Result<Vec<MountInfo<Self::PathOwned>>, Self::Error> {
+        self.inner.mount_points()
+    }
+
+    fn mount_id(&self, path: &Self::Path) -> Result<u64, Self::Error> {
+        self.inner.mount_id(path)
+    }
+
+    fn sync(&self) -> Result<(), Self::Error> {
+        self.inner.sync()
+    }
+
+    fn flush_all(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush_all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+
+    use super::*;
+    use mem::MemFs;
+    use mock::write_file;
+    use {FsEvent, FsOp, FsStage, Metadata, ObserveFs};
+
+    #[test]
+    fn caches_until_invalidated() {
+        let calls = Cell::new(0);
+        let inner = ObserveFs::new(MemFs::new(), |event: &FsEvent<[u8]>| {
+            if let (FsOp::Metadata(_), FsStage::Before) =
+                (event.op, event.stage)
+            {
+                calls.set(calls.get() + 1);
+            }
+        });
+        let mut fs = CacheFs::new(inner);
+        write_file(&fs, b"/a", b"abc").unwrap();
+
+        assert_eq!(fs.metadata(b"/a").unwrap().len(), 3);
+        assert_eq!(fs.metadata(b"/a").unwrap().len(), 3);
+        assert_eq!(calls.get(), 1);
+
+        // writing through the wrapper invalidates the path
+        write_file(&fs, b"/a", b"abcdef").unwrap();
+        assert_eq!(fs.metadata(b"/a").unwrap().len(), 6);
+        assert_eq!(calls.get(), 2);
+
+        fs.invalidate(b"/a");
+        fs.metadata(b"/a").unwrap();
+        fs.clear_cache();
+        fs.metadata(b"/a").unwrap();
+        assert_eq!(calls.get(), 4);
+
+        // errors are not cached
+        assert!(fs.metadata(b"/missing").is_err());
+        assert!(fs.metadata(b"/missing").is_err());
+        assert_eq!(calls.get(), 6);
+    }
+}
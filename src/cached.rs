@@ -0,0 +1,98 @@
+use core::cell::OnceCell;
+
+use DirEntry;
+
+/// A directory entry wrapper which memoizes the metadata and file type of the
+/// entry it wraps.
+///
+/// The first call to [`metadata`] or [`file_type`] is forwarded to the
+/// wrapped entry and a successful result is cached, so later calls don't hit
+/// the filesystem again. Errors are not cached. [`path`] and [`file_name`] are
+/// always forwarded.
+///
+/// [`metadata`]: trait.DirEntry.html#method.metadata
+/// [`file_type`]: trait.DirEntry.html#method.file_type
+/// [`path`]: trait.DirEntry.html#method.path
+/// [`file_name`]: trait.DirEntry.html#method.file_name
+#[derive(Debug, Clone)]
+pub struct CachedDirEntry<E: DirEntry> {
+    inner: E,
+    metadata: OnceCell<E::Metadata>,
+    file_type: OnceCell<E::FileType>,
+}
+
+impl<E: DirEntry> CachedDirEntry<E> {
+    /// Wraps `entry`, with nothing cached yet.
+    pub fn new(entry: E) -> Self {
+        CachedDirEntry {
+            inner: entry,
+            metadata: OnceCell::new(),
+            file_type: OnceCell::new(),
+        }
+    }
+
+    /// Gets a reference to the wrapped entry.
+    pub fn get_ref(&self) -> &E {
+        &self.inner
+    }
+
+    /// Unwraps this `CachedDirEntry`, returning the wrapped entry.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+}
+
+impl<E> DirEntry for CachedDirEntry<E>
+where
+    E: DirEntry,
+    E::Metadata: Clone,
+    E::FileType: Clone,
+{
+    type Path = E::Path;
+    type PathOwned = E::PathOwned;
+    type Metadata = E::Metadata;
+    type FileType = E::FileType;
+    type Error = E::Error;
+
+    fn path(&self) -> Self::PathOwned {
+        self.inner.path()
+    }
+
+    fn metadata(&self) -> Result<Self::Metadata, Self::Error> {
+        if let Some(metadata) = self.metadata.get() {
+            return Ok(metadata.clone());
+        }
+        let metadata = self.inner.metadata()?;
+        Ok(self.metadata.get_or_init(|| metadata).clone())
+    }
+
+    fn file_type(&self) -> Result<Self::FileType, Self::Error> {
+        if let Some(file_type) = self.file_type.get() {
+            return Ok(file_type.clone());
+        }
+        let file_type = self.inner.file_type()?;
+        Ok(self.file_type.get_or_init(|| file_type).clone())
+    }
+
+    fn file_name(&self) -> &Self::Path {
+        self.inner.file_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock::{MockEntry, MockType};
+
+    #[test]
+    fn second_metadata_call_is_cached() {
+        let entry = MockEntry::new(b"file", MockType::File);
+        let calls = entry.metadata_calls.clone();
+        let cached = CachedDirEntry::new(entry);
+        let first = cached.metadata().unwrap();
+        assert_eq!(calls.get(), 1);
+        assert_eq!(cached.metadata().unwrap(), first);
+        assert_eq!(calls.get(), 1);
+        assert_eq!(cached.file_name(), b"file");
+    }
+}
@@ -0,0 +1,180 @@
+use core::cell::Cell;
+
+use {ErrorType, File, IoSlice, IoSliceMut, Read, Seek, SeekFrom, Write};
+
+/// A wrapper around a stream which counts the bytes read from and written to
+/// it.
+///
+/// `Counted<T>` implements [`Read`], [`Write`], [`Seek`] and [`File`] if `T`
+/// does, and forwards every call to the wrapped stream. The counters only
+/// grow by the number of bytes actually transferred, as returned by the
+/// wrapped stream, and are not affected by seeking.
+///
+/// [`Read`]: trait.Read.html
+/// [`Write`]: trait.Write.html
+/// [`Seek`]: trait.Seek.html
+/// [`File`]: trait.File.html
+#[derive(Debug, Default)]
+pub struct Counted<T> {
+    inner: T,
+    read: Cell<u64>,
+    written: u64,
+}
+
+impl<T> Counted<T> {
+    /// Wraps a stream, with both counters starting at zero.
+    pub fn new(inner: T) -> Self {
+        Counted {
+            inner,
+            read: Cell::new(0),
+            written: 0,
+        }
+    }
+
+    /// Returns the total number of bytes read from the stream.
+    pub fn read_count(&self) -> u64 {
+        self.read.get()
+    }
+
+    /// Returns the total number of bytes written to the stream.
+    pub fn write_count(&self) -> u64 {
+        self.written
+    }
+
+    /// Gets a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the wrapped stream.
+    ///
+    /// Bytes transferred directly through the wrapped stream are not counted.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwraps this `Counted`, returning the wrapped stream.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: ErrorType> ErrorType for Counted<T> {
+    type Error = T::Error;
+}
+
+impl<T: Read> Read for Counted<T> {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = self.inner.read(buf)?;
+        self.read.set(self.read.get() + n as u64);
+        Ok(n)
+    }
+}
+
+impl<T: Write> Write for Counted<T> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+
+    fn is_flush_noop(&self) -> bool {
+        self.inner.is_flush_noop()
+    }
+
+    fn reserve(&mut self, additional: usize) -> Result<(), Self::Error> {
+        self.inner.reserve(additional)
+    }
+}
+
+impl<T: Seek> Seek for Counted<T> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        self.inner.seek(pos)
+    }
+}
+
+impl<T: File> File for Counted<T> {
+    fn set_len(&mut self, size: u64) -> Result<(), Self::Error> {
+        self.inner.set_len(size)
+    }
+
+    fn duplicate_to(&self, target: &mut Self) -> Result<(), Self::Error> {
+        self.inner.duplicate_to(&mut target.inner)
+    }
+
+    fn read_at(
+        &self,
+        buf: &mut [u8],
+        offset: u64,
+    ) -> Result<usize, Self::Error> {
+        let n = self.inner.read_at(buf, offset)?;
+        self.read.set(self.read.get() + n as u64);
+        Ok(n)
+    }
+
+    fn write_at(
+        &mut self,
+        buf: &[u8],
+        offset: u64,
+    ) -> Result<usize, Self::Error> {
+        let n = self.inner.write_at(buf, offset)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn read_vectored_at(
+        &self,
+        bufs: &mut [IoSliceMut],
+        offset: u64,
+    ) -> Result<usize, Self::Error> {
+        let n = self.inner.read_vectored_at(bufs, offset)?;
+        self.read.set(self.read.get() + n as u64);
+        Ok(n)
+    }
+
+    fn write_vectored_at(
+        &mut self,
+        bufs: &[IoSlice],
+        offset: u64,
+    ) -> Result<usize, Self::Error> {
+        let n = self.inner.write_vectored_at(bufs, offset)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock::Cursor;
+    use ErrorKind;
+
+    #[test]
+    fn counts_bytes_passed_through() {
+        let mut counted = Counted::new(Cursor::new(b"").chunked(3));
+        counted.write_all(b"hello world").unwrap();
+        assert_eq!(counted.write_count(), 11);
+
+        counted.seek(SeekFrom::Start(6)).unwrap();
+        let mut buf = [0; 8];
+        assert_eq!(counted.read(&mut buf).unwrap(), 3);
+        assert_eq!(counted.read(&mut buf).unwrap(), 2);
+        assert_eq!(counted.read(&mut buf).unwrap(), 0);
+        assert_eq!(counted.read_count(), 5);
+        assert_eq!(counted.write_count(), 11);
+    }
+
+    #[test]
+    fn failed_calls_are_not_counted() {
+        let mut counted = Counted::new(Cursor::new(b"abc"));
+        counted.get_ref().fail(Some(ErrorKind::Interrupted));
+        counted.get_ref().fail(Some(ErrorKind::Interrupted));
+        assert!(counted.read(&mut [0; 3]).is_err());
+        assert!(counted.write(b"abc").is_err());
+        assert_eq!((counted.read_count(), counted.write_count()), (0, 0));
+    }
+}
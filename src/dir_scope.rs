@@ -0,0 +1,91 @@
+use core::ops::{Deref, DerefMut};
+
+use {Fs, OpenOptions, PathBuf};
+
+/// A directory whose path has been resolved once, for a burst of operations
+/// relative to it.
+///
+/// This struct is created by [`Fs::scoped`], which borrows the filesystem
+/// shared, and [`Fs::scoped_mut`], which borrows it exclusively and thus also
+/// allows modifications like [`remove_file_at`]. Paths given to its methods
+/// are joined to the canonical path of the directory, like with `openat` and
+/// friends, and the joined path is passed to the filesystem. An absolute path
+/// is used as is.
+///
+/// This is a path-joining helper, not a directory handle: changing the
+/// symbolic links which led to the directory doesn't affect the scope, but
+/// renaming the directory itself in between isn't noticed.
+///
+/// `B` is the borrow of the filesystem `F`, either `&F` or `&mut F`.
+///
+/// [`Fs::scoped`]: trait.Fs.html#method.scoped
+/// [`Fs::scoped_mut`]: trait.Fs.html#method.scoped_mut
+/// [`remove_file_at`]: #method.remove_file_at
+pub struct DirScope<F: ?Sized + Fs, B> {
+    fs: B,
+    dir: F::PathOwned,
+}
+
+impl<F: ?Sized + Fs, B: Deref<Target = F>> DirScope<F, B> {
+    pub(crate) fn new(fs: B, dir: F::PathOwned) -> Self {
+        DirScope { fs, dir }
+    }
+
+    /// Returns the canonical path of the directory all paths are relative to.
+    pub fn path(&self) -> &F::Path {
+        self.dir.as_path()
+    }
+
+    /// Returns `path` joined to the directory.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the joined path doesn't fit in
+    /// an owned path.
+    pub fn path_at(&self, path: &F::Path) -> Result<F::PathOwned, F::Error> {
+        let mut joined = F::PathOwned::from_path(self.dir.as_path())?;
+        joined.push(path)?;
+        Ok(joined)
+    }
+
+    /// Opens a file relative to the directory.
+    ///
+    /// # Errors
+    ///
+    /// See [`Fs::open`].
+    ///
+    /// [`Fs::open`]: trait.Fs.html#method.open
+    pub fn open_at(
+        &self,
+        path: &F::Path,
+        options: &OpenOptions<F::Permissions>,
+    ) -> Result<F::File, F::Error> {
+        self.fs.open(self.path_at(path)?.as_path(), options)
+    }
+
+    /// Queries the metadata of a file relative to the directory, following
+    /// symbolic links.
+    ///
+    /// # Errors
+    ///
+    /// See [`Fs::metadata`].
+    ///
+    /// [`Fs::metadata`]: trait.Fs.html#method.metadata
+    pub fn metadata_at(&self, path: &F::Path) -> Result<F::Metadata, F::Error> {
+        self.fs.metadata(self.path_at(path)?.as_path())
+    }
+}
+
+impl<F: ?Sized + Fs, B: DerefMut<Target = F>> DirScope<F, B> {
+    /// Removes a file relative to the directory.
+    ///
+    /// # Errors
+    ///
+    /// See [`Fs::remove_file`].
+    ///
+    /// [`Fs::remove_file`]: trait.Fs.html#method.remove_file
+    pub fn remove_file_at(&mut self, path: &F::Path) -> Result<(), F::Error> {
+        let path = self.path_at(path)?;
+        self.fs.remove_file(path.as_path())
+    }
+}
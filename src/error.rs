@@ -0,0 +1,104 @@
+/// A list specifying general categories of filesystem errors.
+///
+/// This list is intended to grow over time and it is not recommended to
+/// exhaustively match against it.
+///
+/// It is used with the [`Error`] trait, which every error type of a
+/// filesystem has to implement.
+///
+/// [`Error`]: trait.Error.html
+#[derive(Copy, PartialEq, Eq, Clone, Debug, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// An entity was not found, often a file.
+    NotFound,
+    /// The operation lacked the necessary privileges to complete.
+    PermissionDenied,
+    /// An entity already exists, often a file.
+    AlreadyExists,
+    /// A filesystem object is, unexpectedly, not a directory.
+    NotADirectory,
+    /// The filesystem object is, unexpectedly, a directory.
+    IsADirectory,
+    /// A non-empty directory was specified where an empty directory was
+    /// expected.
+    DirectoryNotEmpty,
+    /// A parameter was incorrect.
+    InvalidInput,
+    /// Data not valid for the operation were encountered.
+    InvalidData,
+    /// An error returned when an operation could not be completed because an
+    /// "end of file" was reached prematurely.
+    UnexpectedEof,
+    /// An error returned when an operation could not be completed because a
+    /// call to `write` returned `Ok(0)`.
+    WriteZero,
+    /// This operation was interrupted.
+    ///
+    /// Interrupted operations can typically be retried.
+    Interrupted,
+    /// A loop of symbolic links was encountered, or too many of them had to be
+    /// followed.
+    FilesystemLoop,
+    /// The operation would have to move or link an entity across
+    /// filesystems or devices.
+    CrossesDevices,
+    /// This operation is unsupported on this filesystem.
+    Unsupported,
+    /// A quota limiting the resources available to the operation, like the
+    /// number of bytes transferred, was exceeded.
+    QuotaExceeded,
+    /// Any error not part of this list.
+    Other,
+}
+
+/// The set of all errors that can occur while operating on a filesystem.
+///
+/// Every error type used by this crate's traits has to implement this trait,
+/// which allows provided methods to inspect errors and to construct new ones
+/// from an [`ErrorKind`].
+///
+/// [`ErrorKind`]: enum.ErrorKind.html
+pub trait Error: From<ErrorKind> {
+    /// Returns the corresponding [`ErrorKind`] for this error.
+    ///
+    /// [`ErrorKind`]: enum.ErrorKind.html
+    fn kind(&self) -> ErrorKind;
+
+    /// Returns whether this error is of kind [`ErrorKind::Interrupted`], in
+    /// which case the operation which returned it can be retried.
+    ///
+    /// [`ErrorKind::Interrupted`]: enum.ErrorKind.html#variant.Interrupted
+    fn is_interrupted(&self) -> bool {
+        self.kind() == ErrorKind::Interrupted
+    }
+}
+
+impl Error for ErrorKind {
+    fn kind(&self) -> ErrorKind {
+        *self
+    }
+}
+
+/// An invalid combination of [`OpenOptions`].
+///
+/// It is returned by [`OpenOptions::validate`].
+///
+/// [`OpenOptions`]: struct.OpenOptions.html
+/// [`OpenOptions::validate`]: struct.OpenOptions.html#method.validate
+#[derive(Copy, PartialEq, Eq, Clone, Debug, Hash)]
+#[non_exhaustive]
+pub enum OpenOptionsError {
+    /// `create` was set without `write` or `append`.
+    CreateWithoutWrite,
+    /// `create_new` was set without `write` or `append`.
+    CreateNewWithoutWrite,
+    /// `truncate` was set without `write`.
+    TruncateWithoutWrite,
+}
+
+impl From<OpenOptionsError> for ErrorKind {
+    fn from(_: OpenOptionsError) -> Self {
+        ErrorKind::InvalidInput
+    }
+}
@@ -0,0 +1,94 @@
+use {Dir, DirEntry, DirPos, Error};
+
+/// An iterator over the entries of a directory which skips the `.` and `..`
+/// entries.
+///
+/// Some backends include these entries in their listings and some don't. This
+/// wrapper, returned by [`Fs::read_dir_no_dots`], makes sure they are never
+/// yielded, so recursive walks don't descend into the same directory or its
+/// parent again.
+///
+/// [`Fs::read_dir_no_dots`]: trait.Fs.html#method.read_dir_no_dots
+#[derive(Debug)]
+pub struct FilteredDir<D> {
+    inner: D,
+}
+
+impl<D> FilteredDir<D> {
+    /// Wraps an iterator over the entries of a directory.
+    pub fn new(inner: D) -> Self {
+        FilteredDir { inner }
+    }
+
+    /// Unwraps this `FilteredDir`, returning the wrapped iterator.
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<D, T, E> Iterator for FilteredDir<D>
+where
+    D: Iterator<Item = Result<T, E>>,
+    T: DirEntry,
+{
+    type Item = Result<T, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.find(|entry| match *entry {
+            Ok(ref entry) => {
+                let name = entry.file_name_bytes();
+                name != b"." && name != b".."
+            }
+            Err(_) => true,
+        })
+    }
+}
+
+impl<D, T, E> Dir<T, E> for FilteredDir<D>
+where
+    D: Dir<T, E>,
+    T: DirEntry,
+    E: Error,
+{
+    fn rewind(&mut self) -> Result<(), E> {
+        self.inner.rewind()
+    }
+
+    fn tell(&self) -> Result<DirPos, E> {
+        self.inner.tell()
+    }
+
+    fn seek(&mut self, pos: DirPos) -> Result<(), E> {
+        self.inner.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use mock::{MockDir, MockType};
+
+    fn names<D: Iterator<Item = Result<T, E>>, T: DirEntry, E>(
+        dir: D,
+    ) -> Vec<Vec<u8>> {
+        dir.map(|entry| entry.ok().unwrap().file_name_bytes().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn skips_dot_entries() {
+        let mut dir = FilteredDir::new(MockDir::new(&[
+            (b".", MockType::Dir),
+            (b"a", MockType::File),
+            (b"..", MockType::Dir),
+            (b".hidden", MockType::File),
+            (b"..b", MockType::Dir),
+        ]));
+        let expected = [b"a".to_vec(), b".hidden".to_vec(), b"..b".to_vec()];
+        assert_eq!(names(&mut dir), expected);
+        dir.rewind().unwrap();
+        assert_eq!(names(dir), expected);
+    }
+}
@@ -0,0 +1,148 @@
+use core::ops::{Deref, DerefMut};
+
+use Write;
+
+/// A guard which flushes a writer when it goes out of scope.
+///
+/// This struct is created by the [`flush_guard`] method on [`Write`]. The
+/// guard dereferences to the writer, so it can be used in its place.
+///
+/// `Drop` can't return errors, so flushes made by the guard are best-effort:
+/// an error is captured instead of propagated. Errors captured by
+/// [`flush_best_effort`] are kept in the guard until they are retrieved with
+/// [`take_error`], and [`close`] flushes one last time and returns the error
+/// instead of leaving it to the drop. The flush on drop itself has no one to
+/// report to, so its error is discarded. This is a middle ground between
+/// silently discarding every error and requiring every code path to flush
+/// explicitly.
+///
+/// [`flush_guard`]: trait.Write.html#method.flush_guard
+/// [`Write`]: trait.Write.html
+/// [`flush_best_effort`]: #method.flush_best_effort
+/// [`take_error`]: #method.take_error
+/// [`close`]: #method.close
+pub struct FlushGuard<'a, W: 'a + ?Sized + Write> {
+    writer: Option<&'a mut W>,
+    error: Option<W::Error>,
+}
+
+impl<'a, W: ?Sized + Write> FlushGuard<'a, W> {
+    pub(crate) fn new(writer: &'a mut W) -> Self {
+        FlushGuard {
+            writer: Some(writer),
+            error: None,
+        }
+    }
+
+    /// Flushes the writer, capturing an error instead of returning it.
+    ///
+    /// Returns `true` if the flush succeeded. An error replaces any error
+    /// captured earlier which wasn't taken yet.
+    pub fn flush_best_effort(&mut self) -> bool {
+        match self.writer_mut().flush() {
+            Ok(()) => true,
+            Err(err) => {
+                self.error = Some(err);
+                false
+            }
+        }
+    }
+
+    /// Takes the error captured by the last failed best-effort flush, if it
+    /// wasn't taken yet.
+    pub fn take_error(&mut self) -> Option<W::Error> {
+        self.error.take()
+    }
+
+    /// Flushes the writer and disarms the guard, so it doesn't flush again
+    /// when dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of this flush, or otherwise an error captured
+    /// earlier which wasn't taken yet.
+    pub fn close(mut self) -> Result<(), W::Error> {
+        let result = self.writer_mut().flush();
+        self.writer = None;
+        match self.error.take() {
+            Some(err) if result.is_ok() => Err(err),
+            _ => result,
+        }
+    }
+
+    fn writer_mut(&mut self) -> &mut W {
+        self.writer
+            .as_mut()
+            .expect("guard is only disarmed by close")
+    }
+}
+
+impl<'a, W: ?Sized + Write> Deref for FlushGuard<'a, W> {
+    type Target = W;
+
+    fn deref(&self) -> &W {
+        self.writer
+            .as_ref()
+            .expect("guard is only disarmed by close")
+    }
+}
+
+impl<'a, W: ?Sized + Write> DerefMut for FlushGuard<'a, W> {
+    fn deref_mut(&mut self) -> &mut W {
+        self.writer_mut()
+    }
+}
+
+impl<'a, W: ?Sized + Write> Drop for FlushGuard<'a, W> {
+    fn drop(&mut self) {
+        if let Some(ref mut writer) = self.writer {
+            let _ = writer.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mock::Cursor;
+    use {ErrorKind, Write};
+
+    #[test]
+    fn captures_flush_error() {
+        let mut writer = Cursor::new(b"");
+        let mut guard = writer.flush_guard();
+        guard.write_all(b"data").unwrap();
+        guard.fail(Some(ErrorKind::Other));
+        assert!(!guard.flush_best_effort());
+        assert_eq!(guard.take_error(), Some(ErrorKind::Other));
+        assert_eq!(guard.take_error(), None);
+        assert!(guard.flush_best_effort());
+        assert_eq!(guard.take_error(), None);
+
+        // the error of the flush on drop is discarded
+        guard.fail(Some(ErrorKind::Other));
+        drop(guard);
+        assert_eq!(writer.flushes, 3);
+        assert_eq!(writer.data(), b"data");
+    }
+
+    #[test]
+    fn close_returns_error_and_disarms() {
+        let mut writer = Cursor::new(b"");
+        let guard = writer.flush_guard();
+        assert_eq!(guard.close(), Ok(()));
+        assert_eq!(writer.flushes, 1);
+
+        let guard = writer.flush_guard();
+        guard.fail(Some(ErrorKind::Other));
+        assert_eq!(guard.close(), Err(ErrorKind::Other));
+
+        let mut guard = writer.flush_guard();
+        guard.fail(Some(ErrorKind::WriteZero));
+        guard.flush_best_effort();
+        assert_eq!(guard.close(), Err(ErrorKind::WriteZero));
+        assert_eq!(writer.flushes, 4);
+
+        let _ = writer.flush_guard();
+        assert_eq!(writer.flushes, 5);
+    }
+}
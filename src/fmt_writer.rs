@@ -0,0 +1,87 @@
+use core::fmt;
+
+use Write;
+
+/// An adapter which implements `core::fmt::Write` for a byte [`Write`]r.
+///
+/// This struct is created by the [`as_fmt_write`] method on [`Write`]. Strings
+/// are written as UTF-8 with [`write_all`].
+///
+/// `fmt::Error` can't carry any information, so the first error returned by
+/// the writer is stored in the adapter instead, where it can be taken once
+/// formatting failed. After an error all further writes fail without touching
+/// the writer.
+///
+/// [`Write`]: trait.Write.html
+/// [`as_fmt_write`]: trait.Write.html#method.as_fmt_write
+/// [`write_all`]: trait.Write.html#method.write_all
+pub struct FmtWriter<'a, W: 'a + ?Sized + Write> {
+    writer: &'a mut W,
+    error: Option<W::Error>,
+}
+
+impl<'a, W: ?Sized + Write> FmtWriter<'a, W> {
+    pub(crate) fn new(writer: &'a mut W) -> Self {
+        FmtWriter {
+            writer,
+            error: None,
+        }
+    }
+
+    /// Returns the error which made formatting fail, if any.
+    pub fn error(&self) -> Option<&W::Error> {
+        self.error.as_ref()
+    }
+
+    /// Takes the error which made formatting fail, if any, so the adapter can
+    /// be used again.
+    pub fn take_error(&mut self) -> Option<W::Error> {
+        self.error.take()
+    }
+}
+
+impl<'a, W: ?Sized + Write> fmt::Write for FmtWriter<'a, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.error.is_some() {
+            return Err(fmt::Error);
+        }
+        self.writer.write_all(s.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::fmt::Write as _;
+
+    use mock::Cursor;
+    use {ErrorKind, Write};
+
+    #[test]
+    fn writes_formatted_text() {
+        let mut writer = Cursor::new(b"").chunked(3);
+        let mut adapter = writer.as_fmt_write();
+        let (name, id) = ("id", 42);
+        write!(adapter, "{}-{:04}", name, id).unwrap();
+        assert!(adapter.error().is_none());
+        assert_eq!(writer.data(), b"id-0042");
+    }
+
+    #[test]
+    fn stores_first_error() {
+        let mut writer = Cursor::new(b"");
+        writer.fail(Some(ErrorKind::WriteZero));
+        let mut adapter = writer.as_fmt_write();
+        assert!(write!(adapter, "{}", 1).is_err());
+        assert_eq!(adapter.error(), Some(&ErrorKind::WriteZero));
+
+        // later writes fail without reaching the writer
+        assert!(adapter.write_str("more").is_err());
+        assert_eq!(adapter.take_error(), Some(ErrorKind::WriteZero));
+        adapter.write_str("ok").unwrap();
+        assert_eq!(writer.writes, 2);
+        assert_eq!(writer.data(), b"ok");
+    }
+}
@@ -0,0 +1,214 @@
+use core::cell::RefCell;
+
+use {ErrorType, Read, Write};
+
+/// An incremental digest over a stream of bytes, such as a CRC or a
+/// cryptographic hash.
+///
+/// This trait is used by [`HashingReader`] and [`HashingWriter`] and is meant
+/// to be implemented by thin adapters around the hash implementation of
+/// choice.
+///
+/// [`HashingReader`]: struct.HashingReader.html
+/// [`HashingWriter`]: struct.HashingWriter.html
+pub trait Hasher {
+    /// The type of the final digest, usually a fixed-size byte array.
+    type Output;
+
+    /// Feeds `data` into the digest.
+    fn update(&mut self, data: &[u8]);
+
+    /// Consumes the hasher, returning the digest of all data fed into it.
+    fn finish(self) -> Self::Output;
+}
+
+/// A reader which feeds all bytes read through it into a [`Hasher`].
+///
+/// This allows computing a digest while streaming data, without a second pass
+/// over it. Only the bytes actually returned by successful reads are hashed.
+///
+/// [`Hasher`]: trait.Hasher.html
+#[derive(Debug)]
+pub struct HashingReader<R, H> {
+    inner: R,
+    hasher: RefCell<H>,
+}
+
+impl<R: Read, H: Hasher> HashingReader<R, H> {
+    /// Wraps `inner`, feeding everything read from it into `hasher`.
+    pub fn new(inner: R, hasher: H) -> Self {
+        HashingReader {
+            inner,
+            hasher: RefCell::new(hasher),
+        }
+    }
+
+    /// Gets a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Consumes this `HashingReader`, returning the digest of all bytes read
+    /// so far.
+    pub fn into_digest(self) -> H::Output {
+        self.hasher.into_inner().finish()
+    }
+
+    /// Unwraps this `HashingReader`, returning the wrapped reader and the
+    /// hasher.
+    pub fn into_inner(self) -> (R, H) {
+        (self.inner, self.hasher.into_inner())
+    }
+}
+
+impl<R: ErrorType, H> ErrorType for HashingReader<R, H> {
+    type Error = R::Error;
+}
+
+impl<R: Read, H: Hasher> Read for HashingReader<R, H> {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = self.inner.read(buf)?;
+        self.hasher.borrow_mut().update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// A writer which feeds all bytes written through it into a [`Hasher`].
+///
+/// This allows computing a checksum, e.g. for a trailing integrity footer,
+/// while writing data, without a second pass over it. Only the bytes actually
+/// accepted by successful writes are hashed.
+///
+/// [`Hasher`]: trait.Hasher.html
+#[derive(Debug)]
+pub struct HashingWriter<W, H> {
+    inner: W,
+    hasher: H,
+}
+
+impl<W: Write, H: Hasher> HashingWriter<W, H> {
+    /// Wraps `inner`, feeding everything written to it into `hasher`.
+    pub fn new(inner: W, hasher: H) -> Self {
+        HashingWriter { inner, hasher }
+    }
+
+    /// Gets a reference to the wrapped writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the wrapped writer.
+    ///
+    /// Bytes written directly to the wrapped writer are not hashed.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Returns the digest of all bytes written so far, without affecting
+    /// later writes.
+    pub fn digest(&self) -> H::Output
+    where
+        H: Clone,
+    {
+        self.hasher.clone().finish()
+    }
+
+    /// Consumes this `HashingWriter`, returning the digest of all bytes
+    /// written so far.
+    pub fn into_digest(self) -> H::Output {
+        self.hasher.finish()
+    }
+
+    /// Unwraps this `HashingWriter`, returning the wrapped writer.
+    ///
+    /// Use [`digest`] before to retrieve the digest.
+    ///
+    /// [`digest`]: #method.digest
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: ErrorType, H> ErrorType for HashingWriter<W, H> {
+    type Error = W::Error;
+}
+
+impl<W: Write, H: Hasher> Write for HashingWriter<W, H> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+
+    fn is_flush_noop(&self) -> bool {
+        self.inner.is_flush_noop()
+    }
+
+    fn reserve(&mut self, additional: usize) -> Result<(), Self::Error> {
+        self.inner.reserve(additional)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock::Cursor;
+    use ErrorKind;
+
+    /// Sums all bytes, wrapping around.
+    #[derive(Debug, Clone, Default)]
+    struct Sum(u8);
+
+    impl Hasher for Sum {
+        type Output = u8;
+
+        fn update(&mut self, data: &[u8]) {
+            for &b in data {
+                self.0 = self.0.wrapping_add(b);
+            }
+        }
+
+        fn finish(self) -> u8 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn reader_digests_bytes_read() {
+        let reader = HashingReader::new(Cursor::new(&[1, 2, 3, 250]), Sum(0));
+        let mut buf = [0; 3];
+        reader.read_exact(&mut buf).unwrap();
+        reader.read_exact(&mut buf[..1]).unwrap();
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        assert_eq!(reader.into_digest(), 0);
+
+        let reader = HashingReader::new(Cursor::new(b"\x10\x20\x30"), Sum(0));
+        reader.read_exact(&mut [0; 2]).unwrap();
+        assert_eq!(reader.into_digest(), 0x30);
+    }
+
+    #[test]
+    fn writer_digests_bytes_accepted() {
+        let data = b"container payload";
+        let expected = data.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+        let mut writer =
+            HashingWriter::new(Cursor::new(b"").chunked(4), Sum(0));
+        assert_eq!(writer.write(data).unwrap(), 4);
+        assert_eq!(
+            writer.digest(),
+            b"cont".iter().fold(0, |s: u8, &b| s.wrapping_add(b))
+        );
+        writer.write_all(&data[4..]).unwrap();
+        assert_eq!(writer.digest(), expected);
+
+        // failed writes are not hashed
+        writer.get_ref().fail(Some(ErrorKind::Other));
+        assert_eq!(writer.write(b"lost"), Err(ErrorKind::Other));
+        assert_eq!(writer.digest(), expected);
+        assert_eq!(writer.into_inner().data(), data);
+    }
+}
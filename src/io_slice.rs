@@ -0,0 +1,50 @@
+use core::ops::{Deref, DerefMut};
+
+/// A buffer to be written by vectored I/O.
+///
+/// It is a thin wrapper around a byte slice, mirroring `std::io::IoSlice`.
+#[derive(Copy, Clone, Debug)]
+pub struct IoSlice<'a>(&'a [u8]);
+
+impl<'a> IoSlice<'a> {
+    /// Wraps a byte slice.
+    pub fn new(buf: &'a [u8]) -> Self {
+        IoSlice(buf)
+    }
+}
+
+impl<'a> Deref for IoSlice<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.0
+    }
+}
+
+/// A buffer to be filled by vectored I/O.
+///
+/// It is a thin wrapper around a mutable byte slice, mirroring
+/// `std::io::IoSliceMut`.
+#[derive(Debug)]
+pub struct IoSliceMut<'a>(&'a mut [u8]);
+
+impl<'a> IoSliceMut<'a> {
+    /// Wraps a mutable byte slice.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        IoSliceMut(buf)
+    }
+}
+
+impl<'a> Deref for IoSliceMut<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.0
+    }
+}
+
+impl<'a> DerefMut for IoSliceMut<'a> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.0
+    }
+}
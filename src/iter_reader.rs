@@ -0,0 +1,127 @@
+use core::cell::{Cell, RefCell};
+
+use {Error, ErrorType, Read};
+
+/// A reader which pulls its bytes from an iterator of chunks.
+///
+/// This bridges generator-style producers, like decoders, to code expecting a
+/// [`Read`]. Each item of the iterator is either a chunk of bytes, anything
+/// implementing `AsRef<[u8]>`, or an error which is returned from [`read`].
+/// A chunk which doesn't fit into the buffer passed to [`read`] is kept and
+/// its remaining bytes are returned by the following reads. Empty chunks are
+/// skipped, the reader reaches "end of file" once the iterator is exhausted.
+///
+/// [`Read`]: trait.Read.html
+/// [`read`]: trait.Read.html#method.read
+#[derive(Debug)]
+pub struct IterReader<I, C> {
+    iter: RefCell<I>,
+    chunk: RefCell<Option<C>>,
+    pos: Cell<usize>,
+}
+
+impl<I, C, E> IterReader<I, C>
+where
+    I: Iterator<Item = Result<C, E>>,
+    C: AsRef<[u8]>,
+{
+    /// Creates a reader over the chunks produced by `iter`.
+    pub fn new(iter: I) -> Self {
+        IterReader {
+            iter: RefCell::new(iter),
+            chunk: RefCell::new(None),
+            pos: Cell::new(0),
+        }
+    }
+
+    /// Unwraps this `IterReader`, returning the iterator.
+    ///
+    /// The unread part of a partially consumed chunk is lost.
+    pub fn into_inner(self) -> I {
+        self.iter.into_inner()
+    }
+}
+
+impl<I, C, E> ErrorType for IterReader<I, C>
+where
+    I: Iterator<Item = Result<C, E>>,
+    E: Error,
+{
+    type Error = E;
+}
+
+impl<I, C, E> Read for IterReader<I, C>
+where
+    I: Iterator<Item = Result<C, E>>,
+    C: AsRef<[u8]>,
+    E: Error,
+{
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let mut chunk = self.chunk.borrow_mut();
+        loop {
+            if let Some(ref chunk) = *chunk {
+                let rest = &chunk.as_ref()[self.pos.get()..];
+                if !rest.is_empty() {
+                    let n = rest.len().min(buf.len());
+                    buf[..n].copy_from_slice(&rest[..n]);
+                    self.pos.set(self.pos.get() + n);
+                    return Ok(n);
+                }
+            }
+            *chunk = None;
+            match self.iter.borrow_mut().next() {
+                Some(Ok(next)) => {
+                    *chunk = Some(next);
+                    self.pos.set(0);
+                }
+                Some(Err(err)) => return Err(err),
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use ErrorKind;
+
+    #[test]
+    fn reassembles_chunks() {
+        let chunks: Vec<Result<Vec<u8>, ErrorKind>> = vec![
+            Ok(b"hello".to_vec()),
+            Ok(Vec::new()),
+            Ok(b" ".to_vec()),
+            Ok(b"chunked world".to_vec()),
+        ];
+        let reader = IterReader::new(chunks.into_iter());
+        let mut out = Vec::new();
+        let mut buf = [0; 3];
+        loop {
+            match reader.read(&mut buf).unwrap() {
+                0 => break,
+                n => out.extend_from_slice(&buf[..n]),
+            }
+        }
+        assert_eq!(out, b"hello chunked world");
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn returns_errors_in_order() {
+        let chunks: [Result<&[u8], ErrorKind>; 3] =
+            [Ok(b"ab"), Err(ErrorKind::InvalidData), Ok(b"c")];
+        let reader = IterReader::new(chunks.iter().cloned());
+        let mut buf = [0; 4];
+        assert_eq!(reader.read(&mut buf), Ok(2));
+        assert_eq!(reader.read(&mut buf), Err(ErrorKind::InvalidData));
+        assert_eq!(reader.read(&mut buf), Ok(1));
+        assert_eq!(buf[0], b'c');
+    }
+}
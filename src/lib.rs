@@ -12,6 +12,104 @@
 #![no_std]
 #![deny(missing_docs)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(test)]
+#[macro_use]
+extern crate std;
+
+#[cfg(feature = "async")]
+mod async_io;
+#[cfg(feature = "alloc")]
+mod buffered;
+#[cfg(feature = "alloc")]
+mod cache_fs;
+mod cached;
+mod counted;
+mod dir_scope;
+mod error;
+mod filtered_dir;
+mod flush_guard;
+mod fmt_writer;
+mod hashing;
+mod io_slice;
+mod iter_reader;
+#[cfg(feature = "alloc")]
+pub mod mem;
+#[cfg(test)]
+mod mock;
+#[cfg(feature = "alloc")]
+mod mount;
+mod observe;
+pub mod open_flags;
+#[cfg(feature = "alloc")]
+mod overlay;
+mod path;
+mod quota;
+mod raw;
+mod read_only;
+mod records;
+mod scoped;
+mod sink;
+mod take;
+mod tee;
+mod text;
+mod time;
+mod tracked;
+#[cfg(feature = "alloc")]
+mod tree;
+mod umask;
+#[cfg(feature = "alloc")]
+mod walk;
+
+#[cfg(feature = "async")]
+pub use async_io::{AsyncFile, AsyncRead, AsyncSeek, AsyncWrite};
+#[cfg(feature = "alloc")]
+pub use buffered::{BufReader, LineWriter};
+#[cfg(feature = "alloc")]
+pub use cache_fs::CacheFs;
+pub use cached::CachedDirEntry;
+pub use counted::Counted;
+pub use dir_scope::DirScope;
+pub use error::{Error, ErrorKind, OpenOptionsError};
+pub use filtered_dir::FilteredDir;
+pub use flush_guard::FlushGuard;
+pub use fmt_writer::FmtWriter;
+pub use hashing::{Hasher, HashingReader, HashingWriter};
+pub use io_slice::{IoSlice, IoSliceMut};
+pub use iter_reader::IterReader;
+#[cfg(feature = "alloc")]
+pub use mount::MountInfo;
+pub use observe::{FsEvent, FsOp, FsStage, ObserveFs};
+#[cfg(feature = "alloc")]
+pub use overlay::{OverlayDir, OverlayDirEntry, OverlayFile, OverlayFs};
+pub use path::{Component, Components, Path, PathBuf};
+pub use quota::{Quota, QuotaReader, QuotaWriter};
+pub use raw::{AsRawHandle, FromRawHandle, IntoRawHandle};
+pub use read_only::ReadOnlyFs;
+pub use records::Records;
+pub use scoped::{ScopedDir, ScopedDirEntry, ScopedFs};
+pub use sink::Sink;
+pub use take::Take;
+pub use tee::{ReadTee, Tee};
+pub use text::TextReader;
+pub use time::{FileTimes, SystemTime};
+pub use tracked::Tracked;
+#[cfg(feature = "alloc")]
+pub use tree::TreeNode;
+pub use umask::UmaskFs;
+#[cfg(feature = "alloc")]
+pub use walk::{FilterEntry, WalkDir};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::cmp;
+#[cfg(feature = "alloc")]
+use core::convert::TryFrom;
+use core::iter;
+use core::mem::MaybeUninit;
+use core::ops::Add;
+
 /// Enumeration of possible methods to seek within an I/O object.
 ///
 /// It is used by the [`Seek`] trait.
@@ -37,6 +135,59 @@ pub enum SeekFrom {
     Current(i64),
 }
 
+impl SeekFrom {
+    /// Creates a seek to the absolute position `pos`.
+    pub fn from_start(pos: u64) -> Self {
+        SeekFrom::Start(pos)
+    }
+
+    /// Creates a seek to `offset` bytes relative to the end of the object.
+    pub fn from_end(offset: i64) -> Self {
+        SeekFrom::End(offset)
+    }
+
+    /// Creates a seek to `offset` bytes relative to the current position.
+    pub fn from_current(offset: i64) -> Self {
+        SeekFrom::Current(offset)
+    }
+
+    /// Returns the offset of this seek, regardless of what it is relative to.
+    ///
+    /// Positions of [`Start`] beyond `i64::MAX` saturate to `i64::MAX`.
+    ///
+    /// [`Start`]: #variant.Start
+    pub fn offset(self) -> i64 {
+        match self {
+            SeekFrom::Start(pos) => cmp::min(pos, i64::MAX as u64) as i64,
+            SeekFrom::End(offset) | SeekFrom::Current(offset) => offset,
+        }
+    }
+}
+
+/// Shifts the target of a seek by a number of bytes.
+///
+/// The offset saturates at the bounds of its type, so a [`Start`] position
+/// never drops below 0.
+///
+/// [`Start`]: enum.SeekFrom.html#variant.Start
+impl Add<i64> for SeekFrom {
+    type Output = SeekFrom;
+
+    fn add(self, delta: i64) -> SeekFrom {
+        match self {
+            SeekFrom::Start(pos) => {
+                SeekFrom::Start(pos.saturating_add_signed(delta))
+            }
+            SeekFrom::End(offset) => {
+                SeekFrom::End(offset.saturating_add(delta))
+            }
+            SeekFrom::Current(offset) => {
+                SeekFrom::Current(offset.saturating_add(delta))
+            }
+        }
+    }
+}
+
 /// Options and flags which can be used to configure how a file is opened.
 ///
 /// This builder exposes the ability to configure how a [`File`] is opened and
@@ -121,9 +272,9 @@ impl<Permissions: Default> OpenOptions<Permissions> {
     /// This function doesn't create the file if it doesn't exist. Use the
     /// [`create`] method to do so.
     ///
-    /// [`write()`]: trait.File.html#method.write
-    /// [`flush()`]: trait.File.html#method.flush
-    /// [`seek`]: trait.File.html#method.seek
+    /// [`write()`]: trait.Write.html#method.write
+    /// [`flush()`]: trait.Write.html#method.flush
+    /// [`seek`]: trait.Seek.html#method.seek
     /// [`SeekFrom`]: enum.SeekFrom.html
     /// [`Current`]: enum.SeekFrom.html#variant.Current
     /// [`create`]: trait.Fs.html#method.create
@@ -192,6 +343,37 @@ impl<Permissions: Default> OpenOptions<Permissions> {
         self.flags = flags;
         self
     }
+
+    /// Checks that the combination of options is valid.
+    ///
+    /// Backends can call this at the top of [`open`] instead of checking the
+    /// invariants documented on the individual options themselves. Options
+    /// that are ignored, like `create` and `truncate` when `create_new` is
+    /// set, are not checked.
+    ///
+    /// [`open`]: trait.Fs.html#method.open
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`OpenOptionsError`] describing the first violated
+    /// invariant. It can be converted into an error of kind
+    /// [`ErrorKind::InvalidInput`].
+    ///
+    /// [`OpenOptionsError`]: enum.OpenOptionsError.html
+    /// [`ErrorKind::InvalidInput`]: enum.ErrorKind.html#variant.InvalidInput
+    pub fn validate(&self) -> Result<(), OpenOptionsError> {
+        let write = self.write || self.append;
+        if self.create_new {
+            if !write {
+                return Err(OpenOptionsError::CreateNewWithoutWrite);
+            }
+        } else if self.create && !write {
+            return Err(OpenOptionsError::CreateWithoutWrite);
+        } else if self.truncate && !self.write {
+            return Err(OpenOptionsError::TruncateWithoutWrite);
+        }
+        Ok(())
+    }
 }
 
 /// A builder used to create directories in various manners.
@@ -220,6 +402,11 @@ impl<Permissions: Default> DirOptions<Permissions> {
     }
 
     /// Sets the mode to create new directories with.
+    ///
+    /// The mode is applied as part of creating each directory, see
+    /// [`Fs::create_dir`].
+    ///
+    /// [`Fs::create_dir`]: trait.Fs.html#method.create_dir
     pub fn mode(&mut self, mode: Permissions) -> &mut Self {
         self.mode = mode;
         self
@@ -240,10 +427,10 @@ impl<Permissions: Default> DirOptions<Permissions> {
 pub trait Fs {
     /// The borrowed path slice that represents a relative or absolute path on
     /// the filesystem.
-    type Path: ?Sized;
+    type Path: ?Sized + Path;
     /// The owned path that represents a relative or absolute path on
     /// the filesystem.
-    type PathOwned;
+    type PathOwned: PathBuf<Self::Path>;
     /// The type that represents a file on the filesystem.
     type File: File<Error = Self::Error>;
     /// The type that represents a directory on the filesystem.
@@ -255,13 +442,13 @@ pub trait Fs {
         Error = Self::Error,
     >;
     /// The type that represents the metadata on the filesystem.
-    type Metadata;
+    type Metadata: Metadata;
     /// The type that represents the permissions of a reader/writer on the
     /// filesystem.
-    type Permissions;
+    type Permissions: Default;
     /// The type that represents the set of all errors that can occur during
     /// reading or writing.
-    type Error;
+    type Error: Error;
 
     /// Opens a file at `path` with the options specified by `options`.
     ///
@@ -275,6 +462,186 @@ pub trait Fs {
         options: &OpenOptions<Self::Permissions>,
     ) -> Result<Self::File, Self::Error>;
 
+    /// Opens a file like [`open`], retrying while it fails with an error of
+    /// kind [`ErrorKind::Interrupted`].
+    ///
+    /// Backends built on system calls can fail with `EINTR` when a signal
+    /// arrives, which callers are expected to just retry. The call is
+    /// retried at most [`MAX_INTERRUPTED_RETRIES`] times.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the first error returned by [`open`] which
+    /// isn't of kind [`ErrorKind::Interrupted`], or the last one if the
+    /// retries are exhausted.
+    ///
+    /// [`open`]: #method.open
+    /// [`ErrorKind::Interrupted`]: enum.ErrorKind.html#variant.Interrupted
+    /// [`MAX_INTERRUPTED_RETRIES`]: constant.MAX_INTERRUPTED_RETRIES.html
+    fn open_retry(
+        &self,
+        path: &Self::Path,
+        options: &OpenOptions<Self::Permissions>,
+    ) -> Result<Self::File, Self::Error> {
+        let mut retries = 0;
+        loop {
+            match self.open(path, options) {
+                Err(ref err)
+                    if err.is_interrupted()
+                        && retries < MAX_INTERRUPTED_RETRIES =>
+                {
+                    retries += 1
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Opens a file at `path` with POSIX style `flags` and `mode`, like
+    /// `open(2)`.
+    ///
+    /// This eases porting C code which calls `open` directly. The expected
+    /// flag bits are defined in the [`open_flags`] module.
+    ///
+    /// The default implementation translates the flags into
+    /// [`OpenOptions`] and calls [`open`]. `O_CREAT` together with `O_EXCL`
+    /// maps to [`create_new`]. Bits which aren't defined in [`open_flags`]
+    /// are passed on with [`custom_flags`]. This round trip is only as
+    /// faithful as the backend's `open`, so backends which can hand the
+    /// arguments to `open(2)` or a similar native call should override this
+    /// function to pass `flags` and `mode` through unchanged.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * The access mode bits of `flags` are invalid, in which case the error
+    ///   is of kind [`ErrorKind::InvalidInput`].
+    /// * Any error returned by [`open`].
+    ///
+    /// [`open_flags`]: open_flags/index.html
+    /// [`OpenOptions`]: struct.OpenOptions.html
+    /// [`open`]: #method.open
+    /// [`create_new`]: struct.OpenOptions.html#method.create_new
+    /// [`custom_flags`]: struct.OpenOptions.html#method.custom_flags
+    /// [`ErrorKind::InvalidInput`]: enum.ErrorKind.html#variant.InvalidInput
+    fn open_raw(
+        &self,
+        path: &Self::Path,
+        flags: u32,
+        mode: u32,
+    ) -> Result<Self::File, Self::Error>
+    where
+        Self::Permissions: From<u32>,
+    {
+        use open_flags::*;
+
+        let mut options = OpenOptions::new();
+        match flags & O_ACCMODE {
+            O_RDONLY => options.read(true),
+            O_WRONLY => options.write(true),
+            O_RDWR => options.read(true).write(true),
+            _ => return Err(ErrorKind::InvalidInput.into()),
+        };
+        let known = O_ACCMODE | O_CREAT | O_EXCL | O_TRUNC | O_APPEND;
+        options
+            .append(flags & O_APPEND != 0)
+            .truncate(flags & O_TRUNC != 0)
+            .create(flags & O_CREAT != 0)
+            .create_new(flags & O_CREAT != 0 && flags & O_EXCL != 0)
+            .mode(mode.into())
+            .custom_flags(flags & !known);
+        self.open(path, &options)
+    }
+
+    /// Creates an unnamed file in the directory `dir`, like `O_TMPFILE`.
+    ///
+    /// The returned file behaves like any other open file, but has no entry in
+    /// any directory and is reclaimed automatically once it is dropped. This
+    /// makes it suitable for temporary storage which shouldn't be visible to
+    /// other users of the filesystem. The options must allow writing, the
+    /// create options are implied.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * `dir` does not exist or is not a directory.
+    /// * `options` don't allow writing.
+    /// * The user lacks permissions to create a file in `dir`.
+    ///
+    /// The default implementation returns an error of kind
+    /// [`ErrorKind::Unsupported`].
+    ///
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    fn open_anonymous(
+        &mut self,
+        dir: &Self::Path,
+        options: &OpenOptions<Self::Permissions>,
+    ) -> Result<Self::File, Self::Error> {
+        let _ = (dir, options);
+        Err(ErrorKind::Unsupported.into())
+    }
+
+    /// Reads the whole contents of a file which is expected to be exactly `N`
+    /// bytes long.
+    ///
+    /// This is useful for loading fixed-size records, such as a boot sector,
+    /// where a truncated or oversized file indicates corrupt input.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * `path` does not exist or can't be opened for reading.
+    /// * The length of the file isn't `N` bytes, in which case the error is of
+    ///   kind [`ErrorKind::InvalidData`].
+    ///
+    /// [`ErrorKind::InvalidData`]: enum.ErrorKind.html#variant.InvalidData
+    fn read_exact_file<const N: usize>(
+        &self,
+        path: &Self::Path,
+    ) -> Result<[u8; N], Self::Error> {
+        if self.metadata(path)?.len() != N as u64 {
+            return Err(ErrorKind::InvalidData.into());
+        }
+        self.open(path, OpenOptions::new().read(true))?.read_array()
+    }
+
+    /// Reads `len` bytes of a file starting at `offset`.
+    ///
+    /// This is convenient for format readers which know the layout of a file
+    /// and only need a specific region of it. The file is read with
+    /// [`File::read_exact_at`].
+    ///
+    /// [`File::read_exact_at`]: trait.File.html#method.read_exact_at
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * `path` does not exist or can't be opened for reading.
+    /// * The range extends past the end of the file, in which case the error
+    ///   is of kind [`ErrorKind::UnexpectedEof`].
+    ///
+    /// [`ErrorKind::UnexpectedEof`]: enum.ErrorKind.html#variant.UnexpectedEof
+    #[cfg(feature = "alloc")]
+    fn read_range(
+        &self,
+        path: &Self::Path,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, Self::Error> {
+        let file = self.open(path, OpenOptions::new().read(true))?;
+        let mut buf = alloc::vec![0; len];
+        file.read_exact_at(&mut buf, offset)?;
+        Ok(buf)
+    }
+
     /// Removes a file from the filesystem.
     ///
     /// Note that there is no
@@ -290,6 +657,60 @@ pub trait Fs {
     /// * The user lacks permissions to remove the file.
     fn remove_file(&mut self, path: &Self::Path) -> Result<(), Self::Error>;
 
+    /// Removes a file from the filesystem if it exists, returning whether it
+    /// was removed.
+    ///
+    /// This is meant for idempotent cleanup. Instead of checking whether the
+    /// file exists first, which is racy, an error of kind
+    /// [`ErrorKind::NotFound`] from [`remove_file`] is treated as absence.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`remove_file`], except when `path` does not exist.
+    ///
+    /// [`remove_file`]: #method.remove_file
+    /// [`ErrorKind::NotFound`]: enum.ErrorKind.html#variant.NotFound
+    fn remove_file_if_exists(
+        &mut self,
+        path: &Self::Path,
+    ) -> Result<bool, Self::Error> {
+        match self.remove_file(path) {
+            Ok(()) => Ok(true),
+            Err(ref err) if err.kind() == ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Removes a file from the filesystem, returning the number of hard links
+    /// to it which remain.
+    ///
+    /// A return value of 0 means the last link was removed and the contents
+    /// are no longer referenced by any directory entry, which lets e.g. a
+    /// content-addressed store reclaim them. The count is taken from
+    /// [`UnixMetadataExt::nlink`] right before the file is removed, so it may
+    /// be outdated if links are created or removed concurrently.
+    ///
+    /// [`UnixMetadataExt::nlink`]: trait.UnixMetadataExt.html#method.nlink
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`remove_file`], or if querying the metadata of `path` fails.
+    ///
+    /// [`remove_file`]: #method.remove_file
+    fn remove_file_reporting_links(
+        &mut self,
+        path: &Self::Path,
+    ) -> Result<u64, Self::Error>
+    where
+        Self::Metadata: UnixMetadataExt,
+    {
+        let nlink = self.symlink_metadata(path)?.nlink();
+        self.remove_file(path)?;
+        Ok(nlink.saturating_sub(1))
+    }
+
     /// Given a path, query the file system to get information about a file,
     /// directory, etc.
     ///
@@ -322,6 +743,164 @@ pub trait Fs {
         path: &Self::Path,
     ) -> Result<Self::Metadata, Self::Error>;
 
+    /// Query the type of a file, directory, etc.
+    ///
+    /// This function will traverse symbolic links to query information about
+    /// the destination file. It is the analogue of [`DirEntry::file_type`] for
+    /// paths. The default implementation calls [`metadata`], backends which
+    /// can determine the type more cheaply than a full `stat` should override
+    /// it.
+    ///
+    /// [`DirEntry::file_type`]: trait.DirEntry.html#method.file_type
+    /// [`metadata`]: #method.metadata
+    ///
+    /// # Errors
+    ///
+    /// See [`metadata`].
+    fn file_type(
+        &self,
+        path: &Self::Path,
+    ) -> Result<<Self::Metadata as Metadata>::FileType, Self::Error> {
+        self.metadata(path).map(|metadata| metadata.file_type())
+    }
+
+    /// Query the type of a file without following symlinks.
+    ///
+    /// The default implementation calls [`symlink_metadata`], backends which
+    /// can determine the type more cheaply than a full `lstat` should
+    /// override it.
+    ///
+    /// [`symlink_metadata`]: #method.symlink_metadata
+    ///
+    /// # Errors
+    ///
+    /// See [`symlink_metadata`].
+    fn symlink_file_type(
+        &self,
+        path: &Self::Path,
+    ) -> Result<<Self::Metadata as Metadata>::FileType, Self::Error> {
+        self.symlink_metadata(path)
+            .map(|metadata| metadata.file_type())
+    }
+
+    /// Query the metadata about a file, following at most `max_depth` symbolic
+    /// links.
+    ///
+    /// This lies between [`metadata`], which follows any number of links, and
+    /// [`symlink_metadata`], which follows none. With a `max_depth` of 0 it is
+    /// equivalent to [`symlink_metadata`]. Relative link targets are resolved
+    /// against the directory containing the link. Only links in the final
+    /// component are counted, links in the parent directories are resolved by
+    /// the filesystem as usual.
+    ///
+    /// [`metadata`]: #method.metadata
+    /// [`symlink_metadata`]: #method.symlink_metadata
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`symlink_metadata`] and [`read_link`] for any link in the chain. An
+    /// error of kind [`ErrorKind::FilesystemLoop`] is returned if `max_depth`
+    /// is not 0 and the chain is longer than `max_depth` links.
+    ///
+    /// [`read_link`]: #method.read_link
+    /// [`ErrorKind::FilesystemLoop`]: enum.ErrorKind.html#variant.FilesystemLoop
+    fn metadata_follow(
+        &self,
+        path: &Self::Path,
+        max_depth: usize,
+    ) -> Result<Self::Metadata, Self::Error> {
+        let metadata = self.symlink_metadata(path)?;
+        if max_depth == 0 || !metadata.is_symlink() {
+            return Ok(metadata);
+        }
+
+        let mut current = Self::PathOwned::from_path(path)?;
+        for _ in 0..max_depth {
+            let target = self.read_link(current.as_path())?;
+            let parent = current
+                .as_path()
+                .parent()
+                .unwrap_or_else(|| Self::Path::from_bytes(&[]));
+            let mut next = Self::PathOwned::from_path(parent)?;
+            next.push(target.as_path())?;
+            let metadata = self.symlink_metadata(next.as_path())?;
+            if !metadata.is_symlink() {
+                return Ok(metadata);
+            }
+            current = next;
+        }
+        Err(ErrorKind::FilesystemLoop.into())
+    }
+
+    /// Query the metadata about a file, returning `None` if it doesn't exist.
+    ///
+    /// This function will traverse symbolic links to query information about
+    /// the destination file. Unlike [`is_file`] and friends, it only treats an
+    /// error of kind [`ErrorKind::NotFound`] as absence and propagates all
+    /// other errors.
+    ///
+    /// [`is_file`]: #method.is_file
+    /// [`ErrorKind::NotFound`]: enum.ErrorKind.html#variant.NotFound
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`metadata`], except when `path` does not exist.
+    ///
+    /// [`metadata`]: #method.metadata
+    fn metadata_opt(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Option<Self::Metadata>, Self::Error> {
+        match self.metadata(path) {
+            Ok(metadata) => Ok(Some(metadata)),
+            Err(ref err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns `true` if `path` points at an existing directory.
+    ///
+    /// This function will traverse symbolic links to query information about
+    /// the destination file. Any error, including `path` not existing, makes
+    /// this function return `false`.
+    fn is_dir(&self, path: &Self::Path) -> bool {
+        self.metadata(path).is_ok_and(|m| m.is_dir())
+    }
+
+    /// Returns `true` if `path` points at an existing regular file.
+    ///
+    /// This function will traverse symbolic links to query information about
+    /// the destination file. Any error, including `path` not existing, makes
+    /// this function return `false`.
+    fn is_file(&self, path: &Self::Path) -> bool {
+        self.metadata(path).is_ok_and(|m| m.is_file())
+    }
+
+    /// Returns the size of the file at `path`, in bytes.
+    ///
+    /// This function will traverse symbolic links, it is a shorthand for
+    /// calling [`len`] on the result of [`metadata`].
+    ///
+    /// # Errors
+    ///
+    /// See [`metadata`].
+    ///
+    /// [`len`]: trait.Metadata.html#method.len
+    /// [`metadata`]: #method.metadata
+    fn file_size(&self, path: &Self::Path) -> Result<u64, Self::Error> {
+        self.metadata(path).map(|metadata| metadata.len())
+    }
+
+    /// Returns `true` if `path` points at an existing symbolic link.
+    ///
+    /// This function does not traverse symbolic links. Any error, including
+    /// `path` not existing, makes this function return `false`.
+    fn is_symlink(&self, path: &Self::Path) -> bool {
+        self.symlink_metadata(path).is_ok_and(|m| m.is_symlink())
+    }
+
     /// Rename a file or directory to a new name, replacing the original file if
     /// `to` already exists.
     ///
@@ -341,6 +920,187 @@ pub trait Fs {
         to: &Self::Path,
     ) -> Result<(), Self::Error>;
 
+    /// Renames a file or directory like [`rename`], also handling names which
+    /// only differ in case on case-insensitive filesystems.
+    ///
+    /// On a case-insensitive filesystem, `Foo` and `foo` refer to the same
+    /// entry, so renaming one to the other may be treated as a no-op or may
+    /// even remove the entry. If `from` and `to` only differ in ASCII case,
+    /// this function renames `from` to a temporary name next to `to` first and
+    /// then to `to`, which changes the case of the entry on case-insensitive
+    /// filesystems and behaves like [`rename`] on case-sensitive ones. Any
+    /// other rename is forwarded to [`rename`] directly.
+    ///
+    /// The temporary name is derived from the name of `from`, e.g.
+    /// `.Foo.genfs-rename-case` for `Foo`, so concurrent case-only renames of
+    /// different entries in the same directory don't collide.
+    ///
+    /// Unlike [`rename`], a case-only rename is not atomic. If the second step
+    /// fails, the entry is moved back to `from` and the error of the second
+    /// step is returned. If moving it back fails as well, the entry is left at
+    /// the temporary name.
+    ///
+    /// [`rename`]: #method.rename
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`rename`]. An error of kind [`ErrorKind::AlreadyExists`] is returned
+    /// if the temporary name is already taken.
+    ///
+    /// [`ErrorKind::AlreadyExists`]: enum.ErrorKind.html#variant.AlreadyExists
+    fn rename_case(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        let (from_bytes, to_bytes) = (from.as_bytes(), to.as_bytes());
+        if from_bytes == to_bytes || !from_bytes.eq_ignore_ascii_case(to_bytes)
+        {
+            return self.rename(from, to);
+        }
+
+        let parent = to.parent().unwrap_or_else(|| Self::Path::from_bytes(&[]));
+        let name = match from.components().last() {
+            Some(Component::Normal(name)) => name,
+            _ => &[][..],
+        };
+        // long names are cut short so the temporary name stays a valid name
+        let mut buf = [0u8; RENAME_CASE_NAME_MAX];
+        let name =
+            &name[..name.len().min(buf.len() - RENAME_CASE_SUFFIX.len() - 1)];
+        let len = 1 + name.len() + RENAME_CASE_SUFFIX.len();
+        buf[0] = b'.';
+        buf[1..=name.len()].copy_from_slice(name);
+        buf[1 + name.len()..len].copy_from_slice(RENAME_CASE_SUFFIX);
+        let mut temp = Self::PathOwned::from_path(parent)?;
+        temp.push(Self::Path::from_bytes(&buf[..len]))?;
+        match self.symlink_metadata(temp.as_path()) {
+            Ok(_) => return Err(ErrorKind::AlreadyExists.into()),
+            Err(ref err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+
+        self.rename(from, temp.as_path())?;
+        if let Err(err) = self.rename(temp.as_path(), to) {
+            // the original error is more useful than one from the rollback
+            let _ = self.rename(temp.as_path(), from);
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Moves a file, falling back to copying it if it can't be renamed.
+    ///
+    /// This function first tries to [`rename`] `from` to `to`. If that fails
+    /// with an error of kind [`ErrorKind::CrossesDevices`], `from` is
+    /// [`copy`]'d to `to`, including its permission bits, and only removed
+    /// once the copy succeeded.
+    ///
+    /// Unlike a rename, the fallback is not atomic. If it fails half-way, `to`
+    /// may have been created or overwritten while `from` still exists.
+    ///
+    /// [`rename`]: #method.rename
+    /// [`copy`]: #method.copy
+    /// [`ErrorKind::CrossesDevices`]: enum.ErrorKind.html#variant.CrossesDevices
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`rename`] or, when falling back, [`copy`] and [`remove_file`].
+    ///
+    /// [`remove_file`]: #method.remove_file
+    fn move_file(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        match self.rename(from, to) {
+            Err(ref err) if err.kind() == ErrorKind::CrossesDevices => {}
+            result => return result,
+        }
+        self.copy(from, to)?;
+        self.remove_file(from)
+    }
+
+    /// Moves the directory `from` to `to`, merging it into `to` if that is an
+    /// existing directory.
+    ///
+    /// Unlike [`rename`], this also succeeds if `to` is a non-empty
+    /// directory, which is useful e.g. for installers. Conflicts are resolved
+    /// in favor of the source, entry by entry:
+    ///
+    /// * If `to` doesn't exist, `from` is renamed to `to`.
+    /// * If `to` exists but isn't a directory, it is removed and `from` is
+    ///   renamed to `to`.
+    /// * Otherwise each entry of `from` is moved into `to`. Directories which
+    ///   exist in both are merged recursively by the same rules. Any other
+    ///   entry of `from` replaces the entry with the same name in `to`, which
+    ///   is removed first if it is a directory. Entries which only exist in
+    ///   `to` are left alone. Finally the then empty `from` is removed.
+    ///
+    /// Symbolic links are never followed, a link to a directory is treated
+    /// like a file.
+    ///
+    /// The merge is not atomic. If it fails half-way, some entries may have
+    /// been moved already while others are still in `from`.
+    ///
+    /// [`rename`]: #method.rename
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * `from` is not a directory, in which case the error is of kind
+    ///   [`ErrorKind::NotADirectory`].
+    /// * Any error returned by [`rename`], [`read_dir`] or the functions
+    ///   used to remove replaced entries.
+    ///
+    /// [`ErrorKind::NotADirectory`]: enum.ErrorKind.html#variant.NotADirectory
+    /// [`read_dir`]: #method.read_dir
+    #[cfg(feature = "alloc")]
+    fn rename_dir_merge(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        if !self.symlink_metadata(from)?.is_dir() {
+            return Err(ErrorKind::NotADirectory.into());
+        }
+        match self.symlink_metadata(to) {
+            Ok(ref metadata) if metadata.is_dir() => {}
+            Ok(_) => {
+                self.remove_file(to)?;
+                return self.rename(from, to);
+            }
+            Err(ref err) if err.kind() == ErrorKind::NotFound => {
+                return self.rename(from, to)
+            }
+            Err(err) => return Err(err),
+        }
+
+        for entry in self.read_dir_owned(from)? {
+            let source = entry.path();
+            let mut target = Self::PathOwned::from_path(to)?;
+            target.push(entry.file_name())?;
+            if entry.file_type()?.is_dir() {
+                self.rename_dir_merge(source.as_path(), target.as_path())?;
+                continue;
+            }
+            match self.symlink_metadata(target.as_path()) {
+                Ok(ref metadata) if metadata.is_dir() => {
+                    self.remove_dir_all(target.as_path())?
+                }
+                Ok(_) => {}
+                Err(ref err) if err.kind() == ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+            self.rename(source.as_path(), target.as_path())?;
+        }
+        self.remove_dir(from)
+    }
+
     /// Copies the contents of one file to another. This function will also
     /// copy the permission bits of the original file to the destination file.
     ///
@@ -367,132 +1127,1310 @@ pub trait Fs {
         to: &Self::Path,
     ) -> Result<u64, Self::Error>;
 
-    /// Creates a new hard link on the filesystem.
+    /// Copies the contents of one file to another like [`copy`], calling
+    /// `progress` with the total number of bytes copied so far after every
+    /// chunk.
     ///
-    /// The `dst` path will be a link pointing to the `src` path. Note that
-    /// systems often require these two paths to both be located on the
-    /// same filesystem.
+    /// The data is copied in chunks of up to 4 KiB, so `progress` is called
+    /// roughly once per 4 KiB and its final argument is equal to the returned
+    /// total. It isn't called at all for an empty file. Like [`copy`], the
+    /// permission bits of `from` are copied to `to` once the data is.
     ///
-    /// # Errors
+    /// [`copy`]: #method.copy
     ///
-    /// This function will return an error in the following situations, but is
-    /// not limited to just these cases:
+    /// # Errors
     ///
-    /// * The `src` path is not a file or doesn't exist.
-    fn hard_link(
+    /// This function will return an error in the same situations as
+    /// [`copy`].
+    fn copy_with_progress<P>(
         &mut self,
-        src: &Self::Path,
-        dst: &Self::Path,
-    ) -> Result<(), Self::Error>;
+        from: &Self::Path,
+        to: &Self::Path,
+        progress: P,
+    ) -> Result<u64, Self::Error>
+    where
+        P: FnMut(u64),
+        Self::Metadata: UnixMetadataExt,
+        Self::Permissions: From<u32>,
+    {
+        let metadata = self.metadata(from)?;
+        if !metadata.is_file() {
+            return Err(ErrorKind::InvalidInput.into());
+        }
 
-    /// Creates a new symbolic link on the filesystem.
+        let src = self.open(from, OpenOptions::new().read(true))?;
+        let mut dst = self.open(
+            to,
+            OpenOptions::new().write(true).create(true).truncate(true),
+        )?;
+        let copied = copy_data_with_progress(&src, &mut dst, progress)?;
+        dst.flush()?;
+        self.set_permissions(to, metadata.mode().into())?;
+        Ok(copied)
+    }
+
+    /// Copies the contents of one file to another like [`copy`], then reads
+    /// both files back and verifies that they are equal.
     ///
-    /// The `dst` path will be a symbolic link pointing to the `src` path.
-    fn symlink(
+    /// This catches silent corruption, e.g. on flaky storage, at the cost of
+    /// reading the data twice more.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`copy`]. If the contents of the files differ after copying, an error
+    /// of kind [`ErrorKind::InvalidData`] is returned and `to` is left as it
+    /// is.
+    ///
+    /// [`copy`]: #method.copy
+    /// [`ErrorKind::InvalidData`]: enum.ErrorKind.html#variant.InvalidData
+    fn copy_verified(
         &mut self,
-        src: &Self::Path,
-        dst: &Self::Path,
-    ) -> Result<(), Self::Error>;
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<u64, Self::Error> {
+        let copied = self.copy(from, to)?;
+        let src = self.open(from, OpenOptions::new().read(true))?;
+        let dst = self.open(to, OpenOptions::new().read(true))?;
+        if compare_data(&src, &dst)? != Some(copied) {
+            return Err(ErrorKind::InvalidData.into());
+        }
+        Ok(copied)
+    }
 
-    /// Reads a symbolic link, returning the file that the link points to.
+    /// Creates a copy-on-write clone of the file `from` at `to`, like a
+    /// reflink on btrfs or XFS.
+    ///
+    /// The clone shares its data with the original until either of them is
+    /// modified, which makes it nearly instant and space-efficient. Like with
+    /// [`copy`], the contents of `to` are replaced if it exists. Modifying one
+    /// file afterwards never affects the other one.
     ///
     /// # Errors
     ///
     /// This function will return an error in the following situations, but is
     /// not limited to just these cases:
     ///
-    /// * `path` is not a symbolic link.
-    /// * `path` does not exist.
-    fn read_link(
-        &self,
-        path: &Self::Path,
-    ) -> Result<Self::PathOwned, Self::Error>;
-
-    /// Returns the canonical form of a path with all intermediate components
+    /// * The `from` path is not a file or doesn't exist.
+    /// * The filesystem can't share data between `from` and `to`.
+    /// * The current process does not have the permission rights to access
+    ///   `from` or write `to`.
+    ///
+    /// The default implementation returns an error of kind
+    /// [`ErrorKind::Unsupported`].
+    ///
+    /// [`copy`]: #method.copy
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    fn reflink(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        let _ = (from, to);
+        Err(ErrorKind::Unsupported.into())
+    }
+
+    /// Clones the file `from` at `to` with [`reflink`] if possible, and falls
+    /// back to [`copy`] otherwise.
+    ///
+    /// Only an error of kind [`ErrorKind::Unsupported`] from [`reflink`]
+    /// causes the fallback.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the first error returned by [`reflink`]
+    /// which isn't of kind [`ErrorKind::Unsupported`], or any error returned
+    /// by [`copy`].
+    ///
+    /// [`reflink`]: #method.reflink
+    /// [`copy`]: #method.copy
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    fn reflink_or_copy(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        match self.reflink(from, to) {
+            Err(ref err) if err.kind() == ErrorKind::Unsupported => {
+                self.copy(from, to).map(|_| ())
+            }
+            result => result,
+        }
+    }
+
+    /// Creates a new hard link on the filesystem.
+    ///
+    /// The `dst` path will be a link pointing to the `src` path. Note that
+    /// systems often require these two paths to both be located on the
+    /// same filesystem.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * The `src` path is not a file or doesn't exist.
+    fn hard_link(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error>;
+
+    /// Creates a new hard link on the filesystem, reporting whether a real
+    /// link was created.
+    ///
+    /// Some backends can't create hard links and emulate them by copying
+    /// `src` to `dst` instead. Later modifications to one of the paths are not
+    /// visible through the other one in this case, which matters e.g. for
+    /// deduplication tools and for accounting of used space.
+    ///
+    /// The default implementation calls [`hard_link`] and returns
+    /// [`LinkKind::True`]. Backends which emulate hard links have to override
+    /// it.
+    ///
+    /// # Errors
+    ///
+    /// See [`hard_link`].
+    ///
+    /// [`hard_link`]: #method.hard_link
+    /// [`LinkKind::True`]: enum.LinkKind.html#variant.True
+    fn hard_link_checked(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<LinkKind, Self::Error> {
+        self.hard_link(src, dst)?;
+        Ok(LinkKind::True)
+    }
+
+    /// Creates a new symbolic link on the filesystem.
+    ///
+    /// The `dst` path will be a symbolic link pointing to the `src` path.
+    ///
+    /// `src` is stored verbatim as the text of the link, it is neither
+    /// resolved nor made absolute, and [`read_link`] returns it unchanged. A
+    /// relative `src` is resolved against the directory containing the link
+    /// each time the link is followed, an absolute one against the root.
+    ///
+    /// The default implementation calls [`symlink_file`]. Use [`symlink_dir`]
+    /// for links to directories on platforms which tell the two apart.
+    ///
+    /// [`read_link`]: #method.read_link
+    /// [`symlink_file`]: #method.symlink_file
+    /// [`symlink_dir`]: #method.symlink_dir
+    fn symlink(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.symlink_file(src, dst)
+    }
+
+    /// Creates a new symbolic link with a relative target on the filesystem.
+    ///
+    /// The `link` path will be a symbolic link pointing to `target`, which is
+    /// interpreted relative to the directory containing the link. Relative
+    /// links keep working when the tree containing both the link and its
+    /// target is moved or copied as a whole. Like with [`symlink`], `target`
+    /// is stored verbatim and returned unchanged by [`read_link`].
+    ///
+    /// The default implementation checks `target` and calls [`symlink`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * `target` is absolute, in which case the error is of kind
+    ///   [`ErrorKind::InvalidInput`].
+    /// * Any error returned by [`symlink`].
+    ///
+    /// [`symlink`]: #method.symlink
+    /// [`read_link`]: #method.read_link
+    /// [`ErrorKind::InvalidInput`]: enum.ErrorKind.html#variant.InvalidInput
+    fn symlink_relative(
+        &mut self,
+        target: &Self::Path,
+        link: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        if target.is_absolute() {
+            return Err(ErrorKind::InvalidInput.into());
+        }
+        self.symlink(target, link)
+    }
+
+    /// Creates a new symbolic link to a file on the filesystem.
+    ///
+    /// The `dst` path will be a symbolic link pointing to the `src` path.
+    ///
+    /// Unix-like systems don't distinguish between symbolic links to files
+    /// and to directories, but Windows does: a link has to be created as
+    /// either kind and only resolves correctly to a target of the same kind.
+    /// Portable code that knows what the link will point to should use this
+    /// method or [`symlink_dir`] instead of [`symlink`]. Backends for such
+    /// platforms should override [`symlink_dir`] as well, backends for
+    /// Unix-like systems only need to implement this method.
+    ///
+    /// [`symlink`]: #method.symlink
+    /// [`symlink_dir`]: #method.symlink_dir
+    fn symlink_file(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error>;
+
+    /// Creates a new symbolic link to a directory on the filesystem.
+    ///
+    /// The `dst` path will be a symbolic link pointing to the `src` path. See
+    /// [`symlink_file`] for why the two kinds of links are distinguished.
+    ///
+    /// The default implementation calls [`symlink_file`].
+    ///
+    /// [`symlink_file`]: #method.symlink_file
+    fn symlink_dir(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.symlink_file(src, dst)
+    }
+
+    /// Creates a special file, like a named pipe or a device node, mirroring
+    /// `mknod`.
+    ///
+    /// `dev` is the device number of a character or block device and is
+    /// ignored for other kinds of files.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * `path` already exists.
+    /// * User lacks permissions to create a file at `path`.
+    ///
+    /// The default implementation returns an error of kind
+    /// [`ErrorKind::Unsupported`].
+    ///
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    fn mknod(
+        &mut self,
+        path: &Self::Path,
+        mode: Self::Permissions,
+        kind: SpecialKind,
+        dev: u64,
+    ) -> Result<(), Self::Error> {
+        let _ = (path, mode, kind, dev);
+        Err(ErrorKind::Unsupported.into())
+    }
+
+    /// Creates a named pipe, mirroring `mkfifo`.
+    ///
+    /// The default implementation calls [`mknod`] with
+    /// [`SpecialKind::Fifo`].
+    ///
+    /// [`mknod`]: #method.mknod
+    /// [`SpecialKind::Fifo`]: enum.SpecialKind.html#variant.Fifo
+    ///
+    /// # Errors
+    ///
+    /// See [`mknod`].
+    fn mkfifo(
+        &mut self,
+        path: &Self::Path,
+        mode: Self::Permissions,
+    ) -> Result<(), Self::Error> {
+        self.mknod(path, mode, SpecialKind::Fifo, 0)
+    }
+
+    /// Reads a symbolic link, returning the file that the link points to.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * `path` is not a symbolic link.
+    /// * `path` does not exist.
+    fn read_link(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::PathOwned, Self::Error>;
+
+    /// Follows the symbolic link at `path` and any links it points to, one
+    /// step at a time, and returns the path of the first non-link it reaches.
+    ///
+    /// At most `max_hops` links are followed. Relative link targets are
+    /// resolved against the directory containing the link. Only the final
+    /// component of each path is inspected, intermediate components are
+    /// resolved by the filesystem as usual.
+    ///
+    /// This is a bounded alternative to [`canonicalize`] which is suitable
+    /// for untrusted input.
+    ///
+    /// [`canonicalize`]: #method.canonicalize
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * `path` or any link target does not exist.
+    /// * More than `max_hops` links were encountered, in which case the error
+    ///   is of kind [`ErrorKind::FilesystemLoop`].
+    ///
+    /// [`ErrorKind::FilesystemLoop`]: enum.ErrorKind.html#variant.FilesystemLoop
+    fn resolve_symlinks(
+        &self,
+        path: &Self::Path,
+        max_hops: usize,
+    ) -> Result<Self::PathOwned, Self::Error> {
+        let mut current = Self::PathOwned::from_path(path)?;
+        let mut hops = 0;
+        loop {
+            if !self.symlink_metadata(current.as_path())?.is_symlink() {
+                return Ok(current);
+            }
+            if hops == max_hops {
+                return Err(ErrorKind::FilesystemLoop.into());
+            }
+            hops += 1;
+
+            let target = self.read_link(current.as_path())?;
+            current = match current.as_path().parent() {
+                Some(parent) => {
+                    let mut next = Self::PathOwned::from_path(parent)?;
+                    next.push(target.as_path())?;
+                    next
+                }
+                None => target,
+            };
+        }
+    }
+
+    /// Returns the canonical form of a path with all intermediate components
     /// normalized and symbolic links resolved.
     ///
     /// # Errors
     ///
-    /// This function will return an error in the following situations, but is
-    /// not limited to just these cases:
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * `path` does not exist.
+    /// * A component in path is not a directory.
+    fn canonicalize(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::PathOwned, Self::Error>;
+
+    /// Writes the canonical form of a path into `out`, like [`canonicalize`].
+    ///
+    /// The previous contents of `out` are replaced. This lets callers reuse
+    /// one buffer for the result of many resolutions.
+    ///
+    /// The default implementation builds the result in `out` one component
+    /// at a time, checking each prefix with [`symlink_metadata`]. When a
+    /// prefix is a symbolic link, the rest of the path is appended to the
+    /// buffer returned by [`read_link`] and resolution continues from there,
+    /// so one more path is allocated per followed link. Relative paths are
+    /// resolved against [`current_dir`], which is assumed to be canonical and
+    /// is another allocated path, and at most [`MAX_SYMLINK_HOPS`] links are
+    /// followed.
+    ///
+    /// [`canonicalize`]: #method.canonicalize
+    /// [`symlink_metadata`]: #method.symlink_metadata
+    /// [`read_link`]: #method.read_link
+    /// [`current_dir`]: #method.current_dir
+    /// [`MAX_SYMLINK_HOPS`]: constant.MAX_SYMLINK_HOPS.html
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`canonicalize`]. If the canonical path doesn't fit into `out`, the
+    /// error returned by the `PathOwned` for paths which are too long is
+    /// returned. The contents of `out` are unspecified if an error is
+    /// returned.
+    fn canonicalize_into(
+        &self,
+        path: &Self::Path,
+        out: &mut Self::PathOwned,
+    ) -> Result<(), Self::Error> {
+        let sep = Self::Path::separator();
+        let root = Self::Path::root();
+        if path.is_absolute() {
+            out.push(root)?;
+        } else {
+            *out = self.current_dir()?;
+        }
+
+        // the target of the last link followed, with the rest of the path
+        // appended
+        let mut link: Option<Self::PathOwned> = None;
+        let mut offset = 0;
+        let mut hops = 0;
+        loop {
+            let bytes = match link {
+                Some(ref link) => link.as_path().as_bytes(),
+                None => path.as_bytes(),
+            };
+            while bytes.get(offset) == Some(&sep) {
+                offset += 1;
+            }
+            if offset == bytes.len() {
+                return Ok(());
+            }
+            let end = bytes[offset..]
+                .iter()
+                .position(|&b| b == sep)
+                .map_or(bytes.len(), |i| offset + i);
+            let name = &bytes[offset..end];
+            offset = end;
+            match name {
+                b"." => continue,
+                b".." => {
+                    out.pop();
+                    continue;
+                }
+                _ => out.push(Self::Path::from_bytes(name))?,
+            }
+            if !self.symlink_metadata(out.as_path())?.is_symlink() {
+                continue;
+            }
+            if hops == MAX_SYMLINK_HOPS {
+                return Err(ErrorKind::FilesystemLoop.into());
+            }
+            hops += 1;
+
+            let mut target = self.read_link(out.as_path())?;
+            out.pop();
+            if target.as_path().is_absolute() {
+                out.push(root)?;
+            }
+            let rest = &bytes[offset..];
+            if let Some(start) = rest.iter().position(|&b| b != sep) {
+                target.push(Self::Path::from_bytes(&rest[start..]))?;
+            }
+            link = Some(target);
+            offset = 0;
+        }
+    }
+
+    /// Returns the canonical form of a path which may not fully exist yet.
+    ///
+    /// The longest prefix of `path` that exists is [`canonicalize`]'d, with
+    /// symbolic links resolved, and the remaining components are appended to
+    /// it lexically, as they are. `.` and `..` components within the missing
+    /// part are not resolved. If no prefix of a relative path exists, the
+    /// current directory `.` is canonicalized instead.
+    ///
+    /// [`canonicalize`]: #method.canonicalize
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * A component in the existing prefix is not a directory.
+    /// * Canonicalizing the existing prefix fails for a reason other than it
+    ///   not existing.
+    fn canonicalize_lenient(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::PathOwned, Self::Error> {
+        let bytes = path.as_bytes();
+        let mut prefix = Some(path);
+        let mut resolved = loop {
+            let current = match prefix {
+                Some(prefix) => prefix,
+                None => Self::Path::from_bytes(b"."),
+            };
+            match self.canonicalize(current) {
+                Ok(resolved) => break resolved,
+                Err(ref err)
+                    if err.kind() == ErrorKind::NotFound
+                        && prefix.is_some() => {}
+                Err(err) => return Err(err),
+            }
+            prefix = match current.parent() {
+                Some(parent) if !parent.as_bytes().is_empty() => Some(parent),
+                Some(_) => None,
+                None => return Err(ErrorKind::NotFound.into()),
+            };
+        };
+
+        let rest = match prefix {
+            Some(prefix) => &bytes[prefix.as_bytes().len()..],
+            None => bytes,
+        };
+        let sep = Self::Path::separator();
+        let start = rest.iter().position(|&b| b != sep).unwrap_or(rest.len());
+        if start < rest.len() {
+            resolved.push(Self::Path::from_bytes(&rest[start..]))?;
+        }
+        Ok(resolved)
+    }
+
+    /// Creates a new, empty directory at the provided path with the specified
+    /// options.
+    ///
+    /// Implementations must apply the mode of `options` atomically, as part
+    /// of creating the directory, like `mkdir` does. The directory must never
+    /// be observable with any other permissions, so creating it with default
+    /// permissions and changing them afterwards is not allowed, since the
+    /// directory would be accessible to others in between. Parent directories
+    /// created because of the `recursive` option are subject to the same
+    /// requirement.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * User lacks permissions to create directory at `path`.
+    /// * `path` already exists, unless the `recursive` options was set.
+    fn create_dir(
+        &mut self,
+        path: &Self::Path,
+        options: &DirOptions<Self::Permissions>,
+    ) -> Result<(), Self::Error>;
+
+    /// Creates a directory at `path` unless it already exists, returning
+    /// whether it was created.
+    ///
+    /// Returns `Ok(true)` if the directory was created and `Ok(false)` if a
+    /// directory already existed at `path`. Existence is detected from the
+    /// error returned by [`create_dir`], so there is no window between
+    /// checking and creating. With the `recursive` option set, [`create_dir`]
+    /// doesn't report existing directories, so they are checked for
+    /// beforehand instead.
+    ///
+    /// [`create_dir`]: #method.create_dir
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`create_dir`], except when a directory already exists at `path`. If
+    /// a file other than a directory exists at `path`, an error of kind
+    /// [`ErrorKind::AlreadyExists`] is returned.
+    ///
+    /// [`ErrorKind::AlreadyExists`]: enum.ErrorKind.html#variant.AlreadyExists
+    fn create_dir_if_missing(
+        &mut self,
+        path: &Self::Path,
+        options: &DirOptions<Self::Permissions>,
+    ) -> Result<bool, Self::Error> {
+        if options.recursive && self.is_dir(path) {
+            return Ok(false);
+        }
+        match self.create_dir(path, options) {
+            Ok(()) => Ok(true),
+            Err(ref err) if err.kind() == ErrorKind::AlreadyExists => {
+                if self.metadata(path)?.is_dir() {
+                    Ok(false)
+                } else {
+                    Err(ErrorKind::AlreadyExists.into())
+                }
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Recursively creates a directory and all of its parent components if
+    /// they are missing.
+    ///
+    /// Instead of attempting to create every component from the top down, the
+    /// default implementation first walks up from `path` with [`metadata`] to
+    /// find the deepest existing ancestor, and then only creates the missing
+    /// directories below it. This keeps the number of calls low on backends
+    /// where each one is a round trip, since deep paths usually exist for the
+    /// most part. A directory which appears between the check and
+    /// [`create_dir`], e.g. because another process created it, is accepted.
+    /// New directories are created with the default options.
+    ///
+    /// [`metadata`]: #method.metadata
+    /// [`create_dir`]: #method.create_dir
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * User lacks permissions to create any of the directories.
+    /// * `path` or one of its parents exists, but is not a directory.
+    fn create_dir_all(&mut self, path: &Self::Path) -> Result<(), Self::Error> {
+        create_missing_dirs(self, path, |_| Ok(()))
+    }
+
+    /// Recursively creates a directory and all of its parent components if
+    /// they are missing, returning the paths of the directories which were
+    /// actually created.
+    ///
+    /// The returned list is ordered from the shallowest to the deepest
+    /// directory, so a caller that needs to roll back can remove them in
+    /// reverse order. Directories that already existed are not part of the
+    /// list. New directories are created with the default options.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * User lacks permissions to create any of the directories.
+    /// * `path` or one of its parents exists, but is not a directory.
+    #[cfg(feature = "alloc")]
+    fn create_dir_all_tracked(
+        &mut self,
+        path: &Self::Path,
+    ) -> Result<Vec<Self::PathOwned>, Self::Error> {
+        let mut created = Vec::new();
+        create_missing_dirs(self, path, |dir| {
+            created.push(Self::PathOwned::from_path(dir)?);
+            Ok(())
+        })?;
+        Ok(created)
+    }
+
+    /// Creates a whole tree of files, directories and symbolic links below
+    /// `root`.
+    ///
+    /// Each entry consists of a path, which is resolved relative to `root`,
+    /// and the [`TreeNode`] to create there. Entries are created in the order
+    /// they are given, and any missing parent directories of an entry,
+    /// including `root` itself, are created with [`create_dir_all`] before
+    /// the entry. Directories which already exist are kept, files which
+    /// already exist are truncated and overwritten. This is mainly meant for
+    /// setting up fixtures declaratively.
+    ///
+    /// [`TreeNode`]: enum.TreeNode.html
+    /// [`create_dir_all`]: #method.create_dir_all
+    ///
+    /// # Errors
+    ///
+    /// This function returns the first error encountered while creating the
+    /// entries, the entries created up to that point are left in place.
+    #[cfg(feature = "alloc")]
+    fn create_tree(
+        &mut self,
+        root: &Self::Path,
+        entries: &[(&Self::Path, TreeNode<'_, Self::Path>)],
+    ) -> Result<(), Self::Error> {
+        self.create_dir_all(root)?;
+        for &(path, node) in entries {
+            let mut full = Self::PathOwned::from_path(root)?;
+            full.push(path)?;
+            let path = full.as_path();
+            match path.parent() {
+                Some(parent) if !matches!(node, TreeNode::Dir) => {
+                    self.create_dir_all(parent)?
+                }
+                _ => {}
+            }
+            match node {
+                TreeNode::File(data) => {
+                    let mut file = self.open(
+                        path,
+                        OpenOptions::new()
+                            .write(true)
+                            .create(true)
+                            .truncate(true),
+                    )?;
+                    file.write_all(data)?;
+                    file.flush()?;
+                }
+                TreeNode::Dir => self.create_dir_all(path)?,
+                TreeNode::Symlink(target) => self.symlink(target, path)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes an existing, empty directory.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * The user lacks permissions to remove the directory at the provided
+    ///   `path`.
+    /// * The directory isn't empty.
+    fn remove_dir(&mut self, path: &Self::Path) -> Result<(), Self::Error>;
+
+    /// Removes a directory at this path, after removing all its contents. Use
+    /// carefully!
+    ///
+    /// This function does **not** follow symbolic links and it will simply
+    /// remove the symbolic link itself.
+    ///
+    /// # Errors
+    ///
+    /// See `Fs::remove_file` and `Fs::remove_dir`.
+    fn remove_dir_all(&mut self, path: &Self::Path) -> Result<(), Self::Error>;
+
+    /// Like [`remove_dir_all`], but refuses to remove the root of the
+    /// filesystem.
+    ///
+    /// A path without a [`parent`], i.e. the root or the empty path, is
+    /// rejected, as is the path `.` and any path ending in `..`, which could
+    /// refer to the root as well. This is a safety rail against bugs
+    /// which would otherwise wipe everything, and is the recommended way to
+    /// recursively remove a directory whose path comes from untrusted input.
+    /// The check is purely lexical, so symbolic links are not detected.
+    ///
+    /// [`remove_dir_all`]: #method.remove_dir_all
+    /// [`parent`]: trait.Path.html#method.parent
+    ///
+    /// # Errors
+    ///
+    /// An error of kind [`ErrorKind::InvalidInput`] is returned for a
+    /// rejected path. Otherwise, see [`remove_dir_all`].
+    ///
+    /// [`ErrorKind::InvalidInput`]: enum.ErrorKind.html#variant.InvalidInput
+    fn remove_dir_all_guarded(
+        &mut self,
+        path: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        match path.components().last() {
+            Some(Component::Normal(_)) if path.parent().is_some() => {
+                self.remove_dir_all(path)
+            }
+            _ => Err(ErrorKind::InvalidInput.into()),
+        }
+    }
+
+    /// Returns an iterator over the entries within a directory.
+    ///
+    /// The iterator will yield instances of `Result``<`[`DirEntry`]`>`.
+    /// New errors may be encountered after an iterator is initially
+    /// constructed.
+    ///
+    /// Depending on the implementation, the directory may be held open for as
+    /// long as the iterator lives. See [`read_dir_owned`] for a variant which
+    /// releases it before returning.
+    ///
+    /// [`DirEntry`]: trait.DirEntry.html
+    /// [`read_dir_owned`]: #method.read_dir_owned
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * The provided `path` doesn't exist.
+    /// * The process lacks permissions to view the contents.
+    /// * The `path` points at a non-directory file.
+    fn read_dir(&self, path: &Self::Path) -> Result<Self::Dir, Self::Error>;
+
+    /// Resolves `dir` with [`canonicalize`] once, checks that it is a
+    /// directory and passes a [`DirScope`] for it to `f`, returning whatever
+    /// `f` returns.
+    ///
+    /// The scope is a path-joining helper: its methods take paths relative
+    /// to `dir`, which is convenient for a burst of operations on files in
+    /// the same directory, and forward them to this filesystem joined to the
+    /// canonical path. Symbolic links in `dir` and the current directory are
+    /// thus only looked at once, not on every call. It only borrows the
+    /// filesystem shared, so it can't modify it, see [`scoped_mut`] for that.
+    ///
+    /// [`canonicalize`]: #method.canonicalize
+    /// [`DirScope`]: struct.DirScope.html
+    /// [`scoped_mut`]: #method.scoped_mut
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`canonicalize`] and [`metadata`], an error of kind
+    /// [`ErrorKind::NotADirectory`] if `dir` is not a directory, or the error
+    /// returned by `f`.
+    ///
+    /// [`metadata`]: #method.metadata
+    /// [`ErrorKind::NotADirectory`]: enum.ErrorKind.html#variant.NotADirectory
+    fn scoped<T, G>(&self, dir: &Self::Path, f: G) -> Result<T, Self::Error>
+    where
+        Self: Sized,
+        G: FnOnce(&DirScope<Self, &Self>) -> Result<T, Self::Error>,
+    {
+        let dir = self.canonicalize(dir)?;
+        if !self.metadata(dir.as_path())?.is_dir() {
+            return Err(ErrorKind::NotADirectory.into());
+        }
+        f(&DirScope::new(self, dir))
+    }
+
+    /// Like [`scoped`], but borrows the filesystem exclusively, so the
+    /// [`DirScope`] passed to `f` can also modify it.
+    ///
+    /// [`scoped`]: #method.scoped
+    /// [`DirScope`]: struct.DirScope.html
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`scoped`].
+    fn scoped_mut<T, G>(
+        &mut self,
+        dir: &Self::Path,
+        f: G,
+    ) -> Result<T, Self::Error>
+    where
+        Self: Sized,
+        G: FnOnce(&mut DirScope<Self, &mut Self>) -> Result<T, Self::Error>,
+    {
+        let dir = self.canonicalize(dir)?;
+        if !self.metadata(dir.as_path())?.is_dir() {
+            return Err(ErrorKind::NotADirectory.into());
+        }
+        f(&mut DirScope::new(self, dir))
+    }
+
+    /// Returns an iterator over the entries within a directory, excluding the
+    /// `.` and `..` entries.
+    ///
+    /// Backends differ in whether [`read_dir`] yields these entries. This
+    /// method wraps its iterator in a [`FilteredDir`] which skips them, so
+    /// portable code walking directories doesn't have to.
+    ///
+    /// # Errors
+    ///
+    /// See [`read_dir`].
+    ///
+    /// [`read_dir`]: #method.read_dir
+    /// [`FilteredDir`]: struct.FilteredDir.html
+    fn read_dir_no_dots(
+        &self,
+        path: &Self::Path,
+    ) -> Result<FilteredDir<Self::Dir>, Self::Error> {
+        self.read_dir(path).map(FilteredDir::new)
+    }
+
+    /// Returns an iterator which recursively walks the directory tree below
+    /// `path`.
+    ///
+    /// See [`WalkDir`] for the order in which the entries are yielded, and
+    /// [`WalkDir::filter_entry`] for pruning subtrees during the walk.
+    ///
+    /// [`WalkDir`]: struct.WalkDir.html
+    /// [`WalkDir::filter_entry`]: struct.WalkDir.html#method.filter_entry
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `path` doesn't fit in an owned
+    /// path. Errors reading any of the directories, including `path` itself,
+    /// are yielded by the iterator.
+    #[cfg(feature = "alloc")]
+    fn walk(&self, path: &Self::Path) -> Result<WalkDir<'_, Self>, Self::Error>
+    where
+        Self: Sized,
+    {
+        WalkDir::new(self, path)
+    }
+
+    /// Walks the directory tree below `root` and returns the path of the
+    /// first entry for which `pred` returns `true`.
+    ///
+    /// Entries are visited in the order of [`walk`], which stops as soon as a
+    /// match is found. `root` itself is not passed to `pred`.
+    ///
+    /// [`walk`]: #method.walk
+    ///
+    /// # Errors
+    ///
+    /// This function will return the first error encountered during the
+    /// walk, even if a matching entry would have been found later.
+    #[cfg(feature = "alloc")]
+    fn find<P>(
+        &self,
+        root: &Self::Path,
+        mut pred: P,
+    ) -> Result<Option<Self::PathOwned>, Self::Error>
+    where
+        Self: Sized,
+        P: FnMut(&Self::DirEntry) -> bool,
+    {
+        for entry in self.walk(root)? {
+            let entry = entry?;
+            if pred(&entry) {
+                let path = Self::PathOwned::from_path(entry.path().as_path())?;
+                return Ok(Some(path));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns all entries within a directory, collected eagerly.
+    ///
+    /// Unlike [`read_dir`], the directory iterator is exhausted and dropped
+    /// before this function returns, so any handle it holds is closed
+    /// immediately. The returned entries carry their own paths and remain
+    /// usable afterwards. This trades memory for fewer open handles, which
+    /// matters for long walks on backends with limited descriptors.
+    ///
+    /// [`read_dir`]: #method.read_dir
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`read_dir`], or if reading any of the entries fails.
+    #[cfg(feature = "alloc")]
+    fn read_dir_owned(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Vec<Self::DirEntry>, Self::Error> {
+        self.read_dir(path)?.collect()
+    }
+
+    /// Returns the paths of all entries within a directory, each paired with
+    /// its file type.
+    ///
+    /// File types are queried with [`DirEntry::file_type`], which is usually
+    /// cheaper than fetching the full metadata, and symbolic links are not
+    /// traversed. This is what a file picker or `ls` needs for a listing.
+    ///
+    /// [`DirEntry::file_type`]: trait.DirEntry.html#method.file_type
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`read_dir`], or if reading any of the entries or their file types
+    /// fails.
+    ///
+    /// [`read_dir`]: #method.read_dir
+    #[cfg(feature = "alloc")]
+    #[allow(clippy::type_complexity)]
+    fn read_dir_with_types(
+        &self,
+        path: &Self::Path,
+    ) -> Result<
+        Vec<(Self::PathOwned, <Self::DirEntry as DirEntry>::FileType)>,
+        Self::Error,
+    > {
+        let mut entries = Vec::new();
+        for entry in self.read_dir(path)? {
+            let entry = entry?;
+            let path = Self::PathOwned::from_path(entry.path().as_path())?;
+            entries.push((path, entry.file_type()?));
+        }
+        Ok(entries)
+    }
+
+    /// Returns the paths of all entries within a directory, each paired with
+    /// its metadata.
+    ///
+    /// Metadata is queried with [`DirEntry::metadata`], so symbolic links are
+    /// not traversed. The default implementation makes one query per entry,
+    /// backends with a batched call like `readdirplus` should override it to
+    /// fetch the listing and the metadata together.
+    ///
+    /// [`DirEntry::metadata`]: trait.DirEntry.html#method.metadata
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`read_dir`], or if reading any of the entries or their metadata
+    /// fails.
+    ///
+    /// [`read_dir`]: #method.read_dir
+    #[cfg(feature = "alloc")]
+    #[allow(clippy::type_complexity)]
+    fn read_dir_with_metadata(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Vec<(Self::PathOwned, Self::Metadata)>, Self::Error> {
+        let mut entries = Vec::new();
+        for entry in self.read_dir(path)? {
+            let entry = entry?;
+            let path = Self::PathOwned::from_path(entry.path().as_path())?;
+            entries.push((path, entry.metadata()?));
+        }
+        Ok(entries)
+    }
+
+    /// Returns all entries within a directory in a deterministic order.
+    ///
+    /// The order of entries returned by [`read_dir`] is unspecified. This
+    /// function buffers the whole listing and sorts it by
+    /// [`DirEntry::sort_key`], which by default puts directories first and
+    /// then orders bytewise by file name. The key of each entry is computed
+    /// once. This gives reproducible output at the cost of holding all
+    /// entries and their keys in memory at once.
+    ///
+    /// [`read_dir`]: #method.read_dir
+    /// [`DirEntry::sort_key`]: trait.DirEntry.html#method.sort_key
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`read_dir`], or if reading any of the entries or computing their
+    /// keys fails.
+    #[cfg(feature = "alloc")]
+    fn read_dir_sorted(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Vec<Self::DirEntry>, Self::Error> {
+        let mut keyed = Vec::new();
+        for entry in self.read_dir_owned(path)? {
+            let (rest, name) = entry.sort_key()?;
+            keyed.push(((rest, name.to_vec()), entry));
+        }
+        keyed.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(keyed.into_iter().map(|(_, entry)| entry).collect())
+    }
+
+    /// Changes the permissions found on a file or a directory.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * `path` does not exist.
+    /// * The user lacks the permission to change attributes of the file.
+    fn set_permissions(
+        &mut self,
+        path: &Self::Path,
+        perm: Self::Permissions,
+    ) -> Result<(), Self::Error>;
+
+    /// Changes the permissions found on a file without following a final
+    /// symbolic link, mirroring `lchmod`.
+    ///
+    /// Unlike [`set_permissions`], if `path` is a symbolic link, the
+    /// permissions of the link itself are changed instead of those of its
+    /// target. Symbolic links in the parent directories are still followed.
+    /// This matters for permission fixups on untrusted trees, where a link
+    /// could redirect the change to an arbitrary file.
+    ///
+    /// [`set_permissions`]: #method.set_permissions
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`set_permissions`]. The default implementation returns an error of
+    /// kind [`ErrorKind::Unsupported`], as many systems don't support
+    /// permissions on symbolic links.
+    ///
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    fn set_permissions_nofollow(
+        &mut self,
+        path: &Self::Path,
+        perm: Self::Permissions,
+    ) -> Result<(), Self::Error> {
+        let _ = (path, perm);
+        Err(ErrorKind::Unsupported.into())
+    }
+
+    /// Returns the current working directory, against which relative paths
+    /// are resolved.
+    ///
+    /// The default implementation returns the [`root`] path, which is where
+    /// filesystems without a notion of a working directory resolve relative
+    /// paths.
+    ///
+    /// [`root`]: trait.Path.html#method.root
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the current directory doesn't
+    /// exist anymore or doesn't fit in an owned path.
+    fn current_dir(&self) -> Result<Self::PathOwned, Self::Error> {
+        Ok(Self::PathOwned::from_path(<Self::Path as Path>::root())?)
+    }
+
+    /// Changes the current working directory to `path`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * `path` does not exist.
+    /// * `path` is not a directory.
+    ///
+    /// The default implementation returns an error of kind
+    /// [`ErrorKind::Unsupported`].
+    ///
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    fn set_current_dir(
+        &mut self,
+        path: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        let _ = path;
+        Err(ErrorKind::Unsupported.into())
+    }
+
+    /// Changes the timestamps of a file or a directory.
+    ///
+    /// Timestamps which are not set in `times` are left unchanged. This
+    /// function will traverse symbolic links.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following situations, but is
+    /// not limited to just these cases:
+    ///
+    /// * `path` does not exist.
+    /// * The user lacks the permission to change attributes of the file.
+    ///
+    /// The default implementation returns an error of kind
+    /// [`ErrorKind::Unsupported`].
+    ///
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    fn set_times(
+        &mut self,
+        path: &Self::Path,
+        times: FileTimes,
+    ) -> Result<(), Self::Error> {
+        let _ = (path, times);
+        Err(ErrorKind::Unsupported.into())
+    }
+
+    /// Sets the access and modification time of a file to `time`, creating
+    /// an empty file if it doesn't exist, like `touch`.
+    ///
+    /// Without the standard library there is no clock to read the current
+    /// time from, so it has to be passed by the caller.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`set_times`], or if the file doesn't exist and can't be created.
     ///
-    /// * `path` does not exist.
-    /// * A component in path is not a directory.
-    fn canonicalize(
-        &self,
+    /// [`set_times`]: #method.set_times
+    fn touch_at(
+        &mut self,
         path: &Self::Path,
-    ) -> Result<Self::PathOwned, Self::Error>;
+        time: SystemTime,
+    ) -> Result<(), Self::Error> {
+        if self.metadata_opt(path)?.is_none() {
+            self.open(path, OpenOptions::new().write(true).create(true))?;
+        }
+        self.set_times(
+            path,
+            FileTimes::new().set_accessed(time).set_modified(time),
+        )
+    }
 
-    /// Creates a new, empty directory at the provided path with the specified
-    /// options.
+    /// Truncates or extends the file at `path` to `size` bytes, without the
+    /// caller having to hold an open handle.
+    ///
+    /// The default implementation opens the file for writing and calls
+    /// [`File::set_len`] on it.
+    ///
+    /// [`File::set_len`]: trait.File.html#method.set_len
     ///
     /// # Errors
     ///
     /// This function will return an error in the following situations, but is
     /// not limited to just these cases:
     ///
-    /// * User lacks permissions to create directory at `path`.
-    /// * `path` already exists, unless the `recursive` options was set.
-    fn create_dir(
+    /// * `path` does not exist. This function never creates a file.
+    /// * The user lacks permissions to write to `path`.
+    /// * The file doesn't support changing its length.
+    fn truncate(
         &mut self,
         path: &Self::Path,
-        options: &DirOptions<Self::Permissions>,
-    ) -> Result<(), Self::Error>;
+        size: u64,
+    ) -> Result<(), Self::Error> {
+        let mut file = self.open(path, OpenOptions::new().write(true))?;
+        file.set_len(size)?;
+        file.flush()
+    }
 
-    /// Removes an existing, empty directory.
+    /// Returns the number of bytes which can still be written to the
+    /// filesystem containing `path`.
+    ///
+    /// This is meant for pre-flight checks before large writes. The figure is
+    /// only a snapshot, so a subsequent write may still run out of space.
     ///
     /// # Errors
     ///
-    /// This function will return an error in the following situations, but is
-    /// not limited to just these cases:
+    /// The default implementation returns an error of kind
+    /// [`ErrorKind::Unsupported`], as there is no generic way to query the
+    /// free space of a filesystem.
     ///
-    /// * The user lacks permissions to remove the directory at the provided
-    /// `path`. * The directory isn't empty.
-    fn remove_dir(&mut self, path: &Self::Path) -> Result<(), Self::Error>;
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    fn available_space(&self, path: &Self::Path) -> Result<u64, Self::Error> {
+        let _ = path;
+        Err(ErrorKind::Unsupported.into())
+    }
 
-    /// Removes a directory at this path, after removing all its contents. Use
-    /// carefully!
+    /// Returns all filesystems mounted within this filesystem.
     ///
-    /// This function does **not** follow symbolic links and it will simply
-    /// remove the symbolic link itself.
+    /// Tools which span several filesystems, like backups, use this to find
+    /// their boundaries.
     ///
     /// # Errors
     ///
-    /// See `Fs::remove_file` and `Fs::remove_dir`.
-    fn remove_dir_all(&mut self, path: &Self::Path) -> Result<(), Self::Error>;
-
-    /// Returns an iterator over the entries within a directory.
+    /// The default implementation returns an error of kind
+    /// [`ErrorKind::Unsupported`].
     ///
-    /// The iterator will yield instances of `Result``<`[`DirEntry`]`>`.
-    /// New errors may be encountered after an iterator is initially
-    /// constructed.
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    #[cfg(feature = "alloc")]
+    fn mount_points(
+        &self,
+    ) -> Result<Vec<MountInfo<Self::PathOwned>>, Self::Error> {
+        Err(ErrorKind::Unsupported.into())
+    }
+
+    /// Returns an identifier of the mounted filesystem containing `path`,
+    /// like the device number `st_dev`.
     ///
-    /// [`DirEntry`]: trait.DirEntry.html
+    /// Two paths are on the same filesystem if and only if their ids are
+    /// equal, which lets a directory walker notice when it crosses into a
+    /// different filesystem. Symbolic links are followed.
     ///
     /// # Errors
     ///
     /// This function will return an error in the following situations, but is
     /// not limited to just these cases:
     ///
-    /// * The provided `path` doesn't exist.
-    /// * The process lacks permissions to view the contents.
-    /// * The `path` points at a non-directory file.
-    fn read_dir(&self, path: &Self::Path) -> Result<Self::Dir, Self::Error>;
+    /// * `path` does not exist.
+    ///
+    /// The default implementation returns an error of kind
+    /// [`ErrorKind::Unsupported`].
+    ///
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    fn mount_id(&self, path: &Self::Path) -> Result<u64, Self::Error> {
+        let _ = path;
+        Err(ErrorKind::Unsupported.into())
+    }
 
-    /// Changes the permissions found on a file or a directory.
+    /// Writes all pending changes of the whole filesystem to the underlying
+    /// storage, like the `sync` system call.
+    ///
+    /// Depending on the backend, this may be a no-op, for example for purely
+    /// in-memory filesystems, or very expensive, as it may have to write out
+    /// the data of every file that was modified. The default implementation
+    /// does nothing.
     ///
     /// # Errors
     ///
-    /// This function will return an error in the following situations, but is
-    /// not limited to just these cases:
+    /// This function will return an error if any pending change couldn't be
+    /// written.
+    fn sync(&self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Pushes all writes which are buffered by this filesystem object down to
+    /// the backend it wraps.
     ///
-    /// * `path` does not exist.
-    /// * The user lacks the permission to change attributes of the file.
-    fn set_permissions(
-        &mut self,
-        path: &Self::Path,
-        perm: Self::Permissions,
-    ) -> Result<(), Self::Error>;
+    /// Composed filesystems, e.g. ones which buffer the writes of their files
+    /// or cache other state, may hold changes which flushing an individual
+    /// file doesn't reach. This writes them all out in one call, e.g. at
+    /// shutdown. Unlike [`sync`], it doesn't ask the backend to make the
+    /// changes durable. Wrappers forward it to the filesystems they wrap.
+    ///
+    /// The default implementation does nothing.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if any buffered change couldn't be
+    /// written.
+    ///
+    /// [`sync`]: #method.sync
+    fn flush_all(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
 /// A reference to an open file on the filesystem.
@@ -501,11 +2439,230 @@ pub trait Fs {
 /// it was opened with.
 ///
 /// Files should be automatically closed when they go out of scope.
-pub trait File {
+pub trait File: Read + Write + Seek {
+    /// Truncates or extends the underlying file, updating the size of this
+    /// file to become `size`.
+    ///
+    /// If `size` is less than the current file's size, then the file will be
+    /// shrunk. If it is greater than the current file's size, then the file
+    /// will be extended to `size` and have all of the intermediate data filled
+    /// in with 0s. The cursor of the file is not changed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file is not opened for
+    /// writing. The default implementation returns an error of kind
+    /// [`ErrorKind::Unsupported`].
+    ///
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    fn set_len(&mut self, size: u64) -> Result<(), Self::Error> {
+        let _ = size;
+        Err(ErrorKind::Unsupported.into())
+    }
+
+    /// Makes `target` refer to the same open file as `self`, mirroring
+    /// `dup2`.
+    ///
+    /// Afterwards both files share a single open file description: the cursor
+    /// and the access mode, so reading, writing or seeking through one of them
+    /// moves the cursor of the other. Whatever `target` referred to before is
+    /// closed. On unix-like systems with file descriptors, this allows
+    /// redirecting a well-known descriptor such as standard output to `self`.
+    ///
+    /// # Errors
+    ///
+    /// The default implementation returns an error of kind
+    /// [`ErrorKind::Unsupported`], as most filesystems don't expose open file
+    /// descriptions.
+    ///
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    fn duplicate_to(&self, target: &mut Self) -> Result<(), Self::Error>
+    where
+        Self: Sized,
+    {
+        let _ = target;
+        Err(ErrorKind::Unsupported.into())
+    }
+
+    /// Reads a number of bytes starting from a given offset, without changing
+    /// the cursor of the file, mirroring `pread`.
+    ///
+    /// Returns the number of bytes read, which is `0` if `offset` is at or
+    /// past the end of the file.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file is not opened for
+    /// reading. The default implementation returns an error of kind
+    /// [`ErrorKind::Unsupported`].
+    ///
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    fn read_at(
+        &self,
+        buf: &mut [u8],
+        offset: u64,
+    ) -> Result<usize, Self::Error> {
+        let _ = (buf, offset);
+        Err(ErrorKind::Unsupported.into())
+    }
+
+    /// Reads the exact number of bytes required to fill `buf`, starting from
+    /// a given offset, without changing the cursor of the file.
+    ///
+    /// This calls [`read_at`] until `buf` is full, advancing the offset by
+    /// the number of bytes read each time.
+    ///
+    /// [`read_at`]: #method.read_at
+    ///
+    /// # Errors
+    ///
+    /// If this function encounters an "end of file" before completely filling
+    /// the buffer, it returns an error of the kind
+    /// [`ErrorKind::UnexpectedEof`]. Otherwise, it returns an error in the
+    /// same situations as [`read_at`]. The contents of `buf` are unspecified
+    /// in either case.
+    ///
+    /// [`ErrorKind::UnexpectedEof`]: enum.ErrorKind.html#variant.UnexpectedEof
+    fn read_exact_at(
+        &self,
+        mut buf: &mut [u8],
+        mut offset: u64,
+    ) -> Result<(), Self::Error> {
+        while !buf.is_empty() {
+            match self.read_at(buf, offset)? {
+                0 => return Err(ErrorKind::UnexpectedEof.into()),
+                n => {
+                    let tmp = buf;
+                    buf = &mut tmp[n..];
+                    offset += n as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a number of bytes starting from a given offset, without changing
+    /// the cursor of the file, mirroring `pwrite`.
+    ///
+    /// Returns the number of bytes written. Writing past the end of the file
+    /// extends it, filling the gap with 0s.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file is not opened for
+    /// writing. The default implementation returns an error of kind
+    /// [`ErrorKind::Unsupported`].
+    ///
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    fn write_at(
+        &mut self,
+        buf: &[u8],
+        offset: u64,
+    ) -> Result<usize, Self::Error> {
+        let _ = (buf, offset);
+        Err(ErrorKind::Unsupported.into())
+    }
+
+    /// Like [`read_at`], except that it reads into a slice of buffers,
+    /// mirroring `preadv`.
+    ///
+    /// Buffers are filled in order, starting at `offset`. The default
+    /// implementation calls [`read_at`] with the first non-empty buffer only,
+    /// so it may read less than would fit into all buffers.
+    ///
+    /// [`read_at`]: #method.read_at
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`read_at`].
+    fn read_vectored_at(
+        &self,
+        bufs: &mut [IoSliceMut],
+        offset: u64,
+    ) -> Result<usize, Self::Error> {
+        match bufs.iter_mut().find(|buf| !buf.is_empty()) {
+            Some(buf) => self.read_at(buf, offset),
+            None => Ok(0),
+        }
+    }
+
+    /// Like [`write_at`], except that it writes from a slice of buffers,
+    /// mirroring `pwritev`.
+    ///
+    /// Buffers are written in order, starting at `offset`. The default
+    /// implementation calls [`write_at`] with the first non-empty buffer
+    /// only, so it may write less than all buffers hold.
+    ///
+    /// [`write_at`]: #method.write_at
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`write_at`].
+    fn write_vectored_at(
+        &mut self,
+        bufs: &[IoSlice],
+        offset: u64,
+    ) -> Result<usize, Self::Error> {
+        match bufs.iter().find(|buf| !buf.is_empty()) {
+            Some(buf) => self.write_at(buf, offset),
+            None => Ok(0),
+        }
+    }
+
+    /// Reads from the cursor of the file into at most `max` bytes of `buf`.
+    ///
+    /// This is equivalent to calling [`read`] with the first
+    /// `min(buf.len(), max)` bytes of `buf`, which guarantees a bound on a
+    /// single read, e.g. when servicing a length requested by an untrusted
+    /// peer, without slicing at the call site.
+    ///
+    /// [`read`]: trait.Read.html#method.read
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the same situations as
+    /// [`read`].
+    fn read_at_most(
+        &self,
+        buf: &mut [u8],
+        max: usize,
+    ) -> Result<usize, Self::Error> {
+        let len = cmp::min(buf.len(), max);
+        self.read(&mut buf[..len])
+    }
+}
+
+/// Base trait of all I/O objects, which defines their error type.
+///
+/// It is shared between the [`Read`], [`Write`] and [`Seek`] traits so that an
+/// object implementing more than one of them has a single error type.
+///
+/// [`Read`]: trait.Read.html
+/// [`Write`]: trait.Write.html
+/// [`Seek`]: trait.Seek.html
+pub trait ErrorType {
     /// The type that represents the set of all errors that can occur during
     /// reading or writing.
-    type Error;
+    type Error: Error;
+}
 
+/// The `Read` trait allows for reading bytes from a source.
+///
+/// Unlike `std::io::Read`, and unlike [`Write`], reading takes `&self`. Any
+/// number of shared references to a file can read from it, e.g. to share an
+/// open file between several parsers, to read from a file while holding its
+/// metadata, or to combine reading with the positional [`File::read_at`].
+/// The price is that readers which advance a cursor have to keep it in a
+/// `Cell` or some other form of interior mutability. Since a `&R` is itself
+/// a reader, adapters which take ownership of their reader, like
+/// [`BufReader`], can also be used with a borrowed one.
+///
+/// [`Write`]: trait.Write.html
+/// [`File::read_at`]: trait.File.html#method.read_at
+/// [`BufReader`]: struct.BufReader.html
+pub trait Read: ErrorType {
     /// Pull some bytes from this source into the specified buffer, returning
     /// how many bytes were read.
     ///
@@ -535,6 +2692,244 @@ pub trait File {
     /// variant will be returned.
     fn read(&self, buf: &mut [u8]) -> Result<usize, Self::Error>;
 
+    /// Pull some bytes from this source into the possibly uninitialized
+    /// buffer `buf`, returning how many bytes were read.
+    ///
+    /// This behaves like [`read`], but lets readers backed by system calls or
+    /// DMA avoid initializing large buffers which are overwritten anyway.
+    ///
+    /// The default implementation initializes all of `buf` with zeros and
+    /// calls [`read`]. Implementations overriding it must never read from
+    /// `buf` and must never write uninitialized memory into it.
+    ///
+    /// # Safety contract
+    ///
+    /// If this function returns `Ok(n)`, then `n <= buf.len()` and the first
+    /// `n` elements of `buf` have been initialized with the bytes read.
+    /// Nothing is guaranteed about the remaining elements, nor about any
+    /// element if an error is returned. Since this trait is safe to
+    /// implement, unsafe code relying on the contract for soundness should
+    /// only do so for readers it trusts.
+    ///
+    /// # Errors
+    ///
+    /// See [`read`].
+    ///
+    /// [`read`]: #method.read
+    fn read_uninit(
+        &self,
+        buf: &mut [MaybeUninit<u8>],
+    ) -> Result<usize, Self::Error> {
+        for byte in buf.iter_mut() {
+            *byte = MaybeUninit::new(0);
+        }
+        // SAFETY: every element of `buf` was initialized above and
+        // `MaybeUninit<u8>` has the same layout as `u8`.
+        let buf = unsafe { &mut *(buf as *mut [MaybeUninit<u8>] as *mut [u8]) };
+        self.read(buf)
+    }
+
+    /// Read the exact number of bytes required to fill `buf`.
+    ///
+    /// This function reads as many bytes as necessary to completely fill the
+    /// specified buffer `buf`.
+    ///
+    /// No guarantees are provided about the contents of `buf` when this
+    /// function is called, implementations cannot rely on any property of the
+    /// contents of `buf` being true. It is recommended that implementations
+    /// only write data to `buf` instead of reading its contents.
+    ///
+    /// # Errors
+    ///
+    /// If this function encounters an "end of file" before completely filling
+    /// the buffer, it returns an error of the kind
+    /// [`ErrorKind::UnexpectedEof`]. The contents of `buf` are unspecified in
+    /// this case.
+    ///
+    /// If this function encounters an error of the kind
+    /// [`ErrorKind::Interrupted`] then the error is ignored and the operation
+    /// will continue.
+    ///
+    /// If any other read error is encountered then this function immediately
+    /// returns. The contents of `buf` are unspecified in this case.
+    ///
+    /// [`ErrorKind::UnexpectedEof`]: enum.ErrorKind.html#variant.UnexpectedEof
+    /// [`ErrorKind::Interrupted`]: enum.ErrorKind.html#variant.Interrupted
+    fn read_exact(&self, mut buf: &mut [u8]) -> Result<(), Self::Error> {
+        while !buf.is_empty() {
+            match self.read(buf) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(n) => {
+                    let tmp = buf;
+                    buf = &mut tmp[n..];
+                }
+                Err(ref err) if err.is_interrupted() => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the exact number of bytes required to fill `buf`, reporting how
+    /// many bytes were read if it fails.
+    ///
+    /// This behaves like [`read_exact`], but the error is paired with the
+    /// number of bytes at the start of `buf` which were filled before it
+    /// occurred. This distinguishes e.g. a file which was cleanly truncated at
+    /// a record boundary from one which is corrupt.
+    ///
+    /// # Errors
+    ///
+    /// See [`read_exact`]. The first `n` bytes of `buf` hold the data read
+    /// when `(n, err)` is returned, the contents of the rest are unspecified.
+    ///
+    /// [`read_exact`]: #method.read_exact
+    fn read_exact_reporting(
+        &self,
+        buf: &mut [u8],
+    ) -> Result<(), (usize, Self::Error)> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.read(&mut buf[filled..]) {
+                Ok(0) => return Err((filled, ErrorKind::UnexpectedEof.into())),
+                Ok(n) => filled += n,
+                Err(ref err) if err.is_interrupted() => {}
+                Err(err) => return Err((filled, err)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads and discards up to `n` bytes, returning how many bytes were
+    /// skipped.
+    ///
+    /// Fewer than `n` bytes are only skipped if this reader reaches "end of
+    /// file" first. The default implementation reads into a scratch buffer
+    /// on the stack until enough bytes were discarded. Readers which buffer
+    /// data should override it to skip without reading. Seeking requires
+    /// `&mut self`, so seekable readers offer a separate method instead, like
+    /// [`BufReader::skip_seek`].
+    ///
+    /// [`BufReader::skip_seek`]: struct.BufReader.html#method.skip_seek
+    ///
+    /// # Errors
+    ///
+    /// If any read error is encountered then this function immediately
+    /// returns. The number of bytes skipped so far is unspecified in this
+    /// case.
+    fn skip(&self, n: u64) -> Result<u64, Self::Error> {
+        skip_data(self, n)
+    }
+
+    /// Creates an adapter which reads at most `limit` bytes from this
+    /// reader.
+    ///
+    /// The adapter reports "end of file" once `limit` bytes were read, and
+    /// counts the bytes read through it, see [`Take`].
+    ///
+    /// [`Take`]: struct.Take.html
+    fn take(self, limit: u64) -> Take<Self>
+    where
+        Self: Sized,
+    {
+        Take::new(self, limit)
+    }
+
+    /// Read exactly `N` bytes into a new array.
+    ///
+    /// This is a convenience wrapper around [`read_exact`] for fixed-size
+    /// records, such as headers or magic numbers.
+    ///
+    /// # Errors
+    ///
+    /// See [`read_exact`].
+    ///
+    /// [`read_exact`]: #method.read_exact
+    fn read_array<const N: usize>(&self) -> Result<[u8; N], Self::Error> {
+        let mut buf = [0u8; N];
+        self.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Creates an iterator over the consecutive `N`-byte records of this
+    /// reader, e.g. the rows of a fixed-width table.
+    ///
+    /// The iterator ends cleanly if the reader's length is a multiple of
+    /// `N`, otherwise its last item is an error of kind
+    /// [`ErrorKind::UnexpectedEof`], see [`Records`]. With `N == 0` it yields
+    /// nothing.
+    ///
+    /// [`ErrorKind::UnexpectedEof`]: enum.ErrorKind.html#variant.UnexpectedEof
+    /// [`Records`]: struct.Records.html
+    fn records<const N: usize>(self) -> Records<Self, N>
+    where
+        Self: Sized,
+    {
+        Records::new(self)
+    }
+
+    /// Read a frame prefixed with its length as a 4-byte big-endian integer.
+    ///
+    /// The contents of `buf` are replaced with the payload of the frame and
+    /// its length is returned. If `max_len` is given, frames longer than it
+    /// are rejected before anything is allocated for their payload.
+    ///
+    /// # Errors
+    ///
+    /// If the length is larger than `max_len`, an error of the kind
+    /// [`ErrorKind::InvalidData`] is returned and nothing but the length
+    /// prefix is consumed from the source.
+    ///
+    /// See [`read_exact`] for errors caused by a truncated frame.
+    ///
+    /// [`ErrorKind::InvalidData`]: enum.ErrorKind.html#variant.InvalidData
+    /// [`read_exact`]: #method.read_exact
+    #[cfg(feature = "alloc")]
+    fn read_frame_u32_be(
+        &self,
+        buf: &mut Vec<u8>,
+        max_len: Option<usize>,
+    ) -> Result<usize, Self::Error> {
+        let len = u32::from_be_bytes(self.read_array()?);
+        let len = match usize::try_from(len) {
+            Ok(len) if max_len.map_or(true, |max| len <= max) => len,
+            _ => return Err(ErrorKind::InvalidData.into()),
+        };
+
+        buf.clear();
+        buf.resize(len, 0);
+        self.read_exact(buf)?;
+        Ok(len)
+    }
+}
+
+impl<T: ErrorType + ?Sized> ErrorType for &T {
+    type Error = T::Error;
+}
+
+impl<R: Read + ?Sized> Read for &R {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        (**self).read(buf)
+    }
+
+    fn read_uninit(
+        &self,
+        buf: &mut [MaybeUninit<u8>],
+    ) -> Result<usize, Self::Error> {
+        (**self).read_uninit(buf)
+    }
+
+    fn read_exact(&self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        (**self).read_exact(buf)
+    }
+
+    fn skip(&self, n: u64) -> Result<u64, Self::Error> {
+        (**self).skip(n)
+    }
+}
+
+/// A trait for objects which are byte-oriented sinks.
+pub trait Write: ErrorType {
     /// Write a buffer into this object, returning how many bytes were written.
     ///
     /// This function will attempt to write the entire contents of `buf`, but
@@ -553,22 +2948,158 @@ pub trait File {
     ///
     /// # Errors
     ///
-    /// Each call to `write` may generate an I/O error indicating that the
-    /// operation could not be completed.
+    /// Each call to `write` may generate an I/O error indicating that the
+    /// operation could not be completed.
+    ///
+    /// It is **not** considered an error if the entire buffer could not be
+    /// written to this writer.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error>;
+
+    /// Flush this output stream, ensuring that all intermediately buffered
+    /// contents reach their destination.
+    ///
+    /// # Errors
+    ///
+    /// It is considered an error if not all bytes could be written due to
+    /// I/O errors or EOF being reached.
+    fn flush(&mut self) -> Result<(), Self::Error>;
+
+    /// Hints that at least `additional` more bytes are about to be written.
+    ///
+    /// Writers which buffer or allocate can use this to grow their storage
+    /// once up front instead of repeatedly during the following writes. This
+    /// is purely an optimization, writing works the same without it.
+    ///
+    /// The default implementation does nothing.
+    ///
+    /// # Errors
+    ///
+    /// Implementations may return an error if the storage can't be reserved,
+    /// the default implementation never fails.
+    fn reserve(&mut self, additional: usize) -> Result<(), Self::Error> {
+        let _ = additional;
+        Ok(())
+    }
+
+    /// Returns whether [`flush`] is known to do nothing for this writer.
+    ///
+    /// Writers which neither buffer data nor have a destination to sync, like
+    /// [`Sink`], can return `true`, so that wrappers forwarding to several
+    /// writers can skip flushing them. This is purely advisory, calling
+    /// [`flush`] must still be correct, and callers may ignore the hint.
+    ///
+    /// The default implementation returns `false`.
+    ///
+    /// [`flush`]: #method.flush
+    /// [`Sink`]: struct.Sink.html
+    fn is_flush_noop(&self) -> bool {
+        false
+    }
+
+    /// Attempts to write an entire buffer into this writer.
+    ///
+    /// This method will continuously call [`write`] until there is no more
+    /// data to be written or an error is returned. This method will not
+    /// return until the entire buffer has been successfully written or an
+    /// error occurs. The first error generated from this method will be
+    /// returned.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the first error not of kind
+    /// [`ErrorKind::Interrupted`] that [`write`] returns, interrupted writes
+    /// are retried. If [`write`] returns `Ok(0)`, an error of kind
+    /// [`ErrorKind::WriteZero`] is returned.
+    ///
+    /// [`write`]: #method.write
+    /// [`ErrorKind::Interrupted`]: enum.ErrorKind.html#variant.Interrupted
+    /// [`ErrorKind::WriteZero`]: enum.ErrorKind.html#variant.WriteZero
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<(), Self::Error> {
+        while !buf.is_empty() {
+            match self.write(buf) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(n) => buf = &buf[n..],
+                Err(ref err) if err.is_interrupted() => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes `count` zero bytes into this writer.
+    ///
+    /// This is useful for formatting filesystems or clearing regions of a
+    /// file. The default implementation repeatedly writes a small buffer of
+    /// zeros with [`write_all`]. Backends which can allocate zeroed space
+    /// directly, e.g. with `fallocate` or by extending a sparse file, should
+    /// override it.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the first error returned by [`write_all`].
+    /// The number of zeros written so far is unspecified in this case.
     ///
-    /// It is **not** considered an error if the entire buffer could not be
-    /// written to this writer.
-    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error>;
+    /// [`write_all`]: #method.write_all
+    fn write_zeros(&mut self, count: u64) -> Result<(), Self::Error> {
+        let buf = [0u8; COPY_BUF_SIZE];
+        let mut left = count;
+        while left > 0 {
+            let len = cmp::min(left, buf.len() as u64) as usize;
+            self.write_all(&buf[..len])?;
+            left -= len as u64;
+        }
+        Ok(())
+    }
 
-    /// Flush this output stream, ensuring that all intermediately buffered
-    /// contents reach their destination.
+    /// Seeks to the end of this writer and writes an entire buffer there.
+    ///
+    /// This emulates append mode on writers which don't support it. Unlike a
+    /// writer opened for appending, the seek and the write are separate
+    /// operations, so data written by someone else in between may be
+    /// overwritten. It is only a reliable append if the writer isn't shared.
     ///
     /// # Errors
     ///
-    /// It is considered an error if not all bytes could be written due to
-    /// I/O errors or EOF being reached.
-    fn flush(&mut self) -> Result<(), Self::Error>;
+    /// This function will return the first error returned by [`seek`] or
+    /// [`write_all`].
+    ///
+    /// [`seek`]: trait.Seek.html#method.seek
+    /// [`write_all`]: #method.write_all
+    fn append_all(&mut self, buf: &[u8]) -> Result<(), Self::Error>
+    where
+        Self: Seek,
+    {
+        self.seek(SeekFrom::End(0))?;
+        self.write_all(buf)
+    }
+
+    /// Returns a guard which flushes this writer when it is dropped.
+    ///
+    /// Flushes made by the guard capture errors instead of propagating them,
+    /// they can be retrieved with [`FlushGuard::take_error`]. See
+    /// [`FlushGuard`] for details.
+    ///
+    /// [`FlushGuard`]: struct.FlushGuard.html
+    /// [`FlushGuard::take_error`]: struct.FlushGuard.html#method.take_error
+    fn flush_guard(&mut self) -> FlushGuard<'_, Self> {
+        FlushGuard::new(self)
+    }
+
+    /// Returns an adapter implementing `core::fmt::Write` for this writer.
+    ///
+    /// This bridges APIs which format text into a `fmt::Write`. Since
+    /// `fmt::Error` carries no details, the error of the writer is kept by
+    /// the adapter, see [`FmtWriter`].
+    ///
+    /// [`FmtWriter`]: struct.FmtWriter.html
+    fn as_fmt_write(&mut self) -> FmtWriter<'_, Self> {
+        FmtWriter::new(self)
+    }
+}
 
+/// The `Seek` trait provides a cursor which can be moved within a stream of
+/// bytes.
+pub trait Seek: ErrorType {
     /// Seek to an offset, in bytes, in a stream.
     ///
     /// A seek beyond the end of a stream is allowed, but implementation
@@ -584,6 +3115,290 @@ pub trait File {
     ///
     /// [`SeekFrom::Start`]: enum.SeekFrom.html#variant.Start
     fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error>;
+
+    /// Returns the current position within the stream.
+    ///
+    /// This is equivalent to `self.seek(SeekFrom::Current(0))`.
+    ///
+    /// # Errors
+    ///
+    /// See [`seek`].
+    ///
+    /// [`seek`]: #method.seek
+    fn stream_position(&mut self) -> Result<u64, Self::Error> {
+        self.seek(SeekFrom::Current(0))
+    }
+
+    /// Returns the length of the stream, in bytes.
+    ///
+    /// The default implementation seeks to the end of the stream and back to
+    /// the current position.
+    ///
+    /// # Errors
+    ///
+    /// See [`seek`]. The position within the stream is unspecified if an error
+    /// is returned.
+    ///
+    /// [`seek`]: #method.seek
+    fn stream_len(&mut self) -> Result<u64, Self::Error> {
+        let pos = self.stream_position()?;
+        let len = self.seek(SeekFrom::End(0))?;
+        if pos != len {
+            self.seek(SeekFrom::Start(pos))?;
+        }
+        Ok(len)
+    }
+
+    /// Returns the number of bytes from the current position to the end of
+    /// the stream.
+    ///
+    /// This is useful for progress reporting or to size a buffer for the
+    /// rest of the stream. The position is restored afterwards. If it is past
+    /// the end of the stream, 0 is returned.
+    ///
+    /// # Errors
+    ///
+    /// See [`stream_len`].
+    ///
+    /// [`stream_len`]: #method.stream_len
+    fn remaining(&mut self) -> Result<u64, Self::Error> {
+        let pos = self.stream_position()?;
+        let len = self.stream_len()?;
+        Ok(len.saturating_sub(pos))
+    }
+}
+
+/// The number of times the retrying provided methods, like
+/// [`Fs::open_retry`], repeat an operation which failed with an error of kind
+/// [`ErrorKind::Interrupted`] before giving up.
+///
+/// [`Fs::open_retry`]: trait.Fs.html#method.open_retry
+/// [`ErrorKind::Interrupted`]: enum.ErrorKind.html#variant.Interrupted
+pub const MAX_INTERRUPTED_RETRIES: usize = 16;
+
+/// The number of symbolic links resolved by the provided path resolution
+/// methods, like [`Fs::canonicalize_into`], before they fail with an error
+/// of kind [`ErrorKind::FilesystemLoop`].
+///
+/// [`Fs::canonicalize_into`]: trait.Fs.html#method.canonicalize_into
+/// [`ErrorKind::FilesystemLoop`]: enum.ErrorKind.html#variant.FilesystemLoop
+pub const MAX_SYMLINK_HOPS: usize = 40;
+
+/// The longest temporary name used by [`Fs::rename_case`], like `NAME_MAX`.
+///
+/// [`Fs::rename_case`]: trait.Fs.html#method.rename_case
+const RENAME_CASE_NAME_MAX: usize = 255;
+
+/// The suffix of the temporary name used by [`Fs::rename_case`].
+///
+/// [`Fs::rename_case`]: trait.Fs.html#method.rename_case
+const RENAME_CASE_SUFFIX: &[u8] = b".genfs-rename-case";
+
+/// The size of the buffer used by the provided methods which copy data.
+const COPY_BUF_SIZE: usize = 4096;
+
+/// Copies the entire contents of `reader` into `writer`, returning the number
+/// of bytes copied.
+#[cfg(feature = "alloc")]
+pub(crate) fn copy_data<R, W>(
+    reader: &R,
+    writer: &mut W,
+) -> Result<u64, R::Error>
+where
+    R: Read + ?Sized,
+    W: Write<Error = R::Error> + ?Sized,
+{
+    copy_data_with_progress(reader, writer, |_| {})
+}
+
+/// Reads and discards up to `n` bytes from `reader`, returning how many bytes
+/// were skipped.
+pub(crate) fn skip_data<R: Read + ?Sized>(
+    reader: &R,
+    n: u64,
+) -> Result<u64, R::Error> {
+    let mut buf = [0u8; COPY_BUF_SIZE];
+    let mut skipped = 0;
+    while skipped < n {
+        let len = cmp::min(n - skipped, buf.len() as u64) as usize;
+        match reader.read(&mut buf[..len])? {
+            0 => break,
+            read => skipped += read as u64,
+        }
+    }
+    Ok(skipped)
+}
+
+/// Returns whether `a` and `b` yield the same bytes until their end.
+///
+/// Both readers are read in lockstep, one chunk at a time, so the contents
+/// are never held in memory as a whole. Reading stops at the first
+/// difference, including one reader ending before the other, and the rest of
+/// the streams is left unread.
+///
+/// # Errors
+///
+/// This function will return the first error not of kind
+/// [`ErrorKind::Interrupted`] that either reader returns.
+///
+/// [`ErrorKind::Interrupted`]: enum.ErrorKind.html#variant.Interrupted
+pub fn contents_equal<A, B>(a: &A, b: &B) -> Result<bool, A::Error>
+where
+    A: Read + ?Sized,
+    B: Read<Error = A::Error> + ?Sized,
+{
+    compare_data(a, b).map(|len| len.is_some())
+}
+
+/// Reads `a` and `b` to the end, returning their length if their contents are
+/// equal.
+fn compare_data<A, B>(a: &A, b: &B) -> Result<Option<u64>, A::Error>
+where
+    A: Read + ?Sized,
+    B: Read<Error = A::Error> + ?Sized,
+{
+    let mut buf_a = [0u8; COPY_BUF_SIZE];
+    let mut buf_b = [0u8; COPY_BUF_SIZE];
+    let mut len = 0;
+    loop {
+        let n = match read_retrying(a, &mut buf_a)? {
+            0 => break,
+            n => n,
+        };
+        match b.read_exact(&mut buf_b[..n]) {
+            Err(ref err) if err.kind() == ErrorKind::UnexpectedEof => {
+                return Ok(None)
+            }
+            result => result?,
+        }
+        if buf_a[..n] != buf_b[..n] {
+            return Ok(None);
+        }
+        len += n as u64;
+    }
+    match read_retrying(b, &mut buf_b[..1])? {
+        0 => Ok(Some(len)),
+        _ => Ok(None),
+    }
+}
+
+/// Reads into `buf` like [`Read::read`], retrying reads which fail with an
+/// error of kind [`ErrorKind::Interrupted`].
+///
+/// [`Read::read`]: trait.Read.html#tymethod.read
+/// [`ErrorKind::Interrupted`]: enum.ErrorKind.html#variant.Interrupted
+fn read_retrying<R: Read + ?Sized>(
+    reader: &R,
+    buf: &mut [u8],
+) -> Result<usize, R::Error> {
+    loop {
+        match reader.read(buf) {
+            Err(ref err) if err.is_interrupted() => {}
+            result => return result,
+        }
+    }
+}
+
+/// Creates `path` and its missing parents like [`Fs::create_dir_all`], calling
+/// `created` with each directory created by this call, from the shallowest to
+/// the deepest one.
+///
+/// [`Fs::create_dir_all`]: trait.Fs.html#method.create_dir_all
+fn create_missing_dirs<F, C>(
+    fs: &mut F,
+    path: &F::Path,
+    mut created: C,
+) -> Result<(), F::Error>
+where
+    F: Fs + ?Sized,
+    C: FnMut(&F::Path) -> Result<(), F::Error>,
+{
+    let mut missing = 0;
+    let mut current = Some(path);
+    while let Some(path) = current {
+        if path.as_bytes().is_empty() {
+            break;
+        }
+        match fs.metadata(path) {
+            Ok(ref metadata) if metadata.is_dir() => break,
+            Ok(_) if missing == 0 => {
+                return Err(ErrorKind::AlreadyExists.into())
+            }
+            Ok(_) => return Err(ErrorKind::NotADirectory.into()),
+            Err(ref err) if err.kind() == ErrorKind::NotFound => {
+                missing += 1;
+                current = path.parent();
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    let options = DirOptions::new();
+    for depth in (0..missing).rev() {
+        let dir = match iter::successors(Some(path), |p| p.parent()).nth(depth)
+        {
+            Some(dir) => dir,
+            None => continue,
+        };
+        match fs.create_dir(dir, &options) {
+            Ok(()) => created(dir)?,
+            // someone else created it in the meantime
+            Err(ref err)
+                if err.kind() == ErrorKind::AlreadyExists
+                    && fs.metadata(dir).is_ok_and(|m| m.is_dir()) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+/// Copies the entire contents of `reader` into `writer`, calling `progress`
+/// with the number of bytes copied so far after every chunk.
+fn copy_data_with_progress<R, W, P>(
+    reader: &R,
+    writer: &mut W,
+    mut progress: P,
+) -> Result<u64, R::Error>
+where
+    R: Read + ?Sized,
+    W: Write<Error = R::Error> + ?Sized,
+    P: FnMut(u64),
+{
+    let mut buf = [0u8; COPY_BUF_SIZE];
+    let mut copied = 0;
+    loop {
+        match reader.read(&mut buf)? {
+            0 => return Ok(copied),
+            n => {
+                writer.write_all(&buf[..n])?;
+                copied += n as u64;
+                progress(copied);
+            }
+        }
+    }
+}
+
+/// An opaque position within a directory listing.
+///
+/// It is returned by [`Dir::tell`] and can be passed to [`Dir::seek`] to resume
+/// iteration later, possibly on a different iterator over the same directory.
+/// Its value is only meaningful to the filesystem that produced it.
+///
+/// [`Dir::tell`]: trait.Dir.html#method.tell
+/// [`Dir::seek`]: trait.Dir.html#method.seek
+#[derive(Copy, PartialEq, Eq, Clone, Debug, Hash)]
+pub struct DirPos(u64);
+
+impl DirPos {
+    /// Creates a position from a filesystem-specific cookie.
+    pub fn from_raw(cookie: u64) -> Self {
+        DirPos(cookie)
+    }
+
+    /// Returns the filesystem-specific cookie of this position.
+    pub fn into_raw(self) -> u64 {
+        self.0
+    }
 }
 
 /// Iterator over the entries in a directory.
@@ -600,7 +3415,61 @@ pub trait File {
 ///
 /// [`read_dir`]: trait.Fs.html#method.read_dir
 /// [`DirEntry`]: trait.DirEntry.html
-pub trait Dir<T: DirEntry, E>: Iterator<Item = Result<T, E>> {}
+pub trait Dir<T: DirEntry, E: Error>: Iterator<Item = Result<T, E>> {
+    /// Resets the position of this iterator to the beginning of the directory,
+    /// mirroring `rewinddir`.
+    ///
+    /// Entries that were added to or removed from the directory since it was
+    /// opened may or may not appear after rewinding. Backends that read the
+    /// whole listing up front can simply restart iteration over it.
+    ///
+    /// # Errors
+    ///
+    /// The default implementation returns an error of kind
+    /// [`ErrorKind::Unsupported`].
+    ///
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    fn rewind(&mut self) -> Result<(), E> {
+        Err(ErrorKind::Unsupported.into())
+    }
+
+    /// Returns the current position of this iterator, mirroring `telldir`.
+    ///
+    /// The position can be passed to [`seek`] on this or any other iterator
+    /// over the same directory to resume iteration with the entry that would
+    /// have been yielded next.
+    ///
+    /// [`seek`]: #method.seek
+    ///
+    /// # Errors
+    ///
+    /// The default implementation returns an error of kind
+    /// [`ErrorKind::Unsupported`].
+    ///
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    fn tell(&self) -> Result<DirPos, E> {
+        Err(ErrorKind::Unsupported.into())
+    }
+
+    /// Moves this iterator to a position previously returned by [`tell`],
+    /// mirroring `seekdir`.
+    ///
+    /// If entries were added to or removed from the directory in the
+    /// meantime, entries may be skipped or yielded twice.
+    ///
+    /// [`tell`]: #method.tell
+    ///
+    /// # Errors
+    ///
+    /// The default implementation returns an error of kind
+    /// [`ErrorKind::Unsupported`].
+    ///
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    fn seek(&mut self, pos: DirPos) -> Result<(), E> {
+        let _ = pos;
+        Err(ErrorKind::Unsupported.into())
+    }
+}
 
 /// Entries returned by the [`Dir`] iterator.
 ///
@@ -612,17 +3481,17 @@ pub trait Dir<T: DirEntry, E>: Iterator<Item = Result<T, E>> {}
 pub trait DirEntry {
     /// The borrowed path slice that represents a relative or absolute path on
     /// the filesystem.
-    type Path: ?Sized;
+    type Path: ?Sized + Path;
     /// The owned path that represents a relative or absolute path on
     /// the filesystem.
-    type PathOwned;
+    type PathOwned: PathBuf<Self::Path>;
     /// The type that represents a files metadata on the filesystem.
-    type Metadata;
+    type Metadata: Metadata;
     /// The type that represents the union of all possible filetypes.
-    type FileType;
+    type FileType: FileType;
     /// The type that represents the set of all errors that can occur during
     /// reading or writing.
-    type Error;
+    type Error: Error;
 
     /// Returns the full path to the file that this entry represents.
     ///
@@ -645,4 +3514,586 @@ pub trait DirEntry {
     /// Returns the bare file name of this directory entry without any other
     /// leading path component.
     fn file_name(&self) -> &Self::Path;
+
+    /// Returns the raw bytes of the bare file name of this directory entry.
+    ///
+    /// Unlike [`path`], this doesn't allocate, which makes it suitable for
+    /// matching names while walking large trees. The bytes are returned as
+    /// stored, without any conversion which could lose information.
+    ///
+    /// [`path`]: #method.path
+    fn file_name_bytes(&self) -> &[u8] {
+        self.file_name().as_bytes()
+    }
+
+    /// Returns the key by which [`Fs::read_dir_sorted`] orders entries.
+    ///
+    /// The default key sorts directories before all other entries, and then
+    /// bytewise by [`file_name_bytes`]. Backends can override it for a
+    /// custom, but still deterministic, ordering.
+    ///
+    /// # Errors
+    ///
+    /// The default implementation returns an error if the file type of the
+    /// entry can't be determined, see [`file_type`].
+    ///
+    /// [`Fs::read_dir_sorted`]: trait.Fs.html#method.read_dir_sorted
+    /// [`file_name_bytes`]: #method.file_name_bytes
+    /// [`file_type`]: #method.file_type
+    fn sort_key(&self) -> Result<(bool, &[u8]), Self::Error> {
+        let is_dir = self.file_type()?.is_dir();
+        Ok((!is_dir, self.file_name_bytes()))
+    }
+}
+
+/// Metadata information about a file.
+///
+/// This structure is returned from the [`metadata`] or [`symlink_metadata`]
+/// function or method and represents known metadata about a file such as its
+/// permissions, size, modification times, etc.
+///
+/// [`metadata`]: trait.Fs.html#method.metadata
+/// [`symlink_metadata`]: trait.Fs.html#method.symlink_metadata
+pub trait Metadata {
+    /// The type that represents the union of all possible filetypes.
+    type FileType: FileType;
+
+    /// Returns the file type for this metadata.
+    fn file_type(&self) -> Self::FileType;
+
+    /// Returns the size of the file, in bytes, this metadata is for.
+    ///
+    /// The size of a directory is defined by the backend, it can be zero, the
+    /// number of entries, or the space allocated for the directory listing.
+    fn len(&self) -> u64;
+
+    /// Returns `true` if the file this metadata is for is empty, i.e. if
+    /// [`len`] returns 0.
+    ///
+    /// [`len`]: #method.len
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if this metadata is for a directory.
+    fn is_dir(&self) -> bool {
+        self.file_type().is_dir()
+    }
+
+    /// Returns `true` if this metadata is for a regular file.
+    fn is_file(&self) -> bool {
+        self.file_type().is_file()
+    }
+
+    /// Returns `true` if this metadata is for a symbolic link.
+    fn is_symlink(&self) -> bool {
+        self.file_type().is_symlink()
+    }
+
+    /// Returns the last access time of the file, if the filesystem records
+    /// it.
+    ///
+    /// The default implementation returns `None`.
+    fn accessed(&self) -> Option<SystemTime> {
+        None
+    }
+
+    /// Returns the last modification time of the file, if the filesystem
+    /// records it.
+    ///
+    /// The default implementation returns `None`.
+    fn modified(&self) -> Option<SystemTime> {
+        None
+    }
+}
+
+/// Unix-specific extensions to [`Metadata`].
+///
+/// [`Metadata`]: trait.Metadata.html
+pub trait UnixMetadataExt: Metadata {
+    /// Returns the permission bits of the file, like the lower 12 bits of
+    /// `st_mode`.
+    fn mode(&self) -> u32;
+
+    /// Returns the number of blocks allocated to the file, in 512-byte units,
+    /// like `st_blocks`.
+    ///
+    /// The unit is always 512 bytes, regardless of [`block_size`] or the
+    /// block size of the underlying device. For sparse files, this can be
+    /// less than [`len`] implies, which allows computing the actual disk
+    /// usage.
+    ///
+    /// The default implementation assumes no sparse regions and returns the
+    /// number of 512-byte units needed to hold [`len`] bytes.
+    ///
+    /// [`block_size`]: #method.block_size
+    /// [`len`]: trait.Metadata.html#method.len
+    fn blocks(&self) -> u64 {
+        self.len().div_ceil(512)
+    }
+
+    /// Returns the preferred block size for I/O on the file, like
+    /// `st_blksize`.
+    ///
+    /// The default implementation returns 4096.
+    fn block_size(&self) -> u64 {
+        4096
+    }
+
+    /// Returns the number of hard links pointing to the file, like
+    /// `st_nlink`.
+    ///
+    /// The default implementation returns 1, as for filesystems without hard
+    /// links.
+    fn nlink(&self) -> u64 {
+        1
+    }
+}
+
+/// A structure representing a type of file with accessors for each file type.
+///
+/// It is returned by the [`Metadata::file_type`] method.
+///
+/// [`Metadata::file_type`]: trait.Metadata.html#method.file_type
+pub trait FileType {
+    /// Test whether this file type represents a directory.
+    fn is_dir(&self) -> bool;
+
+    /// Test whether this file type represents a regular file.
+    fn is_file(&self) -> bool;
+
+    /// Test whether this file type represents a symbolic link.
+    fn is_symlink(&self) -> bool;
+
+    /// Test whether this file type represents a named pipe.
+    ///
+    /// The default implementation returns `false`.
+    fn is_fifo(&self) -> bool {
+        false
+    }
+
+    /// Test whether this file type represents a unix domain socket.
+    ///
+    /// The default implementation returns `false`.
+    fn is_socket(&self) -> bool {
+        false
+    }
+
+    /// Test whether this file type represents a character device.
+    ///
+    /// The default implementation returns `false`.
+    fn is_char_device(&self) -> bool {
+        false
+    }
+
+    /// Test whether this file type represents a block device.
+    ///
+    /// The default implementation returns `false`.
+    fn is_block_device(&self) -> bool {
+        false
+    }
+}
+
+/// How a hard link created by [`Fs::hard_link_checked`] was made.
+///
+/// [`Fs::hard_link_checked`]: trait.Fs.html#method.hard_link_checked
+#[derive(Copy, PartialEq, Eq, Clone, Debug, Hash)]
+pub enum LinkKind {
+    /// A real hard link, both paths refer to the same file.
+    True,
+    /// The link was emulated by copying the file, the paths refer to
+    /// independent files with the same contents.
+    EmulatedCopy,
+}
+
+/// The kind of a special file created by [`Fs::mknod`].
+///
+/// [`Fs::mknod`]: trait.Fs.html#method.mknod
+#[derive(Copy, PartialEq, Eq, Clone, Debug, Hash)]
+pub enum SpecialKind {
+    /// A named pipe.
+    Fifo,
+    /// A unix domain socket.
+    Socket,
+    /// A character device.
+    CharDevice,
+    /// A block device.
+    BlockDevice,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use mock::{Cursor, MockDir, MockMetadata, MockPath, MockType};
+    use {
+        Dir, DirEntry, ErrorKind, File, OpenOptions, OpenOptionsError, Read,
+        Seek, SeekFrom, UnixMetadataExt, Write,
+    };
+
+    fn names(dir: &mut MockDir) -> Vec<Vec<u8>> {
+        dir.map(|entry| entry.unwrap().file_name().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn dir_rewind_yields_same_entries() {
+        let mut dir = MockDir::new(&[
+            (b"a", MockType::File),
+            (b"b", MockType::Dir),
+            (b"c", MockType::Symlink),
+        ]);
+        let first = names(&mut dir);
+        assert_eq!(first.len(), 3);
+        assert!(dir.next().is_none());
+
+        dir.rewind().unwrap();
+        assert_eq!(names(&mut dir), first);
+    }
+
+    #[test]
+    fn read_array_reads_magic_number() {
+        let source = Cursor::new(b"\x7fELF\x02");
+        assert_eq!(source.read_array::<4>().unwrap(), *b"\x7fELF");
+        assert_eq!(source.read_array::<1>().unwrap(), [2]);
+    }
+
+    #[test]
+    fn read_array_rejects_short_source() {
+        let source = Cursor::new(b"\x7fEL");
+        assert_eq!(source.read_array::<4>(), Err(ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn read_frame_reads_valid_frame() {
+        let source = Cursor::new(b"\0\0\0\x03abcd");
+        let mut buf = Vec::new();
+        assert_eq!(source.read_frame_u32_be(&mut buf, None).unwrap(), 3);
+        assert_eq!(buf, b"abc");
+        assert_eq!(source.position(), 7);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn read_frame_rejects_truncated_frame() {
+        let mut buf = Vec::new();
+        let source = Cursor::new(b"\0\0\0\x05ab");
+        let result = source.read_frame_u32_be(&mut buf, None);
+        assert_eq!(result, Err(ErrorKind::UnexpectedEof));
+
+        let source = Cursor::new(b"\0\0\0\x05abcde");
+        let result = source.read_frame_u32_be(&mut buf, Some(4));
+        assert_eq!(result, Err(ErrorKind::InvalidData));
+        assert_eq!(source.position(), 4);
+    }
+
+    #[test]
+    fn open_options_validation() {
+        let options = || OpenOptions::<u32>::new();
+        assert_eq!(
+            options().read(true).create(true).validate(),
+            Err(OpenOptionsError::CreateWithoutWrite)
+        );
+        assert_eq!(
+            options().read(true).create_new(true).validate(),
+            Err(OpenOptionsError::CreateNewWithoutWrite)
+        );
+        assert_eq!(
+            options().append(true).truncate(true).validate(),
+            Err(OpenOptionsError::TruncateWithoutWrite)
+        );
+        assert_eq!(
+            ErrorKind::from(OpenOptionsError::TruncateWithoutWrite),
+            ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            options().write(true).create(true).truncate(true).validate(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn sparse_file_reports_fewer_blocks() {
+        let dense = MockMetadata {
+            file_type: MockType::File,
+            len: 1 << 20,
+            blocks: None,
+        };
+        assert_eq!(dense.blocks(), 2048);
+        assert_eq!(dense.block_size(), 4096);
+
+        let sparse = MockMetadata {
+            blocks: Some(8),
+            ..dense
+        };
+        assert!(sparse.blocks() * 512 < sparse.len);
+        let partial = MockMetadata { len: 513, ..dense };
+        assert_eq!(partial.blocks(), 2);
+    }
+
+    #[test]
+    fn skip_discards_bytes() {
+        let reader = Cursor::new(b"0123456789").chunked(3);
+        assert_eq!(reader.skip(4).unwrap(), 4);
+        let mut buf = [0; 1];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"4");
+        assert_eq!(reader.skip(0).unwrap(), 0);
+        assert_eq!(reader.skip(10).unwrap(), 5);
+        assert_eq!(reader.skip(1).unwrap(), 0);
+    }
+
+    #[test]
+    fn seek_from_add_shifts_each_variant() {
+        assert_eq!(SeekFrom::from_start(10) + 5, SeekFrom::Start(15));
+        assert_eq!(SeekFrom::from_start(10) + -4, SeekFrom::Start(6));
+        assert_eq!(SeekFrom::from_end(-8) + 3, SeekFrom::End(-5));
+        assert_eq!(SeekFrom::from_current(2) + -7, SeekFrom::Current(-5));
+
+        assert_eq!(SeekFrom::Start(3) + -10, SeekFrom::Start(0));
+        assert_eq!(SeekFrom::Start(u64::MAX) + 1, SeekFrom::Start(u64::MAX));
+        assert_eq!(SeekFrom::End(i64::MIN) + -1, SeekFrom::End(i64::MIN));
+        assert_eq!(
+            SeekFrom::Current(i64::MAX) + 1,
+            SeekFrom::Current(i64::MAX)
+        );
+
+        assert_eq!(SeekFrom::Start(u64::MAX).offset(), i64::MAX);
+        assert_eq!(SeekFrom::End(-3).offset(), -3);
+    }
+
+    #[test]
+    fn file_name_bytes_are_raw() {
+        let names: [&[u8]; 2] = [b"caf\xc3\xa9", b"\xff\xfe.bin"];
+        let dir = MockDir::new(&[
+            (names[0], MockType::File),
+            (names[1], MockType::File),
+        ]);
+        for (entry, name) in dir.zip(names.iter()) {
+            assert_eq!(entry.unwrap().file_name_bytes(), *name);
+        }
+    }
+
+    #[test]
+    fn read_exact_retries_interrupted() {
+        let reader = Cursor::new(b"0123456789").chunked(3);
+        for outcome in &[None, Some(ErrorKind::Interrupted), None] {
+            reader.fail(*outcome);
+            reader.fail(Some(ErrorKind::Interrupted));
+        }
+        let mut buf = [0; 10];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"0123456789");
+
+        let reader = Cursor::new(b"0123456789").chunked(3);
+        reader.fail(None);
+        reader.fail(Some(ErrorKind::Other));
+        assert_eq!(reader.read_exact(&mut buf), Err(ErrorKind::Other));
+    }
+
+    #[test]
+    fn write_all_retries_interrupted() {
+        let mut writer = Cursor::new(b"").chunked(4);
+        writer.fail(Some(ErrorKind::Interrupted));
+        writer.fail(None);
+        writer.fail(Some(ErrorKind::Interrupted));
+        writer.fail(Some(ErrorKind::Interrupted));
+        writer.write_all(b"0123456789").unwrap();
+        assert_eq!(writer.data(), b"0123456789");
+        assert_eq!(writer.writes, 6);
+
+        writer.fail(Some(ErrorKind::WriteZero));
+        assert_eq!(writer.write_all(b"x"), Err(ErrorKind::WriteZero));
+    }
+
+    #[test]
+    fn read_uninit_initializes_read_bytes() {
+        use core::mem::MaybeUninit;
+
+        let reader = Cursor::new(b"abcdef").chunked(4);
+        let mut buf = [MaybeUninit::<u8>::uninit(); 8];
+        let n = reader.read_uninit(&mut buf).unwrap();
+        assert_eq!(n, 4);
+        // SAFETY: the first `n` elements were initialized by `read_uninit`
+        let read: std::vec::Vec<u8> = buf[..n]
+            .iter()
+            .map(|b| unsafe { b.assume_init() })
+            .collect();
+        assert_eq!(read, b"abcd");
+
+        assert_eq!(reader.read_uninit(&mut buf).unwrap(), 2);
+        assert_eq!(unsafe { buf[1].assume_init() }, b'f');
+        assert_eq!(reader.read_uninit(&mut buf).unwrap(), 0);
+        assert_eq!(reader.read_uninit(&mut []).unwrap(), 0);
+    }
+
+    #[test]
+    fn shared_readers_advance_one_cursor() {
+        let cursor = Cursor::new(b"abcdef");
+        let (first, second) = (&cursor, &cursor);
+        let mut buf = [0; 2];
+        first.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ab");
+        second.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"cd");
+
+        // adapters taking ownership of a reader accept a borrowed one
+        let limited = (&cursor).take(1);
+        assert_eq!(limited.read(&mut buf).unwrap(), 1);
+        assert_eq!(limited.read(&mut buf).unwrap(), 0);
+        assert_eq!(cursor.position(), 5);
+        assert_eq!(first.skip(4).unwrap(), 1);
+    }
+
+    #[test]
+    fn write_zeros_writes_count_zeros() {
+        let mut writer = Cursor::new(b"abc");
+        writer.seek(SeekFrom::Start(1)).unwrap();
+        writer.write_zeros(0).unwrap();
+        assert_eq!(writer.data(), b"abc");
+
+        let count = ::COPY_BUF_SIZE as u64 * 2 + 5;
+        writer.write_zeros(count).unwrap();
+        assert_eq!(writer.position(), count + 1);
+        let data = writer.data();
+        assert_eq!(data.len() as u64, count + 1);
+        assert_eq!(data[0], b'a');
+        assert!(data[1..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn read_exact_reporting_counts_partial_read() {
+        let reader = Cursor::new(b"abcde").chunked(2);
+        let mut buf = [0; 8];
+        assert_eq!(
+            reader.read_exact_reporting(&mut buf),
+            Err((5, ErrorKind::UnexpectedEof))
+        );
+        assert_eq!(&buf[..5], b"abcde");
+
+        // errors other than "end of file" report the progress as well
+        let reader = Cursor::new(b"abcdef").chunked(2);
+        reader.fail(None);
+        reader.fail(Some(ErrorKind::Interrupted));
+        reader.fail(Some(ErrorKind::Other));
+        assert_eq!(
+            reader.read_exact_reporting(&mut buf),
+            Err((2, ErrorKind::Other))
+        );
+
+        let reader = Cursor::new(b"abcd");
+        assert_eq!(reader.read_exact_reporting(&mut buf[..4]), Ok(()));
+        assert_eq!(&buf[..4], b"abcd");
+    }
+
+    #[test]
+    fn remaining_restores_position() {
+        let mut cursor = Cursor::new(b"abcdef");
+        assert_eq!(cursor.remaining().unwrap(), 6);
+        cursor.seek(SeekFrom::Start(2)).unwrap();
+        assert_eq!(cursor.remaining().unwrap(), 4);
+        assert_eq!(cursor.position(), 2);
+
+        cursor.seek(SeekFrom::End(0)).unwrap();
+        assert_eq!(cursor.remaining().unwrap(), 0);
+        cursor.seek(SeekFrom::Start(10)).unwrap();
+        assert_eq!(cursor.remaining().unwrap(), 0);
+        assert_eq!(cursor.position(), 10);
+    }
+
+    #[test]
+    fn sort_key_puts_directories_first() {
+        let dir = MockDir::new(&[
+            (b"b", MockType::File),
+            (b"z", MockType::Dir),
+            (b"a", MockType::Symlink),
+            (b"c", MockType::Dir),
+        ]);
+        let mut entries: Vec<_> = dir.map(Result::unwrap).collect();
+        entries
+            .sort_by(|a, b| a.sort_key().unwrap().cmp(&b.sort_key().unwrap()));
+        let names: Vec<_> = entries
+            .iter()
+            .map(|entry| entry.file_name_bytes())
+            .collect();
+        assert_eq!(names, [&b"c"[..], b"z", b"a", b"b"]);
+        assert_eq!(entries[0].sort_key(), Ok((false, &b"c"[..])));
+        assert_eq!(entries[2].sort_key(), Ok((true, &b"a"[..])));
+    }
+
+    #[test]
+    fn sort_key_reports_unknown_file_type() {
+        struct Vanished;
+
+        impl DirEntry for Vanished {
+            type Path = [u8];
+            type PathOwned = MockPath;
+            type Metadata = MockMetadata;
+            type FileType = MockType;
+            type Error = ErrorKind;
+
+            fn path(&self) -> MockPath {
+                MockPath(b"gone".to_vec())
+            }
+
+            fn metadata(&self) -> Result<MockMetadata, ErrorKind> {
+                Err(ErrorKind::NotFound)
+            }
+
+            fn file_type(&self) -> Result<MockType, ErrorKind> {
+                Err(ErrorKind::NotFound)
+            }
+
+            fn file_name(&self) -> &[u8] {
+                b"gone"
+            }
+        }
+
+        assert_eq!(Vanished.sort_key(), Err(ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn read_at_most_caps_read() {
+        let file = Cursor::new(b"abcdefgh");
+        let mut buf = [0; 8];
+        assert_eq!(file.read_at_most(&mut buf, 3).unwrap(), 3);
+        assert_eq!(&buf, b"abc\0\0\0\0\0");
+        assert_eq!(file.read_at_most(&mut buf[..2], 5).unwrap(), 2);
+        assert_eq!(file.read_at_most(&mut buf, 0).unwrap(), 0);
+        assert_eq!(file.position(), 5);
+    }
+
+    #[test]
+    fn contents_equal_compares_streams() {
+        let long = [7u8; ::COPY_BUF_SIZE + 3];
+        let a = Cursor::new(&long);
+        let b = Cursor::new(&long).chunked(100);
+        assert!(::contents_equal(&a, &b).unwrap());
+
+        let mut changed = long;
+        changed[1] = 0;
+        let a = Cursor::new(&long);
+        let b = Cursor::new(&changed);
+        assert!(!::contents_equal(&a, &b).unwrap());
+        // the rest isn't read after the first difference
+        assert_eq!(a.position(), ::COPY_BUF_SIZE as u64);
+
+        let a = Cursor::new(b"abc");
+        assert!(!::contents_equal(&a, &Cursor::new(b"abcd")).unwrap());
+        assert!(!::contents_equal(&Cursor::new(b"abcd"), &a).unwrap());
+        assert!(::contents_equal(&Cursor::new(b""), &Cursor::new(b"")).unwrap());
+    }
+
+    #[test]
+    fn contents_equal_retries_interrupted_reads() {
+        let a = Cursor::new(b"abc");
+        let b = Cursor::new(b"abc");
+        a.fail(Some(ErrorKind::Interrupted));
+        // the probe for more data in `b` at its end is interrupted
+        b.fail(None);
+        b.fail(Some(ErrorKind::Interrupted));
+        assert!(::contents_equal(&a, &b).unwrap());
+        assert_eq!(b.reads.get(), 3);
+    }
 }
@@ -0,0 +1,2441 @@
+//! An in-memory filesystem.
+//!
+//! [`MemFs`] is a reference implementation of the traits in this crate which
+//! keeps the whole tree in memory. It's mostly useful as a test double for
+//! code that is generic over a filesystem.
+//!
+//! Paths are byte slices separated by `/`. Relative paths are resolved
+//! against the current directory, which is the root directory initially.
+//!
+//! [`MemFs`]: struct.MemFs.html
+
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::cmp;
+use core::convert::TryFrom;
+
+use {
+    Dir, DirEntry, DirOptions, DirPos, ErrorKind, ErrorType, File, FileTimes,
+    FileType, Fs, Metadata, OpenOptions, Path, PathBuf, Read, Seek, SeekFrom,
+    SpecialKind, SystemTime, UnixMetadataExt, Write, MAX_SYMLINK_HOPS,
+};
+
+const ROOT: &[u8] = b"/";
+
+type Nodes = BTreeMap<Vec<u8>, Rc<RefCell<Inode>>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Inode {
+    file_type: MemFileType,
+    mode: u32,
+    nlink: u64,
+    accessed: SystemTime,
+    modified: SystemTime,
+    /// The contents of a file or the target of a symbolic link.
+    data: Vec<u8>,
+}
+
+impl Inode {
+    fn new(file_type: MemFileType, mode: u32) -> Self {
+        Inode {
+            file_type,
+            mode,
+            nlink: 0,
+            accessed: SystemTime::UNIX_EPOCH,
+            modified: SystemTime::UNIX_EPOCH,
+            data: Vec::new(),
+        }
+    }
+
+    fn metadata(&self) -> MemMetadata {
+        MemMetadata {
+            file_type: self.file_type,
+            len: self.data.len() as u64,
+            mode: self.mode,
+            nlink: self.nlink,
+            accessed: self.accessed,
+            modified: self.modified,
+        }
+    }
+}
+
+/// An in-memory filesystem.
+///
+/// All operations are performed on a tree of nodes held in memory. Hard links
+/// share their node, so changes made through one link are visible through all
+/// others.
+#[derive(Debug)]
+pub struct MemFs {
+    nodes: RefCell<Nodes>,
+    faults: Faults,
+    /// The resolved path of the current directory.
+    cwd: Vec<u8>,
+}
+
+/// A snapshot of the whole tree of a [`MemFs`].
+///
+/// Snapshots are created with [`MemFs::snapshot`] and can be restored any
+/// number of times with [`MemFs::restore`].
+///
+/// [`MemFs`]: struct.MemFs.html
+/// [`MemFs::snapshot`]: struct.MemFs.html#method.snapshot
+/// [`MemFs::restore`]: struct.MemFs.html#method.restore
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemSnapshot {
+    nodes: Nodes,
+    cwd: Vec<u8>,
+}
+
+impl MemFs {
+    /// Creates a new filesystem containing only an empty root directory.
+    pub fn new() -> Self {
+        let mut root = Inode::new(MemFileType::Dir, 0o755);
+        root.nlink = 1;
+        let mut nodes = BTreeMap::new();
+        nodes.insert(ROOT.to_vec(), Rc::new(RefCell::new(root)));
+        MemFs {
+            nodes: RefCell::new(nodes),
+            faults: Faults::default(),
+            cwd: ROOT.to_vec(),
+        }
+    }
+
+    /// Takes a snapshot of the entire tree, including the contents of all
+    /// files, and of the current directory.
+    ///
+    /// The snapshot is a deep copy, so later changes to the filesystem don't
+    /// affect it.
+    pub fn snapshot(&self) -> MemSnapshot {
+        MemSnapshot {
+            nodes: deep_clone(&self.nodes.borrow()),
+            cwd: self.cwd.clone(),
+        }
+    }
+
+    /// Replaces the entire tree and the current directory with the contents
+    /// of `snapshot`.
+    ///
+    /// Files that are open while the snapshot is restored stay usable, but
+    /// refer to the nodes they were opened on, which are no longer part of
+    /// the tree.
+    pub fn restore(&mut self, snapshot: &MemSnapshot) {
+        *self.nodes.get_mut() = deep_clone(&snapshot.nodes);
+        self.cwd.clone_from(&snapshot.cwd);
+    }
+
+    /// Makes the next call of `op` fail with an error of the given kind.
+    ///
+    /// Injected errors are queued, so calling this function multiple times
+    /// with the same operation makes that many consecutive calls fail. Errors
+    /// are also injected into operations on files opened from this
+    /// filesystem, no matter if they were opened before or after the call.
+    pub fn fail_next(&mut self, op: MemOp, kind: ErrorKind) {
+        self.faults.0.borrow_mut().push((op, kind));
+    }
+
+    /// Removes all injected errors which weren't triggered yet.
+    pub fn clear_faults(&mut self) {
+        self.faults.0.borrow_mut().clear();
+    }
+
+    /// Copies the contents and mode of the file `from` to `to`.
+    fn copy_file(&mut self, from: &[u8], to: &[u8]) -> Result<u64, ErrorKind> {
+        let nodes = self.nodes.get_mut();
+        let from = resolve(nodes, &self.cwd, from, true)?;
+        let (data, mode) = match nodes.get(&from) {
+            Some(inode) => {
+                let inode = inode.borrow();
+                if inode.file_type != MemFileType::File {
+                    return Err(ErrorKind::InvalidInput);
+                }
+                (inode.data.clone(), inode.mode)
+            }
+            None => return Err(ErrorKind::NotFound),
+        };
+
+        let len = data.len() as u64;
+        let to = resolve(nodes, &self.cwd, to, true)?;
+        match nodes.get(&to) {
+            Some(inode) => {
+                let mut inode = inode.borrow_mut();
+                if inode.file_type != MemFileType::File {
+                    return Err(ErrorKind::IsADirectory);
+                }
+                inode.data = data;
+                inode.mode = mode;
+            }
+            None => {
+                let mut inode = Inode::new(MemFileType::File, mode);
+                inode.data = data;
+                link(nodes, to, Rc::new(RefCell::new(inode)))?;
+            }
+        }
+        Ok(len)
+    }
+
+    fn lookup(
+        &self,
+        path: &[u8],
+        follow: bool,
+    ) -> Result<Rc<RefCell<Inode>>, ErrorKind> {
+        let nodes = self.nodes.borrow();
+        let path = resolve(&nodes, &self.cwd, path, follow)?;
+        nodes.get(&path).cloned().ok_or(ErrorKind::NotFound)
+    }
+
+    /// Creates a directory like `create_dir`, without checking for injected
+    /// errors, so a recursive call counts as a single operation.
+    fn make_dir(
+        &mut self,
+        path: &[u8],
+        options: &DirOptions<u32>,
+    ) -> Result<(), ErrorKind> {
+        if options.recursive {
+            if let Some(parent) = path.parent() {
+                if !parent.is_empty() && !self.dir_exists(parent) {
+                    self.make_dir(parent, options)?;
+                }
+            }
+            if self.dir_exists(path) {
+                return Ok(());
+            }
+        }
+
+        let nodes = self.nodes.get_mut();
+        let path = resolve(nodes, &self.cwd, path, false)?;
+        // the mode is set before linking the inode, so the directory is never
+        // visible with other permissions
+        let inode = Inode::new(MemFileType::Dir, options.mode);
+        link(nodes, path, Rc::new(RefCell::new(inode)))
+    }
+
+    fn dir_exists(&self, path: &[u8]) -> bool {
+        self.lookup(path, true)
+            .is_ok_and(|inode| inode.borrow().file_type == MemFileType::Dir)
+    }
+}
+
+impl Default for MemFs {
+    fn default() -> Self {
+        MemFs::new()
+    }
+}
+
+impl Fs for MemFs {
+    type Path = [u8];
+    type PathOwned = Vec<u8>;
+    type File = MemFile;
+    type Dir = MemDir;
+    type DirEntry = MemDirEntry;
+    type Metadata = MemMetadata;
+    type Permissions = u32;
+    type Error = ErrorKind;
+
+    fn open(
+        &self,
+        path: &[u8],
+        options: &OpenOptions<u32>,
+    ) -> Result<MemFile, ErrorKind> {
+        self.faults.check(MemOp::Open)?;
+        let write = options.write || options.append;
+        if !options.read && !write {
+            return Err(ErrorKind::InvalidInput);
+        }
+        options.validate()?;
+
+        let mut nodes = self.nodes.borrow_mut();
+        let path = resolve(&nodes, &self.cwd, path, !options.create_new)?;
+        let inode = match nodes.get(&path) {
+            Some(_) if options.create_new => {
+                return Err(ErrorKind::AlreadyExists)
+            }
+            Some(inode) => {
+                match inode.borrow().file_type {
+                    MemFileType::File => {}
+                    MemFileType::Dir => return Err(ErrorKind::IsADirectory),
+                    MemFileType::Symlink => {
+                        return Err(ErrorKind::InvalidInput)
+                    }
+                    MemFileType::Special(_) => {
+                        return Err(ErrorKind::Unsupported)
+                    }
+                }
+                if options.truncate {
+                    inode.borrow_mut().data.clear();
+                }
+                inode.clone()
+            }
+            None if options.create || options.create_new => {
+                let inode = Inode::new(MemFileType::File, options.mode);
+                let inode = Rc::new(RefCell::new(inode));
+                link(&mut nodes, path, inode.clone())?;
+                inode
+            }
+            None => return Err(ErrorKind::NotFound),
+        };
+
+        Ok(MemFile {
+            inode,
+            faults: self.faults.clone(),
+            pos: Rc::new(Cell::new(0)),
+            read: options.read,
+            write,
+            append: options.append,
+        })
+    }
+
+    fn open_anonymous(
+        &mut self,
+        dir: &[u8],
+        options: &OpenOptions<u32>,
+    ) -> Result<MemFile, ErrorKind> {
+        self.faults.check(MemOp::OpenAnonymous)?;
+        let write = options.write || options.append;
+        if !write {
+            return Err(ErrorKind::InvalidInput);
+        }
+
+        if self.lookup(dir, true)?.borrow().file_type != MemFileType::Dir {
+            return Err(ErrorKind::NotADirectory);
+        }
+        let inode = Inode::new(MemFileType::File, options.mode);
+        Ok(MemFile {
+            inode: Rc::new(RefCell::new(inode)),
+            faults: self.faults.clone(),
+            pos: Rc::new(Cell::new(0)),
+            read: options.read,
+            write,
+            append: options.append,
+        })
+    }
+
+    fn remove_file(&mut self, path: &[u8]) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::RemoveFile)?;
+        let nodes = self.nodes.get_mut();
+        let path = resolve(nodes, &self.cwd, path, false)?;
+        match nodes.get(&path).map(|inode| inode.borrow().file_type) {
+            Some(MemFileType::Dir) => Err(ErrorKind::IsADirectory),
+            Some(_) => {
+                unlink(nodes, &path);
+                Ok(())
+            }
+            None => Err(ErrorKind::NotFound),
+        }
+    }
+
+    fn metadata(&self, path: &[u8]) -> Result<MemMetadata, ErrorKind> {
+        self.faults.check(MemOp::Metadata)?;
+        Ok(self.lookup(path, true)?.borrow().metadata())
+    }
+
+    fn symlink_metadata(&self, path: &[u8]) -> Result<MemMetadata, ErrorKind> {
+        self.faults.check(MemOp::SymlinkMetadata)?;
+        Ok(self.lookup(path, false)?.borrow().metadata())
+    }
+
+    fn rename(&mut self, from: &[u8], to: &[u8]) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::Rename)?;
+        let nodes = self.nodes.get_mut();
+        let from = resolve(nodes, &self.cwd, from, false)?;
+        let to = resolve(nodes, &self.cwd, to, false)?;
+        let is_dir = match nodes.get(&from) {
+            Some(inode) => inode.borrow().file_type == MemFileType::Dir,
+            None => return Err(ErrorKind::NotFound),
+        };
+        if from == to {
+            return Ok(());
+        }
+        if from == ROOT || is_descendant(&to, &from) {
+            return Err(ErrorKind::InvalidInput);
+        }
+
+        match nodes.get(&to).map(|inode| inode.borrow().file_type) {
+            Some(MemFileType::Dir) if !is_dir => {
+                return Err(ErrorKind::IsADirectory)
+            }
+            Some(MemFileType::Dir) => {
+                if nodes.keys().any(|key| is_descendant(key, &to)) {
+                    return Err(ErrorKind::DirectoryNotEmpty);
+                }
+            }
+            Some(_) if is_dir => return Err(ErrorKind::NotADirectory),
+            Some(_) => {}
+            None => check_parent(nodes, &to)?,
+        }
+
+        if let Some(replaced) = nodes.remove(&to) {
+            replaced.borrow_mut().nlink -= 1;
+        }
+        let moved: Vec<Vec<u8>> = nodes
+            .keys()
+            .filter(|key| **key == from || is_descendant(key, &from))
+            .cloned()
+            .collect();
+        for key in moved {
+            if let Some(inode) = nodes.remove(&key) {
+                let mut path = to.clone();
+                path.extend_from_slice(&key[from.len()..]);
+                nodes.insert(path, inode);
+            }
+        }
+        Ok(())
+    }
+
+    fn copy(&mut self, from: &[u8], to: &[u8]) -> Result<u64, ErrorKind> {
+        self.faults.check(MemOp::Copy)?;
+        self.copy_file(from, to)
+    }
+
+    /// Clones the file, which is the same as copying it, since the data of
+    /// files is never shared.
+    fn reflink(&mut self, from: &[u8], to: &[u8]) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::Reflink)?;
+        self.copy_file(from, to).map(|_| ())
+    }
+
+    fn hard_link(&mut self, src: &[u8], dst: &[u8]) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::HardLink)?;
+        let nodes = self.nodes.get_mut();
+        let src = resolve(nodes, &self.cwd, src, false)?;
+        let inode = nodes.get(&src).cloned().ok_or(ErrorKind::NotFound)?;
+        if inode.borrow().file_type == MemFileType::Dir {
+            return Err(ErrorKind::PermissionDenied);
+        }
+        let dst = resolve(nodes, &self.cwd, dst, false)?;
+        link(nodes, dst, inode)
+    }
+
+    fn symlink_file(
+        &mut self,
+        src: &[u8],
+        dst: &[u8],
+    ) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::Symlink)?;
+        let nodes = self.nodes.get_mut();
+        let dst = resolve(nodes, &self.cwd, dst, false)?;
+        let mut inode = Inode::new(MemFileType::Symlink, 0o777);
+        inode.data = src.to_vec();
+        link(nodes, dst, Rc::new(RefCell::new(inode)))
+    }
+
+    fn mknod(
+        &mut self,
+        path: &[u8],
+        mode: u32,
+        kind: SpecialKind,
+        _dev: u64,
+    ) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::Mknod)?;
+        let nodes = self.nodes.get_mut();
+        let path = resolve(nodes, &self.cwd, path, false)?;
+        let inode = Inode::new(MemFileType::Special(kind), mode);
+        link(nodes, path, Rc::new(RefCell::new(inode)))
+    }
+
+    fn read_link(&self, path: &[u8]) -> Result<Vec<u8>, ErrorKind> {
+        self.faults.check(MemOp::ReadLink)?;
+        let inode = self.lookup(path, false)?;
+        let inode = inode.borrow();
+        match inode.file_type {
+            MemFileType::Symlink => Ok(inode.data.clone()),
+            _ => Err(ErrorKind::InvalidInput),
+        }
+    }
+
+    fn canonicalize(&self, path: &[u8]) -> Result<Vec<u8>, ErrorKind> {
+        self.faults.check(MemOp::Canonicalize)?;
+        let nodes = self.nodes.borrow();
+        let path = resolve(&nodes, &self.cwd, path, true)?;
+        if nodes.contains_key(&path) {
+            Ok(path)
+        } else {
+            Err(ErrorKind::NotFound)
+        }
+    }
+
+    fn canonicalize_into(
+        &self,
+        path: &[u8],
+        out: &mut Vec<u8>,
+    ) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::Canonicalize)?;
+        let nodes = self.nodes.borrow();
+        resolve_into(&nodes, &self.cwd, path, true, out)?;
+        if nodes.contains_key(&out[..]) {
+            Ok(())
+        } else {
+            Err(ErrorKind::NotFound)
+        }
+    }
+
+    fn create_dir(
+        &mut self,
+        path: &[u8],
+        options: &DirOptions<u32>,
+    ) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::CreateDir)?;
+        self.make_dir(path, options)
+    }
+
+    fn remove_dir(&mut self, path: &[u8]) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::RemoveDir)?;
+        let nodes = self.nodes.get_mut();
+        let path = resolve(nodes, &self.cwd, path, false)?;
+        match nodes.get(&path).map(|inode| inode.borrow().file_type) {
+            Some(MemFileType::Dir) => {}
+            Some(_) => return Err(ErrorKind::NotADirectory),
+            None => return Err(ErrorKind::NotFound),
+        }
+        if path == ROOT {
+            return Err(ErrorKind::InvalidInput);
+        }
+        if nodes.keys().any(|key| is_descendant(key, &path)) {
+            return Err(ErrorKind::DirectoryNotEmpty);
+        }
+        unlink(nodes, &path);
+        Ok(())
+    }
+
+    fn remove_dir_all(&mut self, path: &[u8]) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::RemoveDirAll)?;
+        let nodes = self.nodes.get_mut();
+        let path = resolve(nodes, &self.cwd, path, false)?;
+        match nodes.get(&path).map(|inode| inode.borrow().file_type) {
+            Some(MemFileType::Dir) => {}
+            Some(MemFileType::Symlink) => {
+                unlink(nodes, &path);
+                return Ok(());
+            }
+            Some(_) => return Err(ErrorKind::NotADirectory),
+            None => return Err(ErrorKind::NotFound),
+        }
+        if path == ROOT {
+            return Err(ErrorKind::InvalidInput);
+        }
+
+        let removed: Vec<Vec<u8>> = nodes
+            .keys()
+            .filter(|key| **key == path || is_descendant(key, &path))
+            .cloned()
+            .collect();
+        for key in removed {
+            unlink(nodes, &key);
+        }
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &[u8]) -> Result<MemDir, ErrorKind> {
+        self.faults.check(MemOp::ReadDir)?;
+        let nodes = self.nodes.borrow();
+        let dir = resolve(&nodes, &self.cwd, path, true)?;
+        match nodes.get(&dir).map(|inode| inode.borrow().file_type) {
+            Some(MemFileType::Dir) => {}
+            Some(_) => return Err(ErrorKind::NotADirectory),
+            None => return Err(ErrorKind::NotFound),
+        }
+
+        let mut entries = Vec::new();
+        for (key, inode) in nodes.iter() {
+            if *key == dir || parent_of(key) != &dir[..] {
+                continue;
+            }
+            let name = file_name_of(key);
+            let mut entry_path = path.to_vec();
+            PathBuf::push(&mut entry_path, name)?;
+            entries.push(MemDirEntry {
+                name_start: entry_path.len() - name.len(),
+                path: entry_path,
+                inode: inode.clone(),
+            });
+        }
+        Ok(MemDir { entries, pos: 0 })
+    }
+
+    fn set_permissions(
+        &mut self,
+        path: &[u8],
+        perm: u32,
+    ) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::SetPermissions)?;
+        self.lookup(path, true)?.borrow_mut().mode = perm;
+        Ok(())
+    }
+
+    fn set_permissions_nofollow(
+        &mut self,
+        path: &[u8],
+        perm: u32,
+    ) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::SetPermissionsNofollow)?;
+        self.lookup(path, false)?.borrow_mut().mode = perm;
+        Ok(())
+    }
+
+    fn set_times(
+        &mut self,
+        path: &[u8],
+        times: FileTimes,
+    ) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::SetTimes)?;
+        let inode = self.lookup(path, true)?;
+        let mut inode = inode.borrow_mut();
+        if let Some(accessed) = times.accessed() {
+            inode.accessed = accessed;
+        }
+        if let Some(modified) = times.modified() {
+            inode.modified = modified;
+        }
+        Ok(())
+    }
+
+    fn current_dir(&self) -> Result<Vec<u8>, ErrorKind> {
+        Ok(self.cwd.clone())
+    }
+
+    fn set_current_dir(&mut self, path: &[u8]) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::SetCurrentDir)?;
+        let nodes = self.nodes.get_mut();
+        let path = resolve(nodes, &self.cwd, path, true)?;
+        match nodes.get(&path).map(|inode| inode.borrow().file_type) {
+            Some(MemFileType::Dir) => {}
+            Some(_) => return Err(ErrorKind::NotADirectory),
+            None => return Err(ErrorKind::NotFound),
+        }
+        self.cwd = path;
+        Ok(())
+    }
+
+    fn available_space(&self, _path: &[u8]) -> Result<u64, ErrorKind> {
+        self.faults.check(MemOp::AvailableSpace)?;
+        Err(ErrorKind::Unsupported)
+    }
+
+    fn sync(&self) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::Sync)
+    }
+
+    fn flush_all(&mut self) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::FlushAll)
+    }
+}
+
+/// A file opened on a [`MemFs`].
+///
+/// Like a file descriptor, each `MemFile` refers to an open file description
+/// holding the cursor and access mode. Descriptions are only shared between
+/// files by [`duplicate_to`].
+///
+/// [`MemFs`]: struct.MemFs.html
+/// [`duplicate_to`]: ../trait.File.html#method.duplicate_to
+#[derive(Debug)]
+pub struct MemFile {
+    inode: Rc<RefCell<Inode>>,
+    faults: Faults,
+    pos: Rc<Cell<u64>>,
+    read: bool,
+    write: bool,
+    append: bool,
+}
+
+impl ErrorType for MemFile {
+    type Error = ErrorKind;
+}
+
+impl Read for MemFile {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+        self.faults.check(MemOp::Read)?;
+        if !self.read {
+            return Err(ErrorKind::PermissionDenied);
+        }
+        let inode = self.inode.borrow();
+        let start = usize::try_from(self.pos.get())
+            .unwrap_or(usize::MAX)
+            .min(inode.data.len());
+        let len = buf.len().min(inode.data.len() - start);
+        buf[..len].copy_from_slice(&inode.data[start..start + len]);
+        // a cursor past the end of the file stays where it is
+        if len > 0 {
+            self.pos.set(self.pos.get() + len as u64);
+        }
+        Ok(len)
+    }
+}
+
+impl Write for MemFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+        self.faults.check(MemOp::Write)?;
+        if !self.write {
+            return Err(ErrorKind::PermissionDenied);
+        }
+        let mut inode = self.inode.borrow_mut();
+        if self.append {
+            self.pos.set(inode.data.len() as u64);
+        }
+        let start = usize::try_from(self.pos.get())
+            .map_err(|_| ErrorKind::InvalidInput)?;
+        let end = start
+            .checked_add(buf.len())
+            .ok_or(ErrorKind::InvalidInput)?;
+        if inode.data.len() < end {
+            inode.data.resize(end, 0);
+        }
+        inode.data[start..end].copy_from_slice(buf);
+        self.pos.set(end as u64);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::Flush)?;
+        Ok(())
+    }
+
+    /// Reserves capacity for writing `additional` bytes at the current
+    /// position.
+    fn reserve(&mut self, additional: usize) -> Result<(), ErrorKind> {
+        if !self.write {
+            return Err(ErrorKind::PermissionDenied);
+        }
+        let mut inode = self.inode.borrow_mut();
+        let start = if self.append {
+            inode.data.len()
+        } else {
+            usize::try_from(self.pos.get())
+                .map_err(|_| ErrorKind::InvalidInput)?
+        };
+        let end = start
+            .checked_add(additional)
+            .ok_or(ErrorKind::InvalidInput)?;
+        let len = inode.data.len();
+        inode.data.reserve(end.saturating_sub(len));
+        Ok(())
+    }
+
+    fn write_zeros(&mut self, count: u64) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::Write)?;
+        if !self.write {
+            return Err(ErrorKind::PermissionDenied);
+        }
+        let mut inode = self.inode.borrow_mut();
+        if self.append {
+            self.pos.set(inode.data.len() as u64);
+        }
+        let start = usize::try_from(self.pos.get())
+            .map_err(|_| ErrorKind::InvalidInput)?;
+        let end = usize::try_from(count)
+            .ok()
+            .and_then(|count| start.checked_add(count))
+            .ok_or(ErrorKind::InvalidInput)?;
+        let overwritten = cmp::min(inode.data.len(), end);
+        if start < overwritten {
+            inode.data[start..overwritten].fill(0);
+        }
+        if inode.data.len() < end {
+            inode.data.resize(end, 0);
+        }
+        self.pos.set(end as u64);
+        Ok(())
+    }
+}
+
+impl Seek for MemFile {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, ErrorKind> {
+        self.faults.check(MemOp::Seek)?;
+        let len = self.inode.borrow().data.len() as u64;
+        let pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => {
+                self.pos.get().checked_add_signed(offset)
+            }
+        };
+        let pos = pos.ok_or(ErrorKind::InvalidInput)?;
+        self.pos.set(pos);
+        Ok(pos)
+    }
+}
+
+impl File for MemFile {
+    fn set_len(&mut self, size: u64) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::SetLen)?;
+        if !self.write {
+            return Err(ErrorKind::PermissionDenied);
+        }
+        let size =
+            usize::try_from(size).map_err(|_| ErrorKind::InvalidInput)?;
+        self.inode.borrow_mut().data.resize(size, 0);
+        Ok(())
+    }
+
+    fn duplicate_to(&self, target: &mut Self) -> Result<(), ErrorKind> {
+        self.faults.check(MemOp::DuplicateTo)?;
+        target.inode = self.inode.clone();
+        target.pos = self.pos.clone();
+        target.read = self.read;
+        target.write = self.write;
+        target.append = self.append;
+        Ok(())
+    }
+
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize, ErrorKind> {
+        self.faults.check(MemOp::Read)?;
+        if !self.read {
+            return Err(ErrorKind::PermissionDenied);
+        }
+        let inode = self.inode.borrow();
+        let start = usize::try_from(offset)
+            .unwrap_or(usize::MAX)
+            .min(inode.data.len());
+        let len = buf.len().min(inode.data.len() - start);
+        buf[..len].copy_from_slice(&inode.data[start..start + len]);
+        Ok(len)
+    }
+
+    fn write_at(
+        &mut self,
+        buf: &[u8],
+        offset: u64,
+    ) -> Result<usize, ErrorKind> {
+        self.faults.check(MemOp::Write)?;
+        if !self.write {
+            return Err(ErrorKind::PermissionDenied);
+        }
+        let start =
+            usize::try_from(offset).map_err(|_| ErrorKind::InvalidInput)?;
+        let end = start
+            .checked_add(buf.len())
+            .ok_or(ErrorKind::InvalidInput)?;
+        let mut inode = self.inode.borrow_mut();
+        if inode.data.len() < end {
+            inode.data.resize(end, 0);
+        }
+        inode.data[start..end].copy_from_slice(buf);
+        Ok(buf.len())
+    }
+}
+
+/// Iterator over the entries in a directory of a [`MemFs`].
+///
+/// The listing is read when the directory is opened, so rewinding is always
+/// supported and yields the same entries again. Positions returned by
+/// [`tell`] are indices into the listing, which is sorted by name, so they can
+/// be used to resume on a new iterator as long as the directory is unchanged.
+///
+/// [`tell`]: ../trait.Dir.html#method.tell
+/// [`MemFs`]: struct.MemFs.html
+#[derive(Debug, Clone)]
+pub struct MemDir {
+    entries: Vec<MemDirEntry>,
+    pos: usize,
+}
+
+impl Iterator for MemDir {
+    type Item = Result<MemDirEntry, ErrorKind>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.entries.get(self.pos)?.clone();
+        self.pos += 1;
+        Some(Ok(entry))
+    }
+}
+
+impl Dir<MemDirEntry, ErrorKind> for MemDir {
+    fn rewind(&mut self) -> Result<(), ErrorKind> {
+        self.pos = 0;
+        Ok(())
+    }
+
+    fn tell(&self) -> Result<DirPos, ErrorKind> {
+        Ok(DirPos::from_raw(self.pos as u64))
+    }
+
+    fn seek(&mut self, pos: DirPos) -> Result<(), ErrorKind> {
+        self.pos = usize::try_from(pos.into_raw())
+            .map_err(|_| ErrorKind::InvalidInput)?;
+        Ok(())
+    }
+}
+
+/// An entry in a directory of a [`MemFs`].
+///
+/// [`MemFs`]: struct.MemFs.html
+#[derive(Debug, Clone)]
+pub struct MemDirEntry {
+    path: Vec<u8>,
+    name_start: usize,
+    inode: Rc<RefCell<Inode>>,
+}
+
+impl DirEntry for MemDirEntry {
+    type Path = [u8];
+    type PathOwned = Vec<u8>;
+    type Metadata = MemMetadata;
+    type FileType = MemFileType;
+    type Error = ErrorKind;
+
+    fn path(&self) -> Vec<u8> {
+        self.path.clone()
+    }
+
+    fn metadata(&self) -> Result<MemMetadata, ErrorKind> {
+        Ok(self.inode.borrow().metadata())
+    }
+
+    fn file_type(&self) -> Result<MemFileType, ErrorKind> {
+        Ok(self.inode.borrow().file_type)
+    }
+
+    fn file_name(&self) -> &[u8] {
+        &self.path[self.name_start..]
+    }
+}
+
+/// Metadata of a node in a [`MemFs`].
+///
+/// [`MemFs`]: struct.MemFs.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MemMetadata {
+    file_type: MemFileType,
+    len: u64,
+    mode: u32,
+    nlink: u64,
+    accessed: SystemTime,
+    modified: SystemTime,
+}
+
+impl MemMetadata {
+    /// Returns the permission bits of the node.
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+}
+
+impl Metadata for MemMetadata {
+    type FileType = MemFileType;
+
+    fn file_type(&self) -> MemFileType {
+        self.file_type
+    }
+
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn accessed(&self) -> Option<SystemTime> {
+        Some(self.accessed)
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        Some(self.modified)
+    }
+}
+
+impl UnixMetadataExt for MemMetadata {
+    fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    fn nlink(&self) -> u64 {
+        self.nlink
+    }
+}
+
+/// The type of a node in a [`MemFs`].
+///
+/// [`MemFs`]: struct.MemFs.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemFileType {
+    /// A regular file.
+    File,
+    /// A directory.
+    Dir,
+    /// A symbolic link.
+    Symlink,
+    /// A special file created by `Fs::mknod`.
+    ///
+    /// Special files can be listed, inspected and removed, but not opened.
+    Special(SpecialKind),
+}
+
+impl FileType for MemFileType {
+    fn is_dir(&self) -> bool {
+        *self == MemFileType::Dir
+    }
+
+    fn is_file(&self) -> bool {
+        *self == MemFileType::File
+    }
+
+    fn is_symlink(&self) -> bool {
+        *self == MemFileType::Symlink
+    }
+
+    fn is_fifo(&self) -> bool {
+        *self == MemFileType::Special(SpecialKind::Fifo)
+    }
+
+    fn is_socket(&self) -> bool {
+        *self == MemFileType::Special(SpecialKind::Socket)
+    }
+
+    fn is_char_device(&self) -> bool {
+        *self == MemFileType::Special(SpecialKind::CharDevice)
+    }
+
+    fn is_block_device(&self) -> bool {
+        *self == MemFileType::Special(SpecialKind::BlockDevice)
+    }
+}
+
+/// An operation on a [`MemFs`] or one of its files which can be made to fail
+/// with [`MemFs::fail_next`].
+///
+/// [`MemFs`]: struct.MemFs.html
+/// [`MemFs::fail_next`]: struct.MemFs.html#method.fail_next
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemOp {
+    /// `Fs::open`
+    Open,
+    /// `Fs::open_anonymous`
+    OpenAnonymous,
+    /// `Fs::remove_file`
+    RemoveFile,
+    /// `Fs::metadata`
+    Metadata,
+    /// `Fs::symlink_metadata`
+    SymlinkMetadata,
+    /// `Fs::rename`
+    Rename,
+    /// `Fs::copy`
+    Copy,
+    /// `Fs::reflink`
+    Reflink,
+    /// `Fs::hard_link`
+    HardLink,
+    /// `Fs::symlink`
+    Symlink,
+    /// `Fs::mknod`
+    Mknod,
+    /// `Fs::read_link`
+    ReadLink,
+    /// `Fs::canonicalize`
+    Canonicalize,
+    /// `Fs::create_dir`, once per call, even if parent directories are
+    /// created as well
+    CreateDir,
+    /// `Fs::remove_dir`
+    RemoveDir,
+    /// `Fs::remove_dir_all`
+    RemoveDirAll,
+    /// `Fs::read_dir`
+    ReadDir,
+    /// `Fs::set_permissions`
+    SetPermissions,
+    /// `Fs::set_permissions_nofollow`
+    SetPermissionsNofollow,
+    /// `Fs::set_times`
+    SetTimes,
+    /// `Fs::set_current_dir`
+    SetCurrentDir,
+    /// `Fs::available_space`
+    AvailableSpace,
+    /// `Fs::sync`
+    Sync,
+    /// `Fs::flush_all`
+    FlushAll,
+    /// `Read::read` on an open file
+    Read,
+    /// `Write::write` on an open file
+    Write,
+    /// `Write::flush` on an open file
+    Flush,
+    /// `Seek::seek` on an open file
+    Seek,
+    /// `File::set_len` on an open file
+    SetLen,
+    /// `File::duplicate_to` on an open file
+    DuplicateTo,
+}
+
+/// Errors queued by [`MemFs::fail_next`], shared with all open files.
+///
+/// [`MemFs::fail_next`]: struct.MemFs.html#method.fail_next
+#[derive(Debug, Clone, Default)]
+struct Faults(Rc<RefCell<Vec<(MemOp, ErrorKind)>>>);
+
+impl Faults {
+    fn check(&self, op: MemOp) -> Result<(), ErrorKind> {
+        let mut faults = self.0.borrow_mut();
+        match faults.iter().position(|&(fault, _)| fault == op) {
+            Some(i) => Err(faults.remove(i).1),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Resolves `path` to the absolute, normalized key of its node.
+///
+/// Symbolic links are followed in all components but the last one, which is
+/// only followed if `follow` is set. The last component doesn't have to
+/// exist.
+fn resolve(
+    nodes: &Nodes,
+    cwd: &[u8],
+    path: &[u8],
+    follow: bool,
+) -> Result<Vec<u8>, ErrorKind> {
+    let mut resolved = Vec::new();
+    resolve_into(nodes, cwd, path, follow, &mut resolved)?;
+    Ok(resolved)
+}
+
+/// Resolves `path` like [`resolve`], writing the key into `out`.
+///
+/// The components still to resolve are read in place from `path` and from
+/// the targets of the links being followed, so the only allocation is
+/// growing `out`.
+fn resolve_into<'a>(
+    nodes: &'a Nodes,
+    cwd: &[u8],
+    path: &[u8],
+    follow: bool,
+    out: &mut Vec<u8>,
+) -> Result<(), ErrorKind> {
+    // `path` and the links being followed, innermost last, each with the
+    // offset of its next component
+    let mut sources: [(Option<&'a Rc<RefCell<Inode>>>, usize);
+        MAX_SYMLINK_HOPS + 1] = [(None, 0); MAX_SYMLINK_HOPS + 1];
+    let mut depth = 1;
+    out.clear();
+    out.extend_from_slice(if path.first() == Some(&b'/') {
+        ROOT
+    } else {
+        cwd
+    });
+    let mut hops = 0;
+    while depth > 0 {
+        let (source, offset) = sources[depth - 1];
+        let target;
+        let bytes = match source {
+            Some(inode) => {
+                target = inode.borrow();
+                &target.data[..]
+            }
+            None => path,
+        };
+        let (start, end) = match next_component(bytes, offset) {
+            Some(range) => range,
+            None => {
+                depth -= 1;
+                continue;
+            }
+        };
+        sources[depth - 1].1 = end;
+        let component = &bytes[start..end];
+        if component == b".." {
+            let len = parent_of(out).len();
+            out.truncate(len);
+            continue;
+        }
+
+        let last = sources[..depth]
+            .iter()
+            .all(|&(source, offset)| !has_component(source, path, offset));
+        let len = out.len();
+        if out[..] != *ROOT {
+            out.push(b'/');
+        }
+        out.extend_from_slice(component);
+        match nodes.get(&out[..]) {
+            Some(inode) => match inode.borrow().file_type {
+                MemFileType::Symlink if follow || !last => {
+                    hops += 1;
+                    if hops > MAX_SYMLINK_HOPS {
+                        return Err(ErrorKind::FilesystemLoop);
+                    }
+                    out.truncate(len);
+                    if inode.borrow().data.first() == Some(&b'/') {
+                        out.clear();
+                        out.extend_from_slice(ROOT);
+                    }
+                    sources[depth] = (Some(inode), 0);
+                    depth += 1;
+                }
+                MemFileType::Dir => {}
+                _ if last => {}
+                _ => return Err(ErrorKind::NotADirectory),
+            },
+            None if last => {}
+            None => return Err(ErrorKind::NotFound),
+        }
+    }
+    Ok(())
+}
+
+/// Returns the range of the next component of `path` at or after `offset`,
+/// skipping empty and `.` components.
+fn next_component(path: &[u8], mut offset: usize) -> Option<(usize, usize)> {
+    loop {
+        while path.get(offset) == Some(&b'/') {
+            offset += 1;
+        }
+        if offset == path.len() {
+            return None;
+        }
+        let end = path[offset..]
+            .iter()
+            .position(|&b| b == b'/')
+            .map_or(path.len(), |i| offset + i);
+        if path[offset..end] != *b"." {
+            return Some((offset, end));
+        }
+        offset = end;
+    }
+}
+
+/// Returns whether a source of [`resolve_into`] has components left.
+fn has_component(
+    source: Option<&Rc<RefCell<Inode>>>,
+    path: &[u8],
+    offset: usize,
+) -> bool {
+    match source {
+        Some(inode) => next_component(&inode.borrow().data, offset).is_some(),
+        None => next_component(path, offset).is_some(),
+    }
+}
+
+fn parent_of(path: &[u8]) -> &[u8] {
+    match path.iter().rposition(|&b| b == b'/') {
+        Some(0) | None => ROOT,
+        Some(i) => &path[..i],
+    }
+}
+
+fn file_name_of(path: &[u8]) -> &[u8] {
+    match path.iter().rposition(|&b| b == b'/') {
+        Some(i) => &path[i + 1..],
+        None => path,
+    }
+}
+
+fn is_descendant(path: &[u8], dir: &[u8]) -> bool {
+    if dir == ROOT {
+        path != ROOT
+    } else {
+        path.starts_with(dir) && path.get(dir.len()) == Some(&b'/')
+    }
+}
+
+fn check_parent(nodes: &Nodes, path: &[u8]) -> Result<(), ErrorKind> {
+    match nodes.get(parent_of(path)) {
+        Some(parent) if parent.borrow().file_type == MemFileType::Dir => Ok(()),
+        Some(_) => Err(ErrorKind::NotADirectory),
+        None => Err(ErrorKind::NotFound),
+    }
+}
+
+fn link(
+    nodes: &mut Nodes,
+    path: Vec<u8>,
+    inode: Rc<RefCell<Inode>>,
+) -> Result<(), ErrorKind> {
+    if nodes.contains_key(&path) {
+        return Err(ErrorKind::AlreadyExists);
+    }
+    check_parent(nodes, &path)?;
+    inode.borrow_mut().nlink += 1;
+    nodes.insert(path, inode);
+    Ok(())
+}
+
+fn unlink(nodes: &mut Nodes, path: &[u8]) {
+    if let Some(inode) = nodes.remove(path) {
+        inode.borrow_mut().nlink -= 1;
+    }
+}
+
+/// Clones every node in `nodes`, keeping hard links shared between the
+/// clones.
+fn deep_clone(nodes: &Nodes) -> Nodes {
+    let mut clones: BTreeMap<*const RefCell<Inode>, Rc<RefCell<Inode>>> =
+        BTreeMap::new();
+    nodes
+        .iter()
+        .map(|(path, inode)| {
+            let clone = clones
+                .entry(Rc::as_ptr(inode))
+                .or_insert_with(|| {
+                    Rc::new(RefCell::new(inode.borrow().clone()))
+                })
+                .clone();
+            (path.clone(), clone)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::time::Duration;
+    use mock::{read_file, write_file, MockFs};
+    use {IoSlice, IoSliceMut, LinkKind, TreeNode};
+
+    #[test]
+    fn resolve_symlinks_follows_short_chain() {
+        let mut fs = MemFs::new();
+        write_file(&fs, b"/target", b"data").unwrap();
+        fs.symlink(b"target", b"/b").unwrap();
+        fs.symlink(b"b", b"/a").unwrap();
+        assert_eq!(fs.resolve_symlinks(b"/a", 2).unwrap(), b"/target");
+        assert_eq!(
+            fs.resolve_symlinks(b"/a", 1),
+            Err(ErrorKind::FilesystemLoop)
+        );
+    }
+
+    #[test]
+    fn resolve_symlinks_rejects_loop() {
+        let mut fs = MemFs::new();
+        fs.symlink(b"/loop", b"/loop").unwrap();
+        assert_eq!(
+            fs.resolve_symlinks(b"/loop", 8),
+            Err(ErrorKind::FilesystemLoop)
+        );
+    }
+
+    #[test]
+    fn resolve_symlinks_returns_plain_path() {
+        let fs = MemFs::new();
+        write_file(&fs, b"/file", b"").unwrap();
+        assert_eq!(fs.resolve_symlinks(b"/file", 0).unwrap(), b"/file");
+    }
+
+    #[test]
+    fn create_dir_all_tracked_reports_new_dirs() {
+        let mut fs = MemFs::new();
+        fs.create_dir(b"/a", &DirOptions::new()).unwrap();
+        let created = fs.create_dir_all_tracked(b"/a/b/c").unwrap();
+        assert_eq!(created, [b"/a/b".to_vec(), b"/a/b/c".to_vec()]);
+        assert!(fs.is_dir(b"/a/b/c"));
+        assert!(fs.create_dir_all_tracked(b"/a/b/c").unwrap().is_empty());
+    }
+
+    #[test]
+    fn type_shortcuts() {
+        let mut fs = MemFs::new();
+        write_file(&fs, b"/file", b"").unwrap();
+        fs.create_dir(b"/dir", &DirOptions::new()).unwrap();
+        fs.symlink(b"dir", b"/link").unwrap();
+
+        assert!(fs.is_file(b"/file"));
+        assert!(!fs.is_dir(b"/file"));
+        assert!(!fs.is_symlink(b"/file"));
+
+        assert!(fs.is_dir(b"/dir"));
+        assert!(!fs.is_file(b"/dir"));
+        assert!(!fs.is_symlink(b"/dir"));
+
+        assert!(fs.is_symlink(b"/link"));
+        assert!(fs.is_dir(b"/link"));
+        assert!(!fs.is_file(b"/link"));
+
+        assert!(!fs.is_file(b"/missing"));
+        assert!(!fs.is_dir(b"/missing"));
+        assert!(!fs.is_symlink(b"/missing"));
+    }
+
+    #[test]
+    fn read_exact_file_checks_size() {
+        let fs = MemFs::new();
+        write_file(&fs, b"/exact", b"abcd").unwrap();
+        write_file(&fs, b"/small", b"abc").unwrap();
+        write_file(&fs, b"/large", b"abcde").unwrap();
+        assert_eq!(fs.read_exact_file::<4>(b"/exact").unwrap(), *b"abcd");
+        assert_eq!(
+            fs.read_exact_file::<4>(b"/small"),
+            Err(ErrorKind::InvalidData)
+        );
+        assert_eq!(
+            fs.read_exact_file::<4>(b"/large"),
+            Err(ErrorKind::InvalidData)
+        );
+    }
+
+    #[test]
+    fn restore_undoes_changes() {
+        let mut fs = MemFs::new();
+        fs.create_dir(b"/dir", &DirOptions::new()).unwrap();
+        write_file(&fs, b"/dir/file", b"original").unwrap();
+        fs.hard_link(b"/dir/file", b"/link").unwrap();
+        let snapshot = fs.snapshot();
+
+        write_file(&fs, b"/dir/file", b"changed").unwrap();
+        write_file(&fs, b"/new", b"new").unwrap();
+        fs.remove_file(b"/link").unwrap();
+        fs.set_current_dir(b"/dir").unwrap();
+        assert_ne!(fs.snapshot(), snapshot);
+
+        fs.restore(&snapshot);
+        assert_eq!(fs.snapshot(), snapshot);
+        assert_eq!(fs.current_dir().unwrap(), b"/");
+        assert_eq!(read_file(&fs, b"/link").unwrap(), b"original");
+
+        // hard links are still shared after restoring
+        write_file(&fs, b"/link", b"shared").unwrap();
+        assert_eq!(fs.file_size(b"/dir/file").unwrap(), 6);
+    }
+
+    #[test]
+    fn read_past_end_keeps_cursor() {
+        let fs = MemFs::new();
+        write_file(&fs, b"/file", b"abc").unwrap();
+        let mut file =
+            fs.open(b"/file", OpenOptions::new().read(true)).unwrap();
+        file.seek(SeekFrom::Start(10)).unwrap();
+        assert_eq!(file.read(&mut [0; 4]).unwrap(), 0);
+        assert_eq!(file.stream_position().unwrap(), 10);
+
+        file.seek(SeekFrom::Start(1)).unwrap();
+        assert_eq!(file.read(&mut [0; 4]).unwrap(), 2);
+        assert_eq!(file.stream_position().unwrap(), 3);
+    }
+
+    #[test]
+    fn injected_errors_surface_once() {
+        let mut fs = MemFs::new();
+        write_file(&fs, b"/file", b"abc").unwrap();
+        let file = fs.open(b"/file", OpenOptions::new().read(true)).unwrap();
+
+        fs.fail_next(MemOp::Open, ErrorKind::NotFound);
+        fs.fail_next(MemOp::Read, ErrorKind::Interrupted);
+        let options = OpenOptions::new();
+        assert_eq!(
+            fs.open(b"/file", options.clone().read(true)).err(),
+            Some(ErrorKind::NotFound)
+        );
+        assert!(fs.open(b"/file", options.clone().read(true)).is_ok());
+        assert_eq!(file.read(&mut [0; 3]), Err(ErrorKind::Interrupted));
+        assert_eq!(file.read(&mut [0; 3]), Ok(3));
+
+        fs.fail_next(MemOp::Metadata, ErrorKind::PermissionDenied);
+        fs.clear_faults();
+        assert!(fs.metadata(b"/file").is_ok());
+    }
+
+    #[test]
+    fn dir_position_resumes_on_new_iterator() {
+        let fs = MemFs::new();
+        for name in [&b"/a"[..], b"/b", b"/c", b"/d"] {
+            write_file(&fs, name, b"").unwrap();
+        }
+        let mut dir = fs.read_dir(b"/").unwrap();
+        assert_eq!(dir.next().unwrap().unwrap().file_name(), b"a");
+        assert_eq!(dir.next().unwrap().unwrap().file_name(), b"b");
+        let pos = dir.tell().unwrap();
+        drop(dir);
+
+        let mut dir = fs.read_dir(b"/").unwrap();
+        dir.seek(pos).unwrap();
+        let rest: Vec<_> = dir
+            .map(|entry| entry.unwrap().file_name().to_vec())
+            .collect();
+        assert_eq!(rest, [b"c", b"d"]);
+    }
+
+    #[test]
+    fn copy_progress_is_monotonic() {
+        let mut fs = MemFs::new();
+        let data: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        write_file(&fs, b"/src", &data).unwrap();
+        let mut reports = Vec::new();
+        let total = fs
+            .copy_with_progress(b"/src", b"/dst", |n| reports.push(n))
+            .unwrap();
+        assert_eq!(total, data.len() as u64);
+        assert_eq!(reports.last(), Some(&total));
+        assert!(reports.len() > 1);
+        assert!(reports.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(read_file(&fs, b"/dst").unwrap(), data);
+    }
+
+    #[test]
+    fn copy_with_progress_copies_permissions() {
+        let mut fs = MemFs::new();
+        write_file(&fs, b"/src", b"data").unwrap();
+        fs.set_permissions(b"/src", 0o604).unwrap();
+        fs.copy_with_progress(b"/src", b"/dst", |_| ()).unwrap();
+        assert_eq!(fs.metadata(b"/dst").unwrap().mode(), 0o604);
+    }
+
+    #[test]
+    fn truncate_by_path() {
+        let mut fs = MemFs::new();
+        write_file(&fs, b"/file", b"abcdef").unwrap();
+        fs.truncate(b"/file", 3).unwrap();
+        assert_eq!(read_file(&fs, b"/file").unwrap(), b"abc");
+        fs.truncate(b"/file", 5).unwrap();
+        assert_eq!(read_file(&fs, b"/file").unwrap(), b"abc\0\0");
+        assert_eq!(fs.truncate(b"/missing", 1), Err(ErrorKind::NotFound));
+        assert!(!fs.is_file(b"/missing"));
+    }
+
+    #[test]
+    fn move_file_renames_on_same_device() {
+        let mut fs = MemFs::new();
+        write_file(&fs, b"/from", b"data").unwrap();
+        fs.move_file(b"/from", b"/to").unwrap();
+        assert_eq!(read_file(&fs, b"/to").unwrap(), b"data");
+        assert!(!fs.is_file(b"/from"));
+    }
+
+    #[test]
+    fn move_file_falls_back_to_copy() {
+        let mut fs = MemFs::new();
+        write_file(&fs, b"/from", b"data").unwrap();
+        fs.set_permissions(b"/from", 0o640).unwrap();
+        fs.fail_next(MemOp::Rename, ErrorKind::CrossesDevices);
+        fs.move_file(b"/from", b"/to").unwrap();
+        assert_eq!(read_file(&fs, b"/to").unwrap(), b"data");
+        assert_eq!(fs.metadata(b"/to").unwrap().mode(), 0o640);
+        assert!(!fs.is_file(b"/from"));
+
+        // other errors are not handled by copying
+        fs.fail_next(MemOp::Rename, ErrorKind::PermissionDenied);
+        assert_eq!(
+            fs.move_file(b"/to", b"/from"),
+            Err(ErrorKind::PermissionDenied)
+        );
+        assert!(fs.is_file(b"/to"));
+    }
+
+    #[test]
+    fn read_dir_sorted_orders_unordered_listing() {
+        let mut fs = MockFs {
+            reversed: true,
+            ..MockFs::default()
+        };
+        for name in [&b"/b"[..], b"/a", b"/B", b"/c"] {
+            write_file(&fs, name, b"").unwrap();
+        }
+        fs.create_dir(b"/z", &DirOptions::new()).unwrap();
+        fs.create_dir(b"/d", &DirOptions::new()).unwrap();
+        let listed: Vec<_> = fs
+            .read_dir(b"/")
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        assert_eq!(listed[0], b"/z");
+
+        let sorted: Vec<_> = fs
+            .read_dir_sorted(b"/")
+            .unwrap()
+            .iter()
+            .map(|entry| entry.file_name().to_vec())
+            .collect();
+        assert_eq!(sorted, [&b"d"[..], b"z", b"B", b"a", b"b", b"c"]);
+    }
+
+    #[test]
+    fn canonicalize_lenient_appends_missing_components() {
+        let mut fs = MemFs::new();
+        fs.create_dir(b"/dir", &DirOptions::new()).unwrap();
+        fs.symlink(b"/dir", b"/link").unwrap();
+        assert_eq!(fs.canonicalize(b"/link/new"), Err(ErrorKind::NotFound));
+        assert_eq!(fs.canonicalize_lenient(b"/link/new").unwrap(), b"/dir/new");
+        assert_eq!(
+            fs.canonicalize_lenient(b"/link/a/b//").unwrap(),
+            b"/dir/a/b//"
+        );
+        assert_eq!(fs.canonicalize_lenient(b"/link").unwrap(), b"/dir");
+
+        fs.set_current_dir(b"/dir").unwrap();
+        assert_eq!(fs.canonicalize_lenient(b"new").unwrap(), b"/dir/new");
+    }
+
+    #[test]
+    fn duplicate_to_shares_cursor() {
+        let fs = MemFs::new();
+        write_file(&fs, b"/a", b"abcdef").unwrap();
+        write_file(&fs, b"/b", b"other").unwrap();
+        let file = fs.open(b"/a", OpenOptions::new().read(true)).unwrap();
+        let mut target = fs.open(b"/b", OpenOptions::new().read(true)).unwrap();
+        file.duplicate_to(&mut target).unwrap();
+
+        let mut buf = [0; 2];
+        target.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ab");
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"cd");
+        target.seek(SeekFrom::Start(5)).unwrap();
+        assert_eq!(file.read(&mut buf).unwrap(), 1);
+        assert_eq!(target.write(b"x"), Err(ErrorKind::PermissionDenied));
+    }
+
+    #[test]
+    fn symlink_dir_resolves_to_directory() {
+        let mut fs = MockFs::default();
+        fs.create_dir(b"/dir", &DirOptions::new()).unwrap();
+        write_file(&fs, b"/file", b"").unwrap();
+        fs.symlink_dir(b"/dir", b"/dir_link").unwrap();
+        fs.symlink_file(b"/file", b"/file_link").unwrap();
+        fs.symlink(b"/file", b"/link").unwrap();
+
+        assert!(fs.metadata(b"/dir_link").unwrap().is_dir());
+        assert!(fs.symlink_metadata(b"/dir_link").unwrap().is_symlink());
+        assert!(fs.metadata(b"/file_link").unwrap().is_file());
+        assert_eq!(fs.read_link(b"/link").unwrap(), b"/file");
+    }
+
+    #[test]
+    fn vectored_at_uses_first_buffer_and_keeps_cursor() {
+        let fs = MemFs::new();
+        write_file(&fs, b"/a", b"0123456789").unwrap();
+        let mut file = fs
+            .open(b"/a", OpenOptions::new().read(true).write(true))
+            .unwrap();
+        file.seek(SeekFrom::Start(1)).unwrap();
+
+        let (mut first, mut second) = ([0; 3], [0; 3]);
+        let mut bufs = [
+            IoSliceMut::new(&mut []),
+            IoSliceMut::new(&mut first),
+            IoSliceMut::new(&mut second),
+        ];
+        assert_eq!(file.read_vectored_at(&mut bufs, 4).unwrap(), 3);
+        assert_eq!((first, second), (*b"456", [0; 3]));
+        assert_eq!(file.read_vectored_at(&mut [], 4).unwrap(), 0);
+
+        let bufs =
+            [IoSlice::new(b""), IoSlice::new(b"ab"), IoSlice::new(b"cd")];
+        assert_eq!(file.write_vectored_at(&bufs, 8).unwrap(), 2);
+        assert_eq!(file.stream_position().unwrap(), 1);
+        drop(file);
+        assert_eq!(read_file(&fs, b"/a").unwrap(), b"01234567ab");
+    }
+
+    #[test]
+    fn mkfifo_creates_fifo() {
+        let mut fs = MemFs::new();
+        fs.mkfifo(b"/pipe", 0o600).unwrap();
+        fs.mknod(b"/sock", 0o600, SpecialKind::Socket, 0).unwrap();
+
+        let file_type = fs.metadata(b"/pipe").unwrap().file_type();
+        assert!(file_type.is_fifo());
+        assert!(!file_type.is_file() && !file_type.is_socket());
+        assert!(fs.metadata(b"/sock").unwrap().file_type().is_socket());
+        assert_eq!(fs.mkfifo(b"/pipe", 0o600), Err(ErrorKind::AlreadyExists));
+
+        // backends without special files keep the default
+        let mut fs = MockFs::default();
+        assert_eq!(fs.mkfifo(b"/pipe", 0o600), Err(ErrorKind::Unsupported));
+    }
+
+    #[test]
+    fn read_dir_with_types_reports_mixed_types() {
+        let mut fs = MockFs::default();
+        fs.create_dir(b"/dir", &DirOptions::new()).unwrap();
+        write_file(&fs, b"/dir/file", b"").unwrap();
+        fs.create_dir(b"/dir/sub", &DirOptions::new()).unwrap();
+        fs.symlink(b"sub", b"/dir/link").unwrap();
+        fs.inner.mkfifo(b"/dir/pipe", 0o600).unwrap();
+
+        let mut entries = fs.read_dir_with_types(b"/dir").unwrap();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let paths: Vec<_> = entries.iter().map(|e| &e.0[..]).collect();
+        assert_eq!(
+            paths,
+            [&b"/dir/file"[..], b"/dir/link", b"/dir/pipe", b"/dir/sub"]
+        );
+        let types: Vec<_> = entries.iter().map(|e| e.1).collect();
+        assert!(types[0].is_file());
+        // symbolic links are not traversed
+        assert!(types[1].is_symlink() && !types[1].is_dir());
+        assert!(types[2].is_fifo());
+        assert!(types[3].is_dir());
+    }
+
+    #[test]
+    fn read_dir_owned_entries_outlive_dir() {
+        let mut fs = MockFs::default();
+        fs.create_dir(b"/dir", &DirOptions::new()).unwrap();
+        write_file(&fs, b"/dir/a", b"abc").unwrap();
+        fs.create_dir(b"/dir/b", &DirOptions::new()).unwrap();
+
+        let mut entries = fs.read_dir_owned(b"/dir").unwrap();
+        entries.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+        // the listing is complete even if the directory changes afterwards
+        fs.remove_dir(b"/dir/b").unwrap();
+        write_file(&fs, b"/dir/c", b"").unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path(), b"/dir/a");
+        assert_eq!(entries[0].metadata().unwrap().len(), 3);
+        assert_eq!(entries[1].file_name(), b"b");
+        assert!(entries[1].file_type().unwrap().is_dir());
+    }
+
+    #[test]
+    fn metadata_opt_distinguishes_absent() {
+        let mut fs = MemFs::new();
+        write_file(&fs, b"/a", b"abc").unwrap();
+        assert_eq!(fs.metadata_opt(b"/a").unwrap().map(|m| m.len()), Some(3));
+        assert_eq!(fs.metadata_opt(b"/missing"), Ok(None));
+        assert_eq!(fs.metadata_opt(b"/missing/child"), Ok(None));
+
+        fs.fail_next(MemOp::Metadata, ErrorKind::PermissionDenied);
+        assert_eq!(fs.metadata_opt(b"/a"), Err(ErrorKind::PermissionDenied));
+    }
+
+    #[test]
+    fn anonymous_file_reads_back_without_entry() {
+        let mut fs = MemFs::new();
+        fs.create_dir(b"/tmp", &DirOptions::new()).unwrap();
+        let mut options = OpenOptions::new();
+        options.read(true).write(true);
+        let mut file = fs.open_anonymous(b"/tmp", &options).unwrap();
+        file.write_all(b"scratch").unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let mut buf = [0; 7];
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"scratch");
+        assert_eq!(fs.read_dir(b"/tmp").unwrap().count(), 0);
+
+        assert_eq!(
+            fs.open_anonymous(b"/missing", &options).err(),
+            Some(ErrorKind::NotFound)
+        );
+        let mut fs = MockFs::default();
+        assert_eq!(
+            fs.open_anonymous(b"/", &options).err(),
+            Some(ErrorKind::Unsupported)
+        );
+    }
+
+    #[test]
+    fn remove_file_reports_remaining_links() {
+        let mut fs = MemFs::new();
+        write_file(&fs, b"/a", b"data").unwrap();
+        fs.hard_link(b"/a", b"/b").unwrap();
+        assert_eq!(fs.remove_file_reporting_links(b"/a").unwrap(), 1);
+        assert_eq!(read_file(&fs, b"/b").unwrap(), b"data");
+        assert_eq!(fs.remove_file_reporting_links(b"/b").unwrap(), 0);
+        assert_eq!(
+            fs.remove_file_reporting_links(b"/b"),
+            Err(ErrorKind::NotFound)
+        );
+    }
+
+    #[test]
+    fn metadata_follow_is_bounded() {
+        let mut fs = MemFs::new();
+        write_file(&fs, b"/target", b"data").unwrap();
+        fs.symlink(b"target", b"/one").unwrap();
+        fs.symlink(b"one", b"/two").unwrap();
+
+        assert!(fs.metadata_follow(b"/one", 0).unwrap().is_symlink());
+        assert_eq!(
+            fs.metadata_follow(b"/one", 0),
+            fs.symlink_metadata(b"/one")
+        );
+        assert_eq!(fs.metadata_follow(b"/one", 1).unwrap().len(), 4);
+        assert_eq!(fs.metadata_follow(b"/target", 1).unwrap().len(), 4);
+        assert_eq!(
+            fs.metadata_follow(b"/two", 1),
+            Err(ErrorKind::FilesystemLoop)
+        );
+        assert_eq!(fs.metadata_follow(b"/two", 2).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn touch_at_creates_or_updates() {
+        let mut fs = MemFs::new();
+        let first = SystemTime::from_unix(Duration::from_secs(100));
+        fs.touch_at(b"/new", first).unwrap();
+        let metadata = fs.metadata(b"/new").unwrap();
+        assert_eq!(metadata.len(), 0);
+        assert_eq!(metadata.modified(), Some(first));
+        assert_eq!(metadata.accessed(), Some(first));
+
+        write_file(&fs, b"/old", b"keep").unwrap();
+        let second = SystemTime::from_unix(Duration::from_secs(200));
+        fs.touch_at(b"/old", second).unwrap();
+        let metadata = fs.metadata(b"/old").unwrap();
+        assert_eq!(metadata.modified(), Some(second));
+        assert_eq!(read_file(&fs, b"/old").unwrap(), b"keep");
+    }
+
+    #[test]
+    fn rename_case_on_case_insensitive_fs() {
+        let names = |fs: &MockFs| -> Vec<Vec<u8>> {
+            let mut names: Vec<_> = fs
+                .read_dir(b"/")
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_vec())
+                .collect();
+            names.sort();
+            names
+        };
+        let mut fs = MockFs {
+            case_insensitive: true,
+            ..MockFs::default()
+        };
+        write_file(&fs, b"/Foo", b"data").unwrap();
+        assert_eq!(read_file(&fs, b"/FOO").unwrap(), b"data");
+
+        // a plain rename resolves both names to the same entry
+        fs.rename(b"/Foo", b"/foo").unwrap();
+        assert_eq!(names(&fs), [b"Foo".to_vec()]);
+        fs.rename_case(b"/Foo", b"/foo").unwrap();
+        assert_eq!(names(&fs), [b"foo".to_vec()]);
+        assert_eq!(read_file(&fs, b"/foo").unwrap(), b"data");
+
+        write_file(&fs, b"/Bar", b"new").unwrap();
+        fs.rename_case(b"/Bar", b"/FOO").unwrap();
+        assert_eq!(names(&fs), [b"foo".to_vec()]);
+        assert_eq!(read_file(&fs, b"/foo").unwrap(), b"new");
+    }
+
+    #[test]
+    fn rename_case_temp_name_follows_source() {
+        let mut fs = MemFs::new();
+        write_file(&fs, b"/Foo", b"data").unwrap();
+        write_file(&fs, b"/.genfs-rename-case", b"other").unwrap();
+        fs.rename_case(b"/Foo", b"/foo").unwrap();
+        assert_eq!(read_file(&fs, b"/foo").unwrap(), b"data");
+
+        write_file(&fs, b"/.foo.genfs-rename-case", b"taken").unwrap();
+        assert_eq!(
+            fs.rename_case(b"/foo", b"/FOO"),
+            Err(ErrorKind::AlreadyExists)
+        );
+        assert_eq!(read_file(&fs, b"/foo").unwrap(), b"data");
+    }
+
+    #[test]
+    fn rename_case_keeps_error_of_second_step() {
+        let mut fs = MemFs::new();
+        write_file(&fs, b"/Foo", b"data").unwrap();
+        fs.create_dir(b"/foo", &DirOptions::new()).unwrap();
+        write_file(&fs, b"/foo/child", b"").unwrap();
+        let err = fs.rename(b"/Foo", b"/foo").unwrap_err();
+        assert_eq!(fs.rename_case(b"/Foo", b"/foo"), Err(err));
+        assert_eq!(read_file(&fs, b"/Foo").unwrap(), b"data");
+    }
+
+    #[test]
+    fn read_range_checks_bounds() {
+        let fs = MemFs::new();
+        write_file(&fs, b"/a", b"0123456789").unwrap();
+        assert_eq!(fs.read_range(b"/a", 2, 5).unwrap(), b"23456");
+        assert_eq!(fs.read_range(b"/a", 10, 0).unwrap(), b"");
+        assert_eq!(fs.read_range(b"/a", 8, 3), Err(ErrorKind::UnexpectedEof));
+        assert_eq!(fs.read_range(b"/a", 20, 1), Err(ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    fn interleaved_append_all_keeps_order() {
+        let fs = MemFs::new();
+        write_file(&fs, b"/log", b"start;").unwrap();
+        let mut options = OpenOptions::new();
+        options.write(true);
+        let mut a = fs.open(b"/log", &options).unwrap();
+        let mut b = fs.open(b"/log", &options).unwrap();
+        a.append_all(b"a1;").unwrap();
+        b.append_all(b"b1;").unwrap();
+        a.append_all(b"a2;").unwrap();
+        b.append_all(b"b2;").unwrap();
+        assert_eq!(read_file(&fs, b"/log").unwrap(), b"start;a1;b1;a2;b2;");
+    }
+
+    #[test]
+    fn create_dir_if_missing_reports_creation() {
+        let mut fs = MockFs::default();
+        let options = DirOptions::new();
+        assert_eq!(fs.create_dir_if_missing(b"/dir", &options), Ok(true));
+        assert_eq!(fs.create_dir_if_missing(b"/dir", &options), Ok(false));
+        assert!(fs.is_dir(b"/dir"));
+
+        write_file(&fs, b"/file", b"").unwrap();
+        assert_eq!(
+            fs.create_dir_if_missing(b"/file", &options),
+            Err(ErrorKind::AlreadyExists)
+        );
+        assert!(fs.is_file(b"/file"));
+
+        let mut recursive = DirOptions::new();
+        recursive.recursive(true);
+        assert_eq!(fs.create_dir_if_missing(b"/a/b", &recursive), Ok(true));
+        assert_eq!(fs.create_dir_if_missing(b"/a/b", &recursive), Ok(false));
+        assert_eq!(
+            fs.create_dir_if_missing(b"/file/sub", &recursive),
+            Err(ErrorKind::NotADirectory)
+        );
+    }
+
+    #[test]
+    fn tracks_current_dir() {
+        let mut fs = MemFs::new();
+        assert_eq!(fs.current_dir().unwrap(), b"/");
+        fs.create_dir(b"/home", &DirOptions::new()).unwrap();
+        fs.set_current_dir(b"/home").unwrap();
+        assert_eq!(fs.current_dir().unwrap(), b"/home");
+
+        write_file(&fs, b"notes", b"relative").unwrap();
+        assert_eq!(read_file(&fs, b"/home/notes").unwrap(), b"relative");
+        fs.create_dir(b"sub", &DirOptions::new()).unwrap();
+        fs.set_current_dir(b"sub").unwrap();
+        assert_eq!(fs.current_dir().unwrap(), b"/home/sub");
+        fs.set_current_dir(b"..").unwrap();
+        assert_eq!(fs.current_dir().unwrap(), b"/home");
+
+        assert_eq!(fs.set_current_dir(b"notes"), Err(ErrorKind::NotADirectory));
+        assert_eq!(fs.set_current_dir(b"/missing"), Err(ErrorKind::NotFound));
+        assert_eq!(fs.current_dir().unwrap(), b"/home");
+
+        // stateless backends resolve relative paths against the root
+        let mut fs = MockFs::default();
+        assert_eq!(fs.current_dir().unwrap(), b"/");
+        assert_eq!(fs.set_current_dir(b"/"), Err(ErrorKind::Unsupported));
+    }
+
+    #[test]
+    fn symlink_relative_stores_target_verbatim() {
+        let mut fs = MemFs::new();
+        fs.create_dir(b"/a", &DirOptions::new()).unwrap();
+        fs.create_dir(b"/a/b", &DirOptions::new()).unwrap();
+        write_file(&fs, b"/a/target", b"data").unwrap();
+        fs.symlink_relative(b"../target", b"/a/b/link").unwrap();
+        assert_eq!(fs.read_link(b"/a/b/link").unwrap(), b"../target");
+        assert_eq!(read_file(&fs, b"/a/b/link").unwrap(), b"data");
+
+        // the link keeps working when the tree is moved as a whole
+        fs.rename(b"/a", b"/moved").unwrap();
+        assert_eq!(read_file(&fs, b"/moved/b/link").unwrap(), b"data");
+
+        assert_eq!(
+            fs.symlink_relative(b"/a/target", b"/abs"),
+            Err(ErrorKind::InvalidInput)
+        );
+        assert!(fs.symlink_metadata(b"/abs").is_err());
+    }
+
+    #[test]
+    fn open_retry_retries_interrupted() {
+        let mut fs = MemFs::new();
+        write_file(&fs, b"/a", b"data").unwrap();
+        let mut options = OpenOptions::new();
+        options.read(true);
+
+        fs.fail_next(MemOp::Open, ErrorKind::Interrupted);
+        fs.fail_next(MemOp::Open, ErrorKind::Interrupted);
+        let file = fs.open_retry(b"/a", &options).unwrap();
+        let mut buf = [0; 4];
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"data");
+
+        fs.fail_next(MemOp::Open, ErrorKind::PermissionDenied);
+        assert_eq!(
+            fs.open_retry(b"/a", &options).err(),
+            Some(ErrorKind::PermissionDenied)
+        );
+
+        // the number of retries is bounded
+        for _ in 0..=::MAX_INTERRUPTED_RETRIES {
+            fs.fail_next(MemOp::Open, ErrorKind::Interrupted);
+        }
+        assert_eq!(
+            fs.open_retry(b"/a", &options).err(),
+            Some(ErrorKind::Interrupted)
+        );
+        assert!(fs.open_retry(b"/a", &options).is_ok());
+    }
+
+    #[test]
+    fn hard_link_checked_reports_emulation() {
+        let mut fs = MockFs::default();
+        write_file(&fs, b"/a", b"data").unwrap();
+        assert_eq!(fs.hard_link_checked(b"/a", b"/real"), Ok(LinkKind::True));
+        write_file(&fs, b"/a", b"changed").unwrap();
+        assert_eq!(read_file(&fs, b"/real").unwrap(), b"changed");
+
+        fs.copy_links = true;
+        assert_eq!(
+            fs.hard_link_checked(b"/a", b"/copy"),
+            Ok(LinkKind::EmulatedCopy)
+        );
+        write_file(&fs, b"/a", b"again").unwrap();
+        assert_eq!(read_file(&fs, b"/copy").unwrap(), b"changed");
+        assert_eq!(fs.metadata(b"/copy").unwrap().nlink(), 1);
+    }
+
+    #[test]
+    fn remove_file_if_exists_ignores_absent() {
+        let mut fs = MemFs::new();
+        write_file(&fs, b"/a", b"").unwrap();
+        assert_eq!(fs.remove_file_if_exists(b"/a"), Ok(true));
+        assert!(!fs.is_file(b"/a"));
+        assert_eq!(fs.remove_file_if_exists(b"/a"), Ok(false));
+        assert_eq!(fs.remove_file_if_exists(b"/missing/a"), Ok(false));
+
+        write_file(&fs, b"/b", b"").unwrap();
+        fs.fail_next(MemOp::RemoveFile, ErrorKind::PermissionDenied);
+        assert_eq!(
+            fs.remove_file_if_exists(b"/b"),
+            Err(ErrorKind::PermissionDenied)
+        );
+        assert!(fs.is_file(b"/b"));
+    }
+
+    #[test]
+    fn create_dir_applies_exact_mode() {
+        let mut fs = MemFs::new();
+        fs.create_dir(b"/private", DirOptions::new().mode(0o700))
+            .unwrap();
+        assert_eq!(fs.metadata(b"/private").unwrap().mode(), 0o700);
+
+        let mut options = DirOptions::new();
+        options.recursive(true).mode(0o750);
+        fs.create_dir(b"/a/b/c", &options).unwrap();
+        for path in &[&b"/a"[..], b"/a/b", b"/a/b/c"] {
+            assert_eq!(fs.metadata(path).unwrap().mode(), 0o750);
+        }
+        // existing directories keep their mode
+        fs.create_dir(b"/private/sub", &options).unwrap();
+        assert_eq!(fs.metadata(b"/private").unwrap().mode(), 0o700);
+    }
+
+    #[test]
+    fn recursive_create_dir_is_one_operation() {
+        let mut fs = MemFs::new();
+        let mut options = DirOptions::new();
+        options.recursive(true);
+
+        fs.fail_next(MemOp::CreateDir, ErrorKind::Other);
+        assert_eq!(fs.create_dir(b"/a/b/c", &options), Err(ErrorKind::Other));
+        assert!(fs.lookup(b"/a", false).is_err());
+
+        // neither the parents nor the checks for them use up injected errors
+        fs.fail_next(MemOp::Metadata, ErrorKind::PermissionDenied);
+        fs.create_dir(b"/a/b/c", &options).unwrap();
+        assert_eq!(fs.metadata(b"/a/b/c"), Err(ErrorKind::PermissionDenied));
+        assert!(fs.metadata(b"/a/b/c").unwrap().is_dir());
+    }
+
+    #[test]
+    fn reserve_avoids_reallocation() {
+        let fs = MemFs::new();
+        let mut options = OpenOptions::new();
+        options.write(true).create(true);
+        let mut file = fs.open(b"/a", &options).unwrap();
+        file.write_all(b"head").unwrap();
+        file.reserve(1000).unwrap();
+        let (capacity, ptr) = {
+            let inode = file.inode.borrow();
+            (inode.data.capacity(), inode.data.as_ptr())
+        };
+        assert!(capacity >= 1004);
+
+        for chunk in [0u8; 1000].chunks(100) {
+            file.write_all(chunk).unwrap();
+        }
+        let inode = file.inode.borrow();
+        assert_eq!(inode.data.len(), 1004);
+        assert_eq!(
+            (inode.data.capacity(), inode.data.as_ptr()),
+            (capacity, ptr)
+        );
+        drop(inode);
+
+        let mut reader = fs.open(b"/a", OpenOptions::new().read(true)).unwrap();
+        assert_eq!(reader.reserve(1), Err(ErrorKind::PermissionDenied));
+    }
+
+    #[test]
+    fn reflink_or_copy_falls_back() {
+        // a queued copy error shows whether `copy` was called
+        let mut fs = MemFs::new();
+        write_file(&fs, b"/a", b"data").unwrap();
+        fs.fail_next(MemOp::Copy, ErrorKind::Other);
+        fs.reflink_or_copy(b"/a", b"/clone").unwrap();
+        assert_eq!(read_file(&fs, b"/clone").unwrap(), b"data");
+        fs.fail_next(MemOp::Reflink, ErrorKind::NotFound);
+        assert_eq!(fs.reflink_or_copy(b"/a", b"/b"), Err(ErrorKind::NotFound));
+        fs.clear_faults();
+
+        let mut fs = MockFs::default();
+        write_file(&fs, b"/a", b"data").unwrap();
+        assert_eq!(fs.reflink(b"/a", b"/clone"), Err(ErrorKind::Unsupported));
+        fs.inner.fail_next(MemOp::Copy, ErrorKind::Other);
+        assert_eq!(fs.reflink_or_copy(b"/a", b"/copy"), Err(ErrorKind::Other));
+        fs.reflink_or_copy(b"/a", b"/copy").unwrap();
+        assert_eq!(read_file(&fs, b"/copy").unwrap(), b"data");
+    }
+
+    #[test]
+    fn read_dir_with_metadata_pairs_entries() {
+        let mut fs = MockFs::default();
+        fs.create_dir(b"/dir", &DirOptions::new()).unwrap();
+        write_file(&fs, b"/dir/one", b"1").unwrap();
+        write_file(&fs, b"/dir/three", b"333").unwrap();
+        fs.create_dir(b"/dir/sub", DirOptions::new().mode(0o700))
+            .unwrap();
+
+        let mut entries = fs.read_dir_with_metadata(b"/dir").unwrap();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(entries.len(), 3);
+        for (path, metadata) in &entries {
+            assert_eq!(*metadata, fs.symlink_metadata(path).unwrap());
+        }
+        assert_eq!(entries[0].0, b"/dir/one");
+        assert_eq!(entries[0].1.len(), 1);
+        assert!(entries[1].1.is_dir());
+        assert_eq!(entries[1].1.mode(), 0o700);
+        assert_eq!(entries[2].1.len(), 3);
+    }
+
+    #[test]
+    fn canonicalize_into_reuses_buffer() {
+        fn check<F>(fs: &mut F)
+        where
+            F: Fs<Path = [u8], PathOwned = Vec<u8>, Error = ErrorKind>,
+        {
+            fs.create_dir(b"/dir", &DirOptions::new()).unwrap();
+            fs.create_dir(b"/dir/sub", &DirOptions::new()).unwrap();
+            fs.open(
+                b"/dir/sub/file",
+                OpenOptions::new().write(true).create(true),
+            )
+            .unwrap();
+            fs.symlink(b"dir/sub", b"/link").unwrap();
+            fs.symlink(b"/link/../sub/file", b"/dir/abs").unwrap();
+            fs.symlink(b"/loop", b"/loop").unwrap();
+
+            let mut out = Vec::with_capacity(64);
+            let ptr = out.as_ptr();
+            fs.canonicalize_into(b"/link/./file", &mut out).unwrap();
+            assert_eq!(out, b"/dir/sub/file");
+            fs.canonicalize_into(b"//dir/abs", &mut out).unwrap();
+            assert_eq!(out, b"/dir/sub/file");
+            fs.canonicalize_into(b"/link/..", &mut out).unwrap();
+            assert_eq!(out, b"/dir");
+            assert_eq!(out.as_ptr(), ptr);
+
+            assert_eq!(
+                fs.canonicalize_into(b"/link/missing", &mut out),
+                Err(ErrorKind::NotFound)
+            );
+            assert_eq!(
+                fs.canonicalize_into(b"/loop", &mut out),
+                Err(ErrorKind::FilesystemLoop)
+            );
+        }
+
+        // the override on `MemFs` and the default implementation on `MockFs`
+        check(&mut MemFs::new());
+        check(&mut MockFs::default());
+    }
+
+    #[test]
+    fn write_zeros_overwrites_and_extends() {
+        let fs = MemFs::new();
+        write_file(&fs, b"/file", b"abcdef").unwrap();
+        let mut options = OpenOptions::new();
+        options.write(true);
+        let mut file = fs.open(b"/file", &options).unwrap();
+        file.seek(SeekFrom::Start(4)).unwrap();
+        file.write_zeros(4).unwrap();
+        assert_eq!(file.stream_position().unwrap(), 8);
+        assert_eq!(read_file(&fs, b"/file").unwrap(), b"abcd\0\0\0\0");
+    }
+
+    #[test]
+    fn find_stops_at_first_match() {
+        let mut fs = MemFs::new();
+        fs.create_dir_all(b"/a/b/c/d").unwrap();
+        write_file(&fs, b"/a/b/c/d/needle", b"").unwrap();
+        write_file(&fs, b"/a/b/c/d/other", b"").unwrap();
+        write_file(&fs, b"/z", b"").unwrap();
+
+        let mut visited = 0;
+        let found = fs
+            .find(b"/", |entry| {
+                visited += 1;
+                entry.file_name() == b"needle"
+            })
+            .unwrap();
+        assert_eq!(found.unwrap(), b"/a/b/c/d/needle");
+        assert!(visited < 7);
+
+        assert_eq!(fs.find(b"/a", |entry| entry.file_name() == b"z"), Ok(None));
+        assert_eq!(fs.find(b"/missing", |_| true), Err(ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn rename_dir_merge_overwrites_with_source() {
+        let mut fs = MemFs::new();
+        fs.create_dir_all(b"/from/both").unwrap();
+        fs.create_dir_all(b"/from/replaces_file").unwrap();
+        write_file(&fs, b"/from/both/shared", b"new").unwrap();
+        write_file(&fs, b"/from/both/only_from", b"from").unwrap();
+        write_file(&fs, b"/from/replaces_dir", b"file").unwrap();
+        fs.create_dir_all(b"/to/both").unwrap();
+        fs.create_dir_all(b"/to/replaces_dir/sub").unwrap();
+        write_file(&fs, b"/to/both/shared", b"old").unwrap();
+        write_file(&fs, b"/to/both/only_to", b"to").unwrap();
+        write_file(&fs, b"/to/replaces_file", b"file").unwrap();
+
+        fs.rename_dir_merge(b"/from", b"/to").unwrap();
+        assert_eq!(fs.metadata(b"/from").unwrap_err(), ErrorKind::NotFound);
+        assert_eq!(read_file(&fs, b"/to/both/shared").unwrap(), b"new");
+        assert_eq!(read_file(&fs, b"/to/both/only_from").unwrap(), b"from");
+        assert_eq!(read_file(&fs, b"/to/both/only_to").unwrap(), b"to");
+        assert_eq!(read_file(&fs, b"/to/replaces_dir").unwrap(), b"file");
+        assert!(fs.metadata(b"/to/replaces_file").unwrap().is_dir());
+
+        // without a target it is a plain rename
+        fs.rename_dir_merge(b"/to", b"/moved").unwrap();
+        assert_eq!(read_file(&fs, b"/moved/both/shared").unwrap(), b"new");
+        write_file(&fs, b"/file", b"").unwrap();
+        assert_eq!(
+            fs.rename_dir_merge(b"/file", b"/moved"),
+            Err(ErrorKind::NotADirectory)
+        );
+    }
+
+    #[test]
+    fn open_raw_passes_flags_and_mode() {
+        use open_flags::*;
+
+        let fs = MockFs::default();
+        let flags = O_WRONLY | O_CREAT | O_EXCL;
+        let mut file = fs.open_raw(b"/file", flags, 0o640).unwrap();
+        file.write_all(b"data").unwrap();
+        assert_eq!(fs.metadata(b"/file").unwrap().mode() & 0o777, 0o640);
+        assert_eq!(
+            fs.open_raw(b"/file", flags, 0o600).unwrap_err(),
+            ErrorKind::AlreadyExists
+        );
+        assert_eq!(
+            fs.open_raw(b"/file", O_ACCMODE, 0).unwrap_err(),
+            ErrorKind::InvalidInput
+        );
+        let file = fs.open_raw(b"/file", O_RDONLY, 0).unwrap();
+        let mut buf = [0; 8];
+        assert_eq!(file.read(&mut buf).unwrap(), 4);
+        assert_eq!(
+            *fs.raw_opens.borrow(),
+            [
+                (flags, 0o640),
+                (flags, 0o600),
+                (O_ACCMODE, 0),
+                (O_RDONLY, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn is_empty_and_file_size() {
+        let fs = MemFs::new();
+        write_file(&fs, b"/empty", b"").unwrap();
+        write_file(&fs, b"/full", b"twelve bytes").unwrap();
+        assert!(fs.metadata(b"/empty").unwrap().is_empty());
+        assert!(!fs.metadata(b"/full").unwrap().is_empty());
+        assert_eq!(fs.file_size(b"/empty").unwrap(), 0);
+        assert_eq!(fs.file_size(b"/full").unwrap(), 12);
+        assert_eq!(fs.file_size(b"/missing"), Err(ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn copy_verified_detects_corruption() {
+        let mut fs = MockFs::default();
+        write_file(&fs, b"/src", b"payload").unwrap();
+        assert_eq!(fs.copy_verified(b"/src", b"/good").unwrap(), 7);
+        assert_eq!(read_file(&fs, b"/good").unwrap(), b"payload");
+
+        fs.corrupt_copies = true;
+        assert_eq!(
+            fs.copy_verified(b"/src", b"/bad"),
+            Err(ErrorKind::InvalidData)
+        );
+        // an empty file has no byte to corrupt
+        write_file(&fs, b"/empty", b"").unwrap();
+        assert_eq!(fs.copy_verified(b"/empty", b"/copy").unwrap(), 0);
+    }
+
+    #[test]
+    fn copy_verified_retries_interrupted_reads() {
+        let mut fs = MemFs::new();
+        write_file(&fs, b"/src", b"payload").unwrap();
+        // copying doesn't read, so the errors hit the verification
+        fs.fail_next(MemOp::Read, ErrorKind::Interrupted);
+        fs.fail_next(MemOp::Read, ErrorKind::Interrupted);
+        assert_eq!(fs.copy_verified(b"/src", b"/dst").unwrap(), 7);
+        assert_eq!(read_file(&fs, b"/dst").unwrap(), b"payload");
+    }
+
+    #[test]
+    fn sync_and_flush_all_report_faults() {
+        let mut fs = MemFs::new();
+        fs.fail_next(MemOp::Sync, ErrorKind::Other);
+        fs.fail_next(MemOp::FlushAll, ErrorKind::WriteZero);
+        assert_eq!(fs.sync(), Err(ErrorKind::Other));
+        assert_eq!(fs.flush_all(), Err(ErrorKind::WriteZero));
+        assert_eq!(fs.sync(), Ok(()));
+        assert_eq!(fs.flush_all(), Ok(()));
+    }
+
+    #[test]
+    fn file_type_follows_links_unless_asked() {
+        let mut fs = MemFs::new();
+        fs.create_dir(b"/dir", &DirOptions::new()).unwrap();
+        write_file(&fs, b"/file", b"").unwrap();
+        fs.symlink(b"/dir", b"/link").unwrap();
+
+        assert_eq!(fs.file_type(b"/file").unwrap(), MemFileType::File);
+        assert_eq!(fs.file_type(b"/dir").unwrap(), MemFileType::Dir);
+        assert_eq!(fs.file_type(b"/link").unwrap(), MemFileType::Dir);
+        assert_eq!(fs.symlink_file_type(b"/file").unwrap(), MemFileType::File);
+        assert_eq!(fs.symlink_file_type(b"/dir").unwrap(), MemFileType::Dir);
+        assert_eq!(
+            fs.symlink_file_type(b"/link").unwrap(),
+            MemFileType::Symlink
+        );
+        assert_eq!(fs.file_type(b"/missing"), Err(ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn create_tree_builds_manifest() {
+        let mut fs = MemFs::new();
+        fs.create_tree(
+            b"/root",
+            &[
+                (&b"empty"[..], TreeNode::Dir),
+                (b"a/b/file", TreeNode::File(b"contents")),
+                (b"a/link", TreeNode::Symlink(b"b/file")),
+                (b"top", TreeNode::File(b"")),
+            ],
+        )
+        .unwrap();
+
+        assert!(fs.metadata(b"/root/empty").unwrap().is_dir());
+        assert!(fs.metadata(b"/root/a/b").unwrap().is_dir());
+        assert_eq!(read_file(&fs, b"/root/a/b/file").unwrap(), b"contents");
+        assert_eq!(fs.read_link(b"/root/a/link").unwrap(), b"b/file");
+        assert_eq!(read_file(&fs, b"/root/a/link").unwrap(), b"contents");
+        assert_eq!(read_file(&fs, b"/root/top").unwrap(), b"");
+
+        // files are replaced, an entry in the way of a directory is an error
+        fs.create_tree(b"/root", &[(&b"top"[..], TreeNode::File(b"new"))])
+            .unwrap();
+        assert_eq!(read_file(&fs, b"/root/top").unwrap(), b"new");
+        assert!(fs
+            .create_tree(b"/root", &[(&b"top/sub"[..], TreeNode::Dir)])
+            .is_err());
+    }
+
+    #[test]
+    fn scoped_joins_paths_to_dir() {
+        let mut fs = MemFs::new();
+        fs.create_dir_all(b"/srv/data").unwrap();
+        write_file(&fs, b"/srv/data/a", b"alpha").unwrap();
+        write_file(&fs, b"/srv/data/b", b"beta").unwrap();
+        write_file(&fs, b"/top", b"").unwrap();
+
+        let len = fs
+            .scoped(b"/srv/data", |dir| {
+                assert_eq!(dir.path(), b"/srv/data");
+                assert_eq!(dir.path_at(b"a").unwrap(), b"/srv/data/a");
+                let mut data = [0; 8];
+                let n = dir
+                    .open_at(b"a", OpenOptions::new().read(true))?
+                    .read(&mut data)?;
+                assert_eq!(&data[..n], b"alpha");
+                assert!(dir.metadata_at(b"/top")?.is_file());
+                Ok(dir.metadata_at(b"b")?.len())
+            })
+            .unwrap();
+        assert_eq!(len, 4);
+
+        fs.scoped_mut(b"/srv/data", |dir| {
+            dir.remove_file_at(b"a")?;
+            assert_eq!(dir.metadata_at(b"a").unwrap_err(), ErrorKind::NotFound);
+            dir.open_at(b"c", OpenOptions::new().write(true).create(true))?;
+            Ok(())
+        })
+        .unwrap();
+        assert!(fs.is_file(b"/srv/data/c"));
+        assert!(fs.is_file(b"/srv/data/b"));
+
+        assert_eq!(
+            fs.scoped(b"/top", |_| Ok(())),
+            Err(ErrorKind::NotADirectory)
+        );
+        assert_eq!(
+            fs.scoped(b"/missing", |_| Ok(())),
+            Err(ErrorKind::NotFound)
+        );
+    }
+
+    #[test]
+    fn scoped_resolves_dir_once() {
+        let mut fs = MemFs::new();
+        fs.create_dir_all(b"/srv/data").unwrap();
+        write_file(&fs, b"/srv/data/a", b"alpha").unwrap();
+        fs.symlink(b"srv/data", b"/current").unwrap();
+        fs.set_current_dir(b"/srv").unwrap();
+
+        fs.scoped(b"/current", |dir| {
+            assert_eq!(dir.path(), b"/srv/data");
+            assert!(dir.metadata_at(b"a")?.is_file());
+            Ok(())
+        })
+        .unwrap();
+        fs.scoped_mut(b"data", |dir| {
+            assert_eq!(dir.path(), b"/srv/data");
+            dir.remove_file_at(b"a")
+        })
+        .unwrap();
+        assert!(!fs.is_file(b"/srv/data/a"));
+        assert_eq!(
+            fs.scoped(b"/srv/missing", |_| Ok(())),
+            Err(ErrorKind::NotFound)
+        );
+    }
+
+    #[test]
+    fn remove_dir_all_guarded_rejects_root() {
+        let mut fs = MemFs::new();
+        fs.create_dir_all(b"/data/sub").unwrap();
+        write_file(&fs, b"/data/sub/file", b"").unwrap();
+
+        for path in [&b"/"[..], b"//", b"", b"/data/..", b"."] {
+            assert_eq!(
+                fs.remove_dir_all_guarded(path),
+                Err(ErrorKind::InvalidInput)
+            );
+        }
+        assert!(fs.is_file(b"/data/sub/file"));
+
+        fs.remove_dir_all_guarded(b"/data/sub").unwrap();
+        assert_eq!(fs.metadata(b"/data/sub").unwrap_err(), ErrorKind::NotFound);
+        assert!(fs.metadata(b"/data").unwrap().is_dir());
+    }
+
+    #[test]
+    fn set_permissions_nofollow_changes_link_only() {
+        let mut fs = MemFs::new();
+        write_file(&fs, b"/target", b"").unwrap();
+        fs.set_permissions(b"/target", 0o644).unwrap();
+        fs.symlink(b"/target", b"/link").unwrap();
+
+        fs.set_permissions_nofollow(b"/link", 0o600).unwrap();
+        assert_eq!(fs.symlink_metadata(b"/link").unwrap().mode(), 0o600);
+        assert_eq!(fs.metadata(b"/target").unwrap().mode(), 0o644);
+
+        fs.set_permissions(b"/link", 0o640).unwrap();
+        assert_eq!(fs.symlink_metadata(b"/link").unwrap().mode(), 0o600);
+        assert_eq!(fs.metadata(b"/target").unwrap().mode(), 0o640);
+    }
+}
@@ -0,0 +1,549 @@
+//! Test doubles shared by the unit tests of this crate.
+
+#![allow(dead_code)]
+
+use std::borrow::Borrow;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::rc::Rc;
+use std::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use mem::{MemDirEntry, MemFile, MemFs, MemMetadata};
+#[cfg(feature = "alloc")]
+use {Component, DirOptions, LinkKind, MountInfo};
+use {
+    Dir, DirEntry, DirPos, ErrorKind, ErrorType, File, FileType, Fs, Metadata,
+    OpenOptions, Path, PathBuf, Read, Seek, SeekFrom, UnixMetadataExt, Write,
+};
+
+/// Creates or truncates the file at `path` and writes `data` to it.
+pub fn write_file<F: Fs + ?Sized>(
+    fs: &F,
+    path: &F::Path,
+    data: &[u8],
+) -> Result<(), F::Error> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    fs.open(path, &options)?.write_all(data)
+}
+
+/// Reads the whole file at `path`.
+pub fn read_file<F: Fs + ?Sized>(
+    fs: &F,
+    path: &F::Path,
+) -> Result<Vec<u8>, F::Error> {
+    let file = fs.open(path, OpenOptions::new().read(true))?;
+    let mut data = Vec::new();
+    let mut buf = [0; 64];
+    loop {
+        match file.read(&mut buf)? {
+            0 => return Ok(data),
+            n => data.extend_from_slice(&buf[..n]),
+        }
+    }
+}
+
+/// An in-memory stream over a vector of bytes, like `std::io::Cursor`.
+///
+/// Every call is counted, transfers can be limited to a few bytes per call
+/// and errors can be scripted per call with [`fail`].
+///
+/// [`fail`]: #method.fail
+#[derive(Debug, Default)]
+pub struct Cursor {
+    data: RefCell<Vec<u8>>,
+    pos: Cell<u64>,
+    chunk: Option<usize>,
+    script: RefCell<VecDeque<Option<ErrorKind>>>,
+    pub reads: Cell<usize>,
+    pub writes: usize,
+    pub seeks: usize,
+    pub flushes: usize,
+    /// The hint returned by `is_flush_noop`.
+    pub flush_noop: bool,
+}
+
+impl Cursor {
+    pub fn new(data: &[u8]) -> Self {
+        Cursor {
+            data: RefCell::new(data.to_vec()),
+            ..Cursor::default()
+        }
+    }
+
+    /// Limits every read and write to at most `chunk` bytes.
+    pub fn chunked(mut self, chunk: usize) -> Self {
+        self.chunk = Some(chunk);
+        self
+    }
+
+    /// Queues the outcome of a call: `Some` fails the call with an error of
+    /// that kind, `None` lets it through. Outcomes are used up in order by
+    /// all calls on the cursor.
+    pub fn fail(&self, outcome: Option<ErrorKind>) {
+        self.script.borrow_mut().push_back(outcome);
+    }
+
+    pub fn data(&self) -> Vec<u8> {
+        self.data.borrow().clone()
+    }
+
+    pub fn position(&self) -> u64 {
+        self.pos.get()
+    }
+
+    fn step(&self) -> Result<(), ErrorKind> {
+        match self.script.borrow_mut().pop_front() {
+            Some(Some(kind)) => Err(kind),
+            _ => Ok(()),
+        }
+    }
+
+    fn limit(&self, len: usize) -> usize {
+        self.chunk.map_or(len, |chunk| len.min(chunk))
+    }
+}
+
+impl ErrorType for Cursor {
+    type Error = ErrorKind;
+}
+
+impl Read for Cursor {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+        self.reads.set(self.reads.get() + 1);
+        self.step()?;
+        let data = self.data.borrow();
+        let start = usize::try_from(self.pos.get())
+            .unwrap_or(usize::MAX)
+            .min(data.len());
+        let len = self.limit(buf.len()).min(data.len() - start);
+        buf[..len].copy_from_slice(&data[start..start + len]);
+        self.pos.set(self.pos.get() + len as u64);
+        Ok(len)
+    }
+}
+
+impl Write for Cursor {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+        self.writes += 1;
+        self.step()?;
+        let len = self.limit(buf.len());
+        let start = self.pos.get() as usize;
+        let data = self.data.get_mut();
+        if data.len() < start + len {
+            data.resize(start + len, 0);
+        }
+        data[start..start + len].copy_from_slice(&buf[..len]);
+        self.pos.set((start + len) as u64);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<(), ErrorKind> {
+        self.flushes += 1;
+        self.step()
+    }
+
+    fn is_flush_noop(&self) -> bool {
+        self.flush_noop
+    }
+}
+
+impl Seek for Cursor {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, ErrorKind> {
+        self.seeks += 1;
+        self.step()?;
+        let len = self.data.get_mut().len() as u64;
+        let pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => {
+                self.pos.get().checked_add_signed(offset)
+            }
+        };
+        let pos = pos.ok_or(ErrorKind::InvalidInput)?;
+        self.pos.set(pos);
+        Ok(pos)
+    }
+}
+
+impl File for Cursor {}
+
+/// An owned byte path which doesn't depend on the `alloc` feature.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MockPath(pub Vec<u8>);
+
+impl Borrow<[u8]> for MockPath {
+    fn borrow(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl PathBuf<[u8]> for MockPath {
+    fn from_path(path: &[u8]) -> Result<Self, ErrorKind> {
+        Ok(MockPath(path.to_vec()))
+    }
+
+    fn push(&mut self, path: &[u8]) -> Result<(), ErrorKind> {
+        let sep = <[u8]>::separator();
+        if path.is_absolute() {
+            self.0.clear();
+        } else if !self.0.is_empty() && self.0.last() != Some(&sep) {
+            self.0.push(sep);
+        }
+        self.0.extend_from_slice(path);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockType {
+    File,
+    Dir,
+    Symlink,
+}
+
+impl FileType for MockType {
+    fn is_dir(&self) -> bool {
+        *self == MockType::Dir
+    }
+
+    fn is_file(&self) -> bool {
+        *self == MockType::File
+    }
+
+    fn is_symlink(&self) -> bool {
+        *self == MockType::Symlink
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MockMetadata {
+    pub file_type: MockType,
+    pub len: u64,
+    /// Overrides the number of allocated blocks, for sparse files.
+    pub blocks: Option<u64>,
+}
+
+impl Metadata for MockMetadata {
+    type FileType = MockType;
+
+    fn file_type(&self) -> MockType {
+        self.file_type
+    }
+
+    fn len(&self) -> u64 {
+        self.len
+    }
+}
+
+impl UnixMetadataExt for MockMetadata {
+    fn mode(&self) -> u32 {
+        0o644
+    }
+
+    fn blocks(&self) -> u64 {
+        self.blocks.unwrap_or_else(|| self.len.div_ceil(512))
+    }
+}
+
+/// A directory entry which counts how often its metadata is requested.
+#[derive(Debug, Clone)]
+pub struct MockEntry {
+    path: MockPath,
+    file_type: MockType,
+    pub metadata_calls: Rc<Cell<usize>>,
+}
+
+impl MockEntry {
+    pub fn new(name: &[u8], file_type: MockType) -> Self {
+        MockEntry {
+            path: MockPath(name.to_vec()),
+            file_type,
+            metadata_calls: Rc::default(),
+        }
+    }
+}
+
+impl DirEntry for MockEntry {
+    type Path = [u8];
+    type PathOwned = MockPath;
+    type Metadata = MockMetadata;
+    type FileType = MockType;
+    type Error = ErrorKind;
+
+    fn path(&self) -> MockPath {
+        self.path.clone()
+    }
+
+    fn metadata(&self) -> Result<MockMetadata, ErrorKind> {
+        self.metadata_calls.set(self.metadata_calls.get() + 1);
+        Ok(MockMetadata {
+            file_type: self.file_type,
+            len: self.path.0.len() as u64,
+            blocks: None,
+        })
+    }
+
+    fn file_type(&self) -> Result<MockType, ErrorKind> {
+        Ok(self.file_type)
+    }
+
+    fn file_name(&self) -> &[u8] {
+        &self.path.0
+    }
+}
+
+/// A directory listing in the order it was created with, supporting
+/// rewinding and seeking.
+#[derive(Debug, Clone)]
+pub struct MockDir<E = MockEntry> {
+    entries: Vec<E>,
+    pos: usize,
+}
+
+impl MockDir {
+    pub fn new(entries: &[(&[u8], MockType)]) -> Self {
+        MockDir::from_entries(
+            entries
+                .iter()
+                .map(|&(name, file_type)| MockEntry::new(name, file_type))
+                .collect(),
+        )
+    }
+}
+
+impl<E> MockDir<E> {
+    pub fn from_entries(entries: Vec<E>) -> Self {
+        MockDir { entries, pos: 0 }
+    }
+}
+
+impl<E: Clone> Iterator for MockDir<E> {
+    type Item = Result<E, ErrorKind>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.entries.get(self.pos)?.clone();
+        self.pos += 1;
+        Some(Ok(entry))
+    }
+}
+
+impl<E> Dir<E, ErrorKind> for MockDir<E>
+where
+    E: DirEntry + Clone,
+{
+    fn rewind(&mut self) -> Result<(), ErrorKind> {
+        self.pos = 0;
+        Ok(())
+    }
+
+    fn tell(&self) -> Result<DirPos, ErrorKind> {
+        Ok(DirPos::from_raw(self.pos as u64))
+    }
+
+    fn seek(&mut self, pos: DirPos) -> Result<(), ErrorKind> {
+        self.pos = pos.into_raw() as usize;
+        Ok(())
+    }
+}
+
+/// A filesystem backed by a [`MemFs`], with knobs for the optional features
+/// which `MemFs` doesn't have.
+///
+/// [`MemFs`]: ../mem/struct.MemFs.html
+#[cfg(feature = "alloc")]
+#[derive(Debug, Default)]
+pub struct MockFs {
+    pub inner: MemFs,
+    /// Lists directories in reverse order.
+    pub reversed: bool,
+    /// Matches names case-insensitively, but preserves their case, in the
+    /// paths passed to `open`, `remove_file`, `metadata`, `symlink_metadata`,
+    /// `rename` and `read_dir`.
+    pub case_insensitive: bool,
+    /// Emulates hard links by copying.
+    pub copy_links: bool,
+    /// The mounts reported by `mount_points`. The id of a mount is its index
+    /// plus one, the id 0 is reported for paths outside of all mounts.
+    pub mounts: Vec<MountInfo<Vec<u8>>>,
+    /// The flags and modes passed to `open_raw`, which are forwarded to the
+    /// default implementation of `MemFs`.
+    pub raw_opens: RefCell<Vec<(u32, u32)>>,
+    /// Flips the bits of the last byte written by `copy`, like flaky
+    /// storage.
+    pub corrupt_copies: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl MockFs {
+    /// Replaces each component of `path` by the name of an existing entry
+    /// which only differs in ASCII case, if `case_insensitive` is set.
+    fn fold(&self, path: &[u8]) -> Vec<u8> {
+        if !self.case_insensitive {
+            return path.to_vec();
+        }
+        let mut folded = Vec::new();
+        for component in path.components() {
+            let name = match component {
+                Component::Normal(name) => self
+                    .inner
+                    .read_dir(&folded)
+                    .ok()
+                    .and_then(|dir| {
+                        dir.filter_map(Result::ok)
+                            .map(|entry| entry.file_name().to_vec())
+                            .find(|entry| entry.eq_ignore_ascii_case(name))
+                    })
+                    .unwrap_or_else(|| name.to_vec()),
+                component => component.as_bytes::<[u8]>().to_vec(),
+            };
+            PathBuf::push(&mut folded, &name[..]).unwrap();
+        }
+        folded
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Fs for MockFs {
+    type Path = [u8];
+    type PathOwned = Vec<u8>;
+    type File = MemFile;
+    type Dir = MockDir<MemDirEntry>;
+    type DirEntry = MemDirEntry;
+    type Metadata = MemMetadata;
+    type Permissions = u32;
+    type Error = ErrorKind;
+
+    fn open(
+        &self,
+        path: &[u8],
+        options: &OpenOptions<u32>,
+    ) -> Result<MemFile, ErrorKind> {
+        self.inner.open(&self.fold(path), options)
+    }
+
+    fn open_raw(
+        &self,
+        path: &[u8],
+        flags: u32,
+        mode: u32,
+    ) -> Result<MemFile, ErrorKind> {
+        self.raw_opens.borrow_mut().push((flags, mode));
+        self.inner.open_raw(&self.fold(path), flags, mode)
+    }
+
+    fn remove_file(&mut self, path: &[u8]) -> Result<(), ErrorKind> {
+        self.inner.remove_file(&self.fold(path))
+    }
+
+    fn metadata(&self, path: &[u8]) -> Result<MemMetadata, ErrorKind> {
+        self.inner.metadata(&self.fold(path))
+    }
+
+    fn symlink_metadata(&self, path: &[u8]) -> Result<MemMetadata, ErrorKind> {
+        self.inner.symlink_metadata(&self.fold(path))
+    }
+
+    fn rename(&mut self, from: &[u8], to: &[u8]) -> Result<(), ErrorKind> {
+        self.inner.rename(&self.fold(from), &self.fold(to))
+    }
+
+    fn copy(&mut self, from: &[u8], to: &[u8]) -> Result<u64, ErrorKind> {
+        let copied = self.inner.copy(from, to)?;
+        if self.corrupt_copies {
+            let mut data = read_file(&self.inner, to)?;
+            if let Some(last) = data.last_mut() {
+                *last = !*last;
+            }
+            write_file(&self.inner, to, &data)?;
+        }
+        Ok(copied)
+    }
+
+    fn hard_link(&mut self, src: &[u8], dst: &[u8]) -> Result<(), ErrorKind> {
+        self.hard_link_checked(src, dst).map(|_| ())
+    }
+
+    fn hard_link_checked(
+        &mut self,
+        src: &[u8],
+        dst: &[u8],
+    ) -> Result<LinkKind, ErrorKind> {
+        if self.copy_links {
+            self.inner.copy(src, dst)?;
+            Ok(LinkKind::EmulatedCopy)
+        } else {
+            self.inner.hard_link_checked(src, dst)
+        }
+    }
+
+    fn symlink_file(
+        &mut self,
+        src: &[u8],
+        dst: &[u8],
+    ) -> Result<(), ErrorKind> {
+        self.inner.symlink_file(src, dst)
+    }
+
+    fn read_link(&self, path: &[u8]) -> Result<Vec<u8>, ErrorKind> {
+        self.inner.read_link(path)
+    }
+
+    fn canonicalize(&self, path: &[u8]) -> Result<Vec<u8>, ErrorKind> {
+        self.inner.canonicalize(path)
+    }
+
+    fn create_dir(
+        &mut self,
+        path: &[u8],
+        options: &DirOptions<u32>,
+    ) -> Result<(), ErrorKind> {
+        self.inner.create_dir(path, options)
+    }
+
+    fn remove_dir(&mut self, path: &[u8]) -> Result<(), ErrorKind> {
+        self.inner.remove_dir(path)
+    }
+
+    fn remove_dir_all(&mut self, path: &[u8]) -> Result<(), ErrorKind> {
+        self.inner.remove_dir_all(path)
+    }
+
+    fn read_dir(&self, path: &[u8]) -> Result<Self::Dir, ErrorKind> {
+        let mut entries = self
+            .inner
+            .read_dir(&self.fold(path))?
+            .collect::<Result<Vec<_>, _>>()?;
+        if self.reversed {
+            entries.reverse();
+        }
+        Ok(MockDir::from_entries(entries))
+    }
+
+    fn set_permissions(
+        &mut self,
+        path: &[u8],
+        perm: u32,
+    ) -> Result<(), ErrorKind> {
+        self.inner.set_permissions(path, perm)
+    }
+
+    fn mount_points(&self) -> Result<Vec<MountInfo<Vec<u8>>>, ErrorKind> {
+        Ok(self.mounts.clone())
+    }
+
+    fn mount_id(&self, path: &[u8]) -> Result<u64, ErrorKind> {
+        let path = self.inner.canonicalize(path)?;
+        let innermost = self
+            .mounts
+            .iter()
+            .enumerate()
+            .filter(|(_, mount)| {
+                <[u8] as Path>::starts_with(&path, &mount.path)
+            })
+            .max_by_key(|(_, mount)| mount.path.len());
+        Ok(innermost.map_or(0, |(i, _)| i as u64 + 1))
+    }
+}
@@ -0,0 +1,15 @@
+use alloc::string::String;
+
+/// Information about a mounted filesystem, as returned by
+/// [`Fs::mount_points`].
+///
+/// [`Fs::mount_points`]: trait.Fs.html#method.mount_points
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MountInfo<P> {
+    /// The path the filesystem is mounted at.
+    pub path: P,
+    /// The type of the filesystem, like `ext4` or `tmpfs`.
+    pub fs_type: String,
+    /// Whether the filesystem is mounted read-only.
+    pub read_only: bool,
+}
@@ -0,0 +1,707 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+#[cfg(feature = "alloc")]
+use MountInfo;
+use {
+    DirOptions, Error, ErrorKind, FileTimes, Fs, LinkKind, Metadata,
+    OpenOptions, SpecialKind,
+};
+
+/// An operation on a filesystem, along with its path arguments.
+///
+/// It is part of every [`FsEvent`] reported by an [`ObserveFs`].
+///
+/// [`FsEvent`]: struct.FsEvent.html
+/// [`ObserveFs`]: struct.ObserveFs.html
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum FsOp<'a, P: ?Sized + 'a> {
+    /// `Fs::open` with the path of the file.
+    Open(&'a P),
+    /// `Fs::open_anonymous` with the path of the directory.
+    OpenAnonymous(&'a P),
+    /// `Fs::remove_file` with the path of the file.
+    RemoveFile(&'a P),
+    /// `Fs::metadata` with the queried path.
+    Metadata(&'a P),
+    /// `Fs::symlink_metadata` with the queried path.
+    SymlinkMetadata(&'a P),
+    /// `Fs::file_type` with the queried path.
+    FileType(&'a P),
+    /// `Fs::symlink_file_type` with the queried path.
+    SymlinkFileType(&'a P),
+    /// `Fs::rename` with the source and destination paths.
+    Rename(&'a P, &'a P),
+    /// `Fs::copy` with the source and destination paths.
+    Copy(&'a P, &'a P),
+    /// `Fs::reflink` with the source and destination paths.
+    Reflink(&'a P, &'a P),
+    /// `Fs::hard_link` with the source and destination paths.
+    HardLink(&'a P, &'a P),
+    /// `Fs::symlink` with the source and destination paths.
+    Symlink(&'a P, &'a P),
+    /// `Fs::mknod` with the path of the special file.
+    Mknod(&'a P),
+    /// `Fs::read_link` with the path of the link.
+    ReadLink(&'a P),
+    /// `Fs::canonicalize` with the path being resolved.
+    Canonicalize(&'a P),
+    /// `Fs::create_dir` with the path of the directory.
+    CreateDir(&'a P),
+    /// `Fs::remove_dir` with the path of the directory.
+    RemoveDir(&'a P),
+    /// `Fs::remove_dir_all` with the path of the directory.
+    RemoveDirAll(&'a P),
+    /// `Fs::read_dir` with the path of the directory.
+    ReadDir(&'a P),
+    /// `Fs::set_permissions` with the path whose permissions are changed.
+    SetPermissions(&'a P),
+    /// `Fs::set_permissions_nofollow` with the path whose permissions are
+    /// changed.
+    SetPermissionsNofollow(&'a P),
+    /// `Fs::set_times` with the path whose timestamps are changed.
+    SetTimes(&'a P),
+    /// `Fs::current_dir`, which has no path arguments.
+    CurrentDir,
+    /// `Fs::set_current_dir` with the new working directory.
+    SetCurrentDir(&'a P),
+    /// `Fs::available_space` with the queried path.
+    AvailableSpace(&'a P),
+    /// `Fs::mount_points`, which has no path arguments.
+    MountPoints,
+    /// `Fs::mount_id` with the queried path.
+    MountId(&'a P),
+    /// `Fs::sync`, which has no path arguments.
+    Sync,
+    /// `Fs::flush_all`, which has no path arguments.
+    FlushAll,
+}
+
+impl<'a, P: ?Sized> Clone for FsOp<'a, P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, P: ?Sized> Copy for FsOp<'a, P> {}
+
+/// The point in an operation at which an [`FsEvent`] is reported.
+///
+/// [`FsEvent`]: struct.FsEvent.html
+#[derive(Copy, PartialEq, Eq, Clone, Debug, Hash)]
+pub enum FsStage {
+    /// The operation is about to be forwarded to the wrapped filesystem.
+    Before,
+    /// The operation has completed, successfully or with an error of the given
+    /// kind.
+    After(Result<(), ErrorKind>),
+}
+
+/// An event reported by an [`ObserveFs`] to its callback.
+///
+/// [`ObserveFs`]: struct.ObserveFs.html
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct FsEvent<'a, P: ?Sized + 'a> {
+    /// The operation performed.
+    pub op: FsOp<'a, P>,
+    /// Whether the operation is about to start or has completed.
+    pub stage: FsStage,
+}
+
+impl<'a, P: ?Sized> Clone for FsEvent<'a, P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, P: ?Sized> Copy for FsEvent<'a, P> {}
+
+/// A filesystem wrapper which reports every operation to a callback.
+///
+/// The callback is invoked with an [`FsEvent`] right before an operation is
+/// forwarded to the wrapped filesystem and once more after it completed. All
+/// results are passed through unchanged. This can be used for access logging
+/// or for asserting on the sequence of calls made by higher-level code.
+///
+/// Only operations on the filesystem itself are reported, reads and writes on
+/// opened files are not.
+///
+/// [`FsEvent`]: struct.FsEvent.html
+#[derive(Debug)]
+pub struct ObserveFs<F, C> {
+    inner: F,
+    callback: RefCell<C>,
+}
+
+impl<F, C> ObserveFs<F, C>
+where
+    F: Fs,
+    C: FnMut(&FsEvent<F::Path>),
+{
+    /// Wraps a filesystem, reporting all operations on it to `callback`.
+    pub fn new(inner: F, callback: C) -> Self {
+        ObserveFs {
+            inner,
+            callback: RefCell::new(callback),
+        }
+    }
+
+    /// Gets a reference to the underlying filesystem.
+    pub fn get_ref(&self) -> &F {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying filesystem.
+    ///
+    /// Operations performed through this reference are not reported.
+    pub fn get_mut(&mut self) -> &mut F {
+        &mut self.inner
+    }
+
+    /// Unwraps this `ObserveFs`, returning the underlying filesystem and the
+    /// callback.
+    pub fn into_inner(self) -> (F, C) {
+        (self.inner, self.callback.into_inner())
+    }
+}
+
+fn observe<P, C, T, E, G>(
+    callback: &RefCell<C>,
+    op: FsOp<P>,
+    f: G,
+) -> Result<T, E>
+where
+    P: ?Sized,
+    C: FnMut(&FsEvent<P>),
+    E: Error,
+    G: FnOnce() -> Result<T, E>,
+{
+    (callback.borrow_mut())(&FsEvent {
+        op,
+        stage: FsStage::Before,
+    });
+    let result = f();
+    (callback.borrow_mut())(&FsEvent {
+        op,
+        stage: FsStage::After(match result {
+            Ok(_) => Ok(()),
+            Err(ref err) => Err(err.kind()),
+        }),
+    });
+    result
+}
+
+impl<F, C> Fs for ObserveFs<F, C>
+where
+    F: Fs,
+    C: FnMut(&FsEvent<F::Path>),
+{
+    type Path = F::Path;
+    type PathOwned = F::PathOwned;
+    type File = F::File;
+    type Dir = F::Dir;
+    type DirEntry = F::DirEntry;
+    type Metadata = F::Metadata;
+    type Permissions = F::Permissions;
+    type Error = F::Error;
+
+    fn open(
+        &self,
+        path: &Self::Path,
+        options: &OpenOptions<Self::Permissions>,
+    ) -> Result<Self::File, Self::Error> {
+        let inner = &self.inner;
+        observe(&self.callback, FsOp::Open(path), || {
+            inner.open(path, options)
+        })
+    }
+
+    fn open_anonymous(
+        &mut self,
+        dir: &Self::Path,
+        options: &OpenOptions<Self::Permissions>,
+    ) -> Result<Self::File, Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::OpenAnonymous(dir), || {
+            inner.open_anonymous(dir, options)
+        })
+    }
+
+    fn remove_file(&mut self, path: &Self::Path) -> Result<(), Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::RemoveFile(path), || {
+            inner.remove_file(path)
+        })
+    }
+
+    fn metadata(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::Metadata, Self::Error> {
+        let inner = &self.inner;
+        observe(&self.callback, FsOp::Metadata(path), || {
+            inner.metadata(path)
+        })
+    }
+
+    fn symlink_metadata(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::Metadata, Self::Error> {
+        let inner = &self.inner;
+        observe(&self.callback, FsOp::SymlinkMetadata(path), || {
+            inner.symlink_metadata(path)
+        })
+    }
+
+    fn file_type(
+        &self,
+        path: &Self::Path,
+    ) -> Result<<Self::Metadata as Metadata>::FileType, Self::Error> {
+        let inner = &self.inner;
+        observe(&self.callback, FsOp::FileType(path), || {
+            inner.file_type(path)
+        })
+    }
+
+    fn symlink_file_type(
+        &self,
+        path: &Self::Path,
+    ) -> Result<<Self::Metadata as Metadata>::FileType, Self::Error> {
+        let inner = &self.inner;
+        observe(&self.callback, FsOp::SymlinkFileType(path), || {
+            inner.symlink_file_type(path)
+        })
+    }
+
+    fn rename(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::Rename(from, to), || {
+            inner.rename(from, to)
+        })
+    }
+
+    fn copy(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<u64, Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::Copy(from, to), || {
+            inner.copy(from, to)
+        })
+    }
+
+    fn reflink(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::Reflink(from, to), || {
+            inner.reflink(from, to)
+        })
+    }
+
+    fn hard_link(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::HardLink(src, dst), || {
+            inner.hard_link(src, dst)
+        })
+    }
+
+    fn hard_link_checked(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<LinkKind, Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::HardLink(src, dst), || {
+            inner.hard_link_checked(src, dst)
+        })
+    }
+
+    fn symlink(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::Symlink(src, dst), || {
+            inner.symlink(src, dst)
+        })
+    }
+
+    fn symlink_file(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::Symlink(src, dst), || {
+            inner.symlink_file(src, dst)
+        })
+    }
+
+    fn symlink_dir(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::Symlink(src, dst), || {
+            inner.symlink_dir(src, dst)
+        })
+    }
+
+    fn mknod(
+        &mut self,
+        path: &Self::Path,
+        mode: Self::Permissions,
+        kind: SpecialKind,
+        dev: u64,
+    ) -> Result<(), Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::Mknod(path), || {
+            inner.mknod(path, mode, kind, dev)
+        })
+    }
+
+    fn read_link(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::PathOwned, Self::Error> {
+        let inner = &self.inner;
+        observe(&self.callback, FsOp::ReadLink(path), || {
+            inner.read_link(path)
+        })
+    }
+
+    fn canonicalize(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::PathOwned, Self::Error> {
+        let inner = &self.inner;
+        observe(&self.callback, FsOp::Canonicalize(path), || {
+            inner.canonicalize(path)
+        })
+    }
+
+    fn canonicalize_into(
+        &self,
+        path: &Self::Path,
+        out: &mut Self::PathOwned,
+    ) -> Result<(), Self::Error> {
+        let inner = &self.inner;
+        observe(&self.callback, FsOp::Canonicalize(path), || {
+            inner.canonicalize_into(path, out)
+        })
+    }
+
+    fn create_dir(
+        &mut self,
+        path: &Self::Path,
+        options: &DirOptions<Self::Permissions>,
+    ) -> Result<(), Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::CreateDir(path), || {
+            inner.create_dir(path, options)
+        })
+    }
+
+    fn remove_dir(&mut self, path: &Self::Path) -> Result<(), Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::RemoveDir(path), || {
+            inner.remove_dir(path)
+        })
+    }
+
+    fn remove_dir_all(&mut self, path: &Self::Path) -> Result<(), Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::RemoveDirAll(path), || {
+            inner.remove_dir_all(path)
+        })
+    }
+
+    fn read_dir(&self, path: &Self::Path) -> Result<Self::Dir, Self::Error> {
+        let inner = &self.inner;
+        observe(&self.callback, FsOp::ReadDir(path), || inner.read_dir(path))
+    }
+
+    fn set_permissions(
+        &mut self,
+        path: &Self::Path,
+        perm: Self::Permissions,
+    ) -> Result<(), Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::SetPermissions(path), || {
+            inner.set_permissions(path, perm)
+        })
+    }
+
+    fn set_permissions_nofollow(
+        &mut self,
+        path: &Self::Path,
+        perm: Self::Permissions,
+    ) -> Result<(), Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::SetPermissionsNofollow(path), || {
+            inner.set_permissions_nofollow(path, perm)
+        })
+    }
+
+    fn set_times(
+        &mut self,
+        path: &Self::Path,
+        times: FileTimes,
+    ) -> Result<(), Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::SetTimes(path), || {
+            inner.set_times(path, times)
+        })
+    }
+
+    fn current_dir(&self) -> Result<Self::PathOwned, Self::Error> {
+        let inner = &self.inner;
+        observe(&self.callback, FsOp::CurrentDir, || inner.current_dir())
+    }
+
+    fn set_current_dir(
+        &mut self,
+        path: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::SetCurrentDir(path), || {
+            inner.set_current_dir(path)
+        })
+    }
+
+    fn available_space(&self, path: &Self::Path) -> Result<u64, Self::Error> {
+        let inner = &self.inner;
+        observe(&self.callback, FsOp::AvailableSpace(path), || {
+            inner.available_space(path)
+        })
+    }
+
+    #[cfg(feature = "alloc")]
+    fn mount_points(
+        &self,
+    ) -> Result<Vec<MountInfo<Self::PathOwned>>, Self::Error> {
+        let inner = &self.inner;
+        observe(&self.callback, FsOp::MountPoints, || inner.mount_points())
+    }
+
+    fn mount_id(&self, path: &Self::Path) -> Result<u64, Self::Error> {
+        let inner = &self.inner;
+        observe(&self.callback, FsOp::MountId(path), || inner.mount_id(path))
+    }
+
+    fn sync(&self) -> Result<(), Self::Error> {
+        let inner = &self.inner;
+        observe(&self.callback, FsOp::Sync, || inner.sync())
+    }
+
+    fn flush_all(&mut self) -> Result<(), Self::Error> {
+        let inner = &mut self.inner;
+        observe(&self.callback, FsOp::FlushAll, || inner.flush_all())
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use mem::{MemFileType, MemFs, MemOp};
+    use mock::MockFs;
+
+    fn name(op: &FsOp<[u8]>) -> (&'static str, Vec<u8>) {
+        let (name, path): (_, &[u8]) = match *op {
+            FsOp::Metadata(path) => ("metadata", path),
+            FsOp::CreateDir(path) => ("create_dir", path),
+            _ => ("other", b""),
+        };
+        (name, path.to_vec())
+    }
+
+    #[test]
+    fn records_calls_of_helper() {
+        let mut log = Vec::new();
+        let mut fs = ObserveFs::new(MemFs::new(), |event: &FsEvent<[u8]>| {
+            log.push((name(&event.op), event.stage));
+        });
+        fs.create_dir_all(b"/a/b").unwrap();
+        drop(fs);
+
+        let ok = FsStage::After(Ok(()));
+        let not_found = FsStage::After(Err(ErrorKind::NotFound));
+        let expected = [
+            (("metadata", b"/a/b".to_vec()), not_found),
+            (("metadata", b"/a".to_vec()), not_found),
+            (("metadata", b"/".to_vec()), ok),
+            (("create_dir", b"/a".to_vec()), ok),
+            (("create_dir", b"/a/b".to_vec()), ok),
+        ];
+        let completed: Vec<_> = log
+            .iter()
+            .filter(|(_, stage)| *stage != FsStage::Before)
+            .cloned()
+            .collect();
+        assert_eq!(completed, expected);
+        assert_eq!(log.len(), 2 * expected.len());
+        assert_eq!(log[0], (("metadata", b"/a/b".to_vec()), FsStage::Before));
+    }
+
+    #[test]
+    fn forwards_available_space() {
+        let mut inner = MemFs::new();
+        inner.fail_next(MemOp::AvailableSpace, ErrorKind::Other);
+        let mut log = Vec::new();
+        let fs = ObserveFs::new(inner, |event: &FsEvent<[u8]>| {
+            if let FsOp::AvailableSpace(path) = event.op {
+                log.push((path.to_vec(), event.stage));
+            }
+        });
+        assert_eq!(fs.available_space(b"/"), Err(ErrorKind::Other));
+        assert_eq!(fs.available_space(b"/"), fs.inner.available_space(b"/"));
+        drop(fs);
+        assert_eq!(
+            log[..2],
+            [
+                (b"/".to_vec(), FsStage::Before),
+                (b"/".to_vec(), FsStage::After(Err(ErrorKind::Other)))
+            ]
+        );
+    }
+
+    #[test]
+    fn forwards_sync() {
+        let mut inner = MemFs::new();
+        inner.fail_next(MemOp::Sync, ErrorKind::Other);
+        let mut stages = Vec::new();
+        let fs = ObserveFs::new(inner, |event: &FsEvent<[u8]>| {
+            if let FsOp::Sync = event.op {
+                stages.push(event.stage);
+            }
+        });
+        assert_eq!(fs.sync(), Err(ErrorKind::Other));
+        assert_eq!(fs.sync(), Ok(()));
+        drop(fs);
+        assert_eq!(
+            stages,
+            [
+                FsStage::Before,
+                FsStage::After(Err(ErrorKind::Other)),
+                FsStage::Before,
+                FsStage::After(Ok(()))
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_file_type_queries() {
+        let mut inner = MemFs::new();
+        inner.symlink(b"/missing", b"/link").unwrap();
+        let mut log = Vec::new();
+        let fs = ObserveFs::new(inner, |event: &FsEvent<[u8]>| {
+            if let FsStage::Before = event.stage {
+                log.push(match event.op {
+                    FsOp::FileType(path) => ("file_type", path.to_vec()),
+                    FsOp::SymlinkFileType(path) => {
+                        ("symlink_file_type", path.to_vec())
+                    }
+                    _ => ("other", Vec::new()),
+                });
+            }
+        });
+        assert_eq!(fs.symlink_file_type(b"/link"), Ok(MemFileType::Symlink));
+        assert_eq!(fs.file_type(b"/link").unwrap_err(), ErrorKind::NotFound);
+        drop(fs);
+        assert_eq!(
+            log,
+            [
+                ("symlink_file_type", b"/link".to_vec()),
+                ("file_type", b"/link".to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn forwards_mounts() {
+        let mut inner = MockFs::default();
+        inner.create_dir(b"/mnt", &DirOptions::new()).unwrap();
+        inner.create_dir(b"/mnt/usb", &DirOptions::new()).unwrap();
+        inner.symlink(b"/mnt/usb", b"/usb").unwrap();
+        inner.mounts = vec![
+            MountInfo {
+                path: b"/".to_vec(),
+                fs_type: "ext4".into(),
+                read_only: false,
+            },
+            MountInfo {
+                path: b"/mnt/usb".to_vec(),
+                fs_type: "vfat".into(),
+                read_only: true,
+            },
+        ];
+
+        let mut log = Vec::new();
+        let fs = ObserveFs::new(inner, |event: &FsEvent<[u8]>| {
+            if event.stage == FsStage::Before {
+                match event.op {
+                    FsOp::MountPoints => log.push(b"".to_vec()),
+                    FsOp::MountId(path) => log.push(path.to_vec()),
+                    _ => {}
+                }
+            }
+        });
+        let mounts = fs.mount_points().unwrap();
+        assert_eq!(mounts.len(), 2);
+        assert_eq!(mounts[1].fs_type, "vfat");
+        assert!(mounts[1].read_only);
+
+        // a walker notices the boundary, even behind a symbolic link
+        assert_eq!(fs.mount_id(b"/mnt").unwrap(), 1);
+        assert_eq!(fs.mount_id(b"/mnt/usb").unwrap(), 2);
+        assert_eq!(fs.mount_id(b"/usb").unwrap(), 2);
+        assert_eq!(fs.mount_id(b"/missing"), Err(ErrorKind::NotFound));
+        drop(fs);
+        assert_eq!(
+            log,
+            [
+                b"".to_vec(),
+                b"/mnt".to_vec(),
+                b"/mnt/usb".to_vec(),
+                b"/usb".to_vec(),
+                b"/missing".to_vec(),
+            ]
+        );
+    }
+
+    #[test]
+    fn create_dir_all_skips_existing_prefix() {
+        let mut inner = MemFs::new();
+        inner.create_dir_all(b"/a/b/c/d").unwrap();
+        let mut creates = Vec::new();
+        let mut fs = ObserveFs::new(inner, |event: &FsEvent<[u8]>| {
+            if let (FsOp::CreateDir(path), FsStage::Before) =
+                (event.op, event.stage)
+            {
+                creates.push(path.to_vec());
+            }
+        });
+        fs.create_dir_all(b"/a/b/c/d/e/f").unwrap();
+        fs.create_dir_all(b"/a/b/c").unwrap();
+        drop(fs);
+        assert_eq!(creates, [b"/a/b/c/d/e".to_vec(), b"/a/b/c/d/e/f".to_vec()]);
+    }
+}
@@ -0,0 +1,29 @@
+//! The bits of the `flags` argument of [`Fs::open_raw`].
+//!
+//! The values are those of the POSIX `O_*` constants on Linux. Exactly one of
+//! the access modes [`O_RDONLY`], [`O_WRONLY`] and [`O_RDWR`] has to be
+//! selected, the other flags can be combined freely.
+//!
+//! [`Fs::open_raw`]: ../trait.Fs.html#method.open_raw
+//! [`O_RDONLY`]: constant.O_RDONLY.html
+//! [`O_WRONLY`]: constant.O_WRONLY.html
+//! [`O_RDWR`]: constant.O_RDWR.html
+
+/// Open for reading only.
+pub const O_RDONLY: u32 = 0;
+/// Open for writing only.
+pub const O_WRONLY: u32 = 0o1;
+/// Open for reading and writing.
+pub const O_RDWR: u32 = 0o2;
+/// The mask of the bits which select the access mode.
+pub const O_ACCMODE: u32 = 0o3;
+/// Create the file if it doesn't exist.
+pub const O_CREAT: u32 = 0o100;
+/// Together with [`O_CREAT`], fail if the file already exists.
+///
+/// [`O_CREAT`]: constant.O_CREAT.html
+pub const O_EXCL: u32 = 0o200;
+/// Truncate the file to length 0 if it exists.
+pub const O_TRUNC: u32 = 0o1000;
+/// Append all writes to the end of the file.
+pub const O_APPEND: u32 = 0o2000;
@@ -0,0 +1,1089 @@
+use alloc::vec::{self, Vec};
+use core::cell::{Ref, RefCell};
+
+use {
+    copy_data, Component, Dir, DirEntry, DirOptions, Error, ErrorKind,
+    ErrorType, File, FileTimes, Fs, IoSlice, IoSliceMut, LinkKind, Metadata,
+    OpenOptions, Path, PathBuf, Read, Seek, SeekFrom, SpecialKind, Write,
+};
+
+/// The prefix of the name of a whiteout marker.
+const WHITEOUT_PREFIX: &[u8] = b".wh.";
+/// The name of the marker which makes an upper directory opaque.
+const OPAQUE_MARKER: &[u8] = b".wh..wh..opq";
+
+/// A union of two filesystems, where one is layered on top of the other.
+///
+/// Reads check the upper layer first and fall through to the lower layer. All
+/// modifications are made in the upper layer only, the lower layer is never
+/// written to. Files, directories and symbolic links which only exist in the
+/// lower layer are copied up to the upper layer before being modified, which
+/// includes opening them for writing. Copied
+/// up entries are created with the default permissions, as the [`Metadata`]
+/// trait doesn't expose them.
+///
+/// Removing an entry which exists in the lower layer creates a whiteout
+/// marker in the upper layer, an empty file named `.wh.<name>`, which masks
+/// the lower entry. Directories that are created in place of a removed lower
+/// directory are made opaque with a `.wh..wh..opq` marker, which hides the
+/// whole lower directory. Markers never appear in directory listings.
+///
+/// Directory listings merge the entries of both layers, with entries of the
+/// upper layer taking precedence over lower entries with the same name.
+/// Renaming a directory which exists in the lower layer is not supported and
+/// returns an error of kind [`ErrorKind::CrossesDevices`], just like Linux'
+/// overlayfs does. A directory renamed onto an empty directory of the lower
+/// layer is made opaque, so the lower directory can't show through.
+///
+/// [`Metadata`]: trait.Metadata.html
+/// [`ErrorKind::CrossesDevices`]: enum.ErrorKind.html#variant.CrossesDevices
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct OverlayFs<U, L> {
+    upper: RefCell<U>,
+    lower: L,
+}
+
+impl<U, L> OverlayFs<U, L>
+where
+    U: Fs,
+    L: Fs<
+        Path = U::Path,
+        PathOwned = U::PathOwned,
+        Metadata = U::Metadata,
+        Permissions = U::Permissions,
+        Error = U::Error,
+    >,
+{
+    /// Layers `upper` on top of `lower`.
+    pub fn new(upper: U, lower: L) -> Self {
+        OverlayFs {
+            upper: RefCell::new(upper),
+            lower,
+        }
+    }
+
+    /// Gets a reference to the upper layer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the upper layer is currently borrowed mutably, which can
+    /// only happen while copying up from within a call to [`open`].
+    ///
+    /// [`open`]: trait.Fs.html#method.open
+    pub fn upper(&self) -> Ref<'_, U> {
+        self.upper.borrow()
+    }
+
+    /// Gets a mutable reference to the upper layer.
+    pub fn upper_mut(&mut self) -> &mut U {
+        self.upper.get_mut()
+    }
+
+    /// Gets a reference to the lower layer.
+    pub fn lower(&self) -> &L {
+        &self.lower
+    }
+
+    /// Unwraps this `OverlayFs`, returning the upper and lower layer.
+    pub fn into_inner(self) -> (U, L) {
+        (self.upper.into_inner(), self.lower)
+    }
+
+    fn exists_in_upper(&self, path: &U::Path) -> Result<bool, U::Error> {
+        exists(self.upper.borrow().symlink_metadata(path))
+    }
+
+    fn exists_in_lower(&self, path: &U::Path) -> Result<bool, U::Error> {
+        Ok(self.lower_visible(path)?
+            && exists(self.lower.symlink_metadata(path))?)
+    }
+
+    fn exists(&self, path: &U::Path) -> Result<bool, U::Error> {
+        Ok(self.exists_in_upper(path)? || self.exists_in_lower(path)?)
+    }
+
+    /// Returns `true` if neither `path` nor any of its parents is masked by a
+    /// marker in the upper layer.
+    fn lower_visible(&self, path: &U::Path) -> Result<bool, U::Error> {
+        let mut current = Some(path);
+        let mut is_parent = false;
+        while let Some(path) = current {
+            if is_parent {
+                let opaque = child::<_, U::PathOwned>(path, OPAQUE_MARKER)?;
+                if self.exists_in_upper(opaque.as_path())? {
+                    return Ok(false);
+                }
+            }
+            if let Some(whiteout) = whiteout::<_, U::PathOwned>(path)? {
+                if self.exists_in_upper(whiteout.as_path())? {
+                    return Ok(false);
+                }
+            }
+            current = path.parent();
+            is_parent = true;
+        }
+        Ok(true)
+    }
+
+    /// Creates a whiteout marker for `path` in the upper layer.
+    fn mask(&self, path: &U::Path) -> Result<(), U::Error> {
+        if let Some(whiteout) = whiteout::<_, U::PathOwned>(path)? {
+            self.create_parents(path)?;
+            self.upper.borrow().open(
+                whiteout.as_path(),
+                OpenOptions::new().write(true).create(true),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Removes the whiteout marker for `path` from the upper layer, returning
+    /// `true` if there was one.
+    fn unmask(&self, path: &U::Path) -> Result<bool, U::Error> {
+        let whiteout = match whiteout::<_, U::PathOwned>(path)? {
+            Some(whiteout) => whiteout,
+            None => return Ok(false),
+        };
+        match self.upper.borrow_mut().remove_file(whiteout.as_path()) {
+            Ok(()) => Ok(true),
+            Err(ref err) if err.kind() == ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Makes sure all parents of `path` exist in the upper layer.
+    fn create_parents(&self, path: &U::Path) -> Result<(), U::Error> {
+        match path.parent() {
+            Some(parent) if !parent.as_bytes().is_empty() => {
+                self.copy_up(parent)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Copies `path` from the lower to the upper layer, unless it already
+    /// exists there.
+    fn copy_up(&self, path: &U::Path) -> Result<(), U::Error> {
+        if self.exists_in_upper(path)? {
+            return Ok(());
+        }
+        let metadata = match self.lower.symlink_metadata(path) {
+            Ok(ref metadata) if self.lower_visible(path)? => metadata.is_dir(),
+            Ok(_) => return Err(ErrorKind::NotFound.into()),
+            Err(err) => return Err(err),
+        };
+        self.create_parents(path)?;
+
+        if metadata {
+            return self
+                .upper
+                .borrow_mut()
+                .create_dir(path, &DirOptions::new());
+        }
+        if self.lower.symlink_metadata(path)?.is_symlink() {
+            let target = self.lower.read_link(path)?;
+            return self.upper.borrow_mut().symlink(target.as_path(), path);
+        }
+        let src = self.lower.open(path, OpenOptions::new().read(true))?;
+        let mut dst = self
+            .upper
+            .borrow()
+            .open(path, OpenOptions::new().write(true).create_new(true))?;
+        copy_data(&src, &mut dst)?;
+        dst.flush()
+    }
+
+    /// Prepares the upper layer for a new entry at `path`.
+    ///
+    /// Returns `true` if `path` used to be masked.
+    fn prepare_new(&self, path: &U::Path) -> Result<bool, U::Error> {
+        self.create_parents(path)?;
+        self.unmask(path)
+    }
+
+    /// Prepares the upper layer for a new symbolic link at `path`, which must
+    /// not exist in either layer.
+    fn prepare_symlink(&self, path: &U::Path) -> Result<(), U::Error> {
+        if self.exists(path)? {
+            return Err(ErrorKind::AlreadyExists.into());
+        }
+        self.prepare_new(path).map(|_| ())
+    }
+}
+
+impl<U, L> Fs for OverlayFs<U, L>
+where
+    U: Fs,
+    L: Fs<
+        Path = U::Path,
+        PathOwned = U::PathOwned,
+        Metadata = U::Metadata,
+        Permissions = U::Permissions,
+        Error = U::Error,
+    >,
+    L::DirEntry: DirEntry<
+        PathOwned = <U::DirEntry as DirEntry>::PathOwned,
+        FileType = <U::DirEntry as DirEntry>::FileType,
+    >,
+{
+    type Path = U::Path;
+    type PathOwned = U::PathOwned;
+    type File = OverlayFile<U::File, L::File>;
+    type Dir = OverlayDir<U::DirEntry, L::DirEntry>;
+    type DirEntry = OverlayDirEntry<U::DirEntry, L::DirEntry>;
+    type Metadata = U::Metadata;
+    type Permissions = U::Permissions;
+    type Error = U::Error;
+
+    fn open(
+        &self,
+        path: &Self::Path,
+        options: &OpenOptions<Self::Permissions>,
+    ) -> Result<Self::File, Self::Error> {
+        let write = options.write
+            || options.append
+            || options.create
+            || options.create_new
+            || options.truncate;
+        if write && !self.exists_in_upper(path)? {
+            if self.exists_in_lower(path)? {
+                if options.create_new {
+                    return Err(ErrorKind::AlreadyExists.into());
+                }
+                self.copy_up(path)?;
+            } else if options.create || options.create_new {
+                let masked = self.prepare_new(path)?;
+                let result = self.upper.borrow().open(path, options);
+                if result.is_err() && masked {
+                    self.mask(path)?;
+                }
+                return result.map(OverlayFile::Upper);
+            }
+        }
+        if write || self.exists_in_upper(path)? {
+            return self
+                .upper
+                .borrow()
+                .open(path, options)
+                .map(OverlayFile::Upper);
+        }
+        if !self.lower_visible(path)? {
+            return Err(ErrorKind::NotFound.into());
+        }
+        self.lower.open(path, options).map(OverlayFile::Lower)
+    }
+
+    fn open_anonymous(
+        &mut self,
+        dir: &Self::Path,
+        options: &OpenOptions<Self::Permissions>,
+    ) -> Result<Self::File, Self::Error> {
+        self.copy_up(dir)?;
+        self.upper
+            .borrow_mut()
+            .open_anonymous(dir, options)
+            .map(OverlayFile::Upper)
+    }
+
+    fn remove_file(&mut self, path: &Self::Path) -> Result<(), Self::Error> {
+        let in_upper = self.exists_in_upper(path)?;
+        let in_lower = self.exists_in_lower(path)?;
+        if !in_upper && !in_lower {
+            return Err(ErrorKind::NotFound.into());
+        }
+        if self.symlink_metadata(path)?.is_dir() {
+            return Err(ErrorKind::IsADirectory.into());
+        }
+        if in_upper {
+            self.upper.borrow_mut().remove_file(path)?;
+        }
+        if in_lower {
+            self.mask(path)?;
+        }
+        Ok(())
+    }
+
+    fn metadata(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::Metadata, Self::Error> {
+        match self.upper.borrow().metadata(path) {
+            Err(ref err)
+                if err.kind() == ErrorKind::NotFound
+                    && self.lower_visible(path)? =>
+            {
+                self.lower.metadata(path)
+            }
+            result => result,
+        }
+    }
+
+    fn symlink_metadata(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::Metadata, Self::Error> {
+        match self.upper.borrow().symlink_metadata(path) {
+            Err(ref err)
+                if err.kind() == ErrorKind::NotFound
+                    && self.lower_visible(path)? =>
+            {
+                self.lower.symlink_metadata(path)
+            }
+            result => result,
+        }
+    }
+
+    fn rename(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        let in_lower = self.exists_in_lower(from)?;
+        let is_dir = self.symlink_metadata(from)?.is_dir();
+        if in_lower && is_dir {
+            return Err(ErrorKind::CrossesDevices.into());
+        }
+        let replaces_lower = self.exists_in_lower(to)?;
+        if replaces_lower {
+            match (is_dir, self.lower.symlink_metadata(to)?.is_dir()) {
+                (false, true) => return Err(ErrorKind::IsADirectory.into()),
+                (true, false) => return Err(ErrorKind::NotADirectory.into()),
+                (true, true) => {
+                    // the upper part of an empty merged directory can only
+                    // hold markers
+                    if self.read_dir(to)?.next().is_some() {
+                        return Err(ErrorKind::DirectoryNotEmpty.into());
+                    }
+                    if self.exists_in_upper(to)? {
+                        self.upper.borrow_mut().remove_dir_all(to)?;
+                    }
+                }
+                (false, false) => {}
+            }
+        }
+        self.copy_up(from)?;
+        let masked = self.prepare_new(to)?;
+        let result = self.upper.borrow_mut().rename(from, to);
+        if let Err(err) = result {
+            if masked {
+                self.mask(to)?;
+            }
+            return Err(err);
+        }
+        if is_dir && (masked || replaces_lower) {
+            let opaque = child::<_, U::PathOwned>(to, OPAQUE_MARKER)?;
+            self.upper.borrow().open(
+                opaque.as_path(),
+                OpenOptions::new().write(true).create(true),
+            )?;
+        }
+        if in_lower {
+            self.mask(from)?;
+        }
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<u64, Self::Error> {
+        if !self.metadata(from)?.is_file() {
+            return Err(ErrorKind::InvalidInput.into());
+        }
+        self.prepare_new(to)?;
+        if self.exists_in_upper(from)? {
+            return self.upper.borrow_mut().copy(from, to);
+        }
+
+        let src = self.lower.open(from, OpenOptions::new().read(true))?;
+        let mut dst = self.upper.borrow().open(
+            to,
+            OpenOptions::new().write(true).create(true).truncate(true),
+        )?;
+        let copied = copy_data(&src, &mut dst)?;
+        dst.flush()?;
+        Ok(copied)
+    }
+
+    fn hard_link(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        if self.exists(dst)? {
+            return Err(ErrorKind::AlreadyExists.into());
+        }
+        self.copy_up(src)?;
+        self.prepare_new(dst)?;
+        self.upper.borrow_mut().hard_link(src, dst)
+    }
+
+    fn hard_link_checked(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<LinkKind, Self::Error> {
+        if self.exists(dst)? {
+            return Err(ErrorKind::AlreadyExists.into());
+        }
+        self.copy_up(src)?;
+        self.prepare_new(dst)?;
+        self.upper.borrow_mut().hard_link_checked(src, dst)
+    }
+
+    fn symlink(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.prepare_symlink(dst)?;
+        self.upper.borrow_mut().symlink(src, dst)
+    }
+
+    fn symlink_file(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.prepare_symlink(dst)?;
+        self.upper.borrow_mut().symlink_file(src, dst)
+    }
+
+    fn symlink_dir(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.prepare_symlink(dst)?;
+        self.upper.borrow_mut().symlink_dir(src, dst)
+    }
+
+    fn mknod(
+        &mut self,
+        path: &Self::Path,
+        mode: Self::Permissions,
+        kind: SpecialKind,
+        dev: u64,
+    ) -> Result<(), Self::Error> {
+        self.prepare_symlink(path)?;
+        self.upper.borrow_mut().mknod(path, mode, kind, dev)
+    }
+
+    fn read_link(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::PathOwned, Self::Error> {
+        if self.exists_in_upper(path)? {
+            self.upper.borrow().read_link(path)
+        } else if self.lower_visible(path)? {
+            self.lower.read_link(path)
+        } else {
+            Err(ErrorKind::NotFound.into())
+        }
+    }
+
+    fn canonicalize(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::PathOwned, Self::Error> {
+        if self.exists_in_upper(path)? {
+            self.upper.borrow().canonicalize(path)
+        } else if self.lower_visible(path)? {
+            self.lower.canonicalize(path)
+        } else {
+            Err(ErrorKind::NotFound.into())
+        }
+    }
+
+    fn create_dir(
+        &mut self,
+        path: &Self::Path,
+        options: &DirOptions<Self::Permissions>,
+    ) -> Result<(), Self::Error> {
+        if self.exists(path)? {
+            if options.recursive && self.metadata(path)?.is_dir() {
+                return Ok(());
+            }
+            return Err(ErrorKind::AlreadyExists.into());
+        }
+        if options.recursive {
+            if let Some(parent) = path.parent() {
+                if !parent.as_bytes().is_empty() {
+                    self.create_dir(parent, options)?;
+                }
+            }
+        }
+
+        let masked = self.prepare_new(path)?;
+        self.upper.borrow_mut().create_dir(path, options)?;
+        if masked {
+            let opaque = child::<_, U::PathOwned>(path, OPAQUE_MARKER)?;
+            self.upper.borrow().open(
+                opaque.as_path(),
+                OpenOptions::new().write(true).create(true),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn remove_dir(&mut self, path: &Self::Path) -> Result<(), Self::Error> {
+        if !self.symlink_metadata(path)?.is_dir() {
+            return Err(ErrorKind::NotADirectory.into());
+        }
+        if self.read_dir(path)?.next().is_some() {
+            return Err(ErrorKind::DirectoryNotEmpty.into());
+        }
+        self.remove_dir_all(path)
+    }
+
+    fn remove_dir_all(&mut self, path: &Self::Path) -> Result<(), Self::Error> {
+        let in_upper = self.exists_in_upper(path)?;
+        let in_lower = self.exists_in_lower(path)?;
+        if !in_upper && !in_lower {
+            return Err(ErrorKind::NotFound.into());
+        }
+        if self.symlink_metadata(path)?.is_file() {
+            return Err(ErrorKind::NotADirectory.into());
+        }
+        if in_upper {
+            self.upper.borrow_mut().remove_dir_all(path)?;
+        }
+        if in_lower {
+            self.mask(path)?;
+        }
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Self::Path) -> Result<Self::Dir, Self::Error> {
+        let mut entries = Vec::new();
+        let mut names = Vec::new();
+        let mut whiteouts = Vec::new();
+        let mut opaque = false;
+
+        let in_upper = match self.upper.borrow().read_dir(path) {
+            Ok(dir) => {
+                for entry in dir {
+                    let entry = entry?;
+                    let name = entry.file_name().as_bytes();
+                    if name == OPAQUE_MARKER {
+                        opaque = true;
+                    } else if name.starts_with(WHITEOUT_PREFIX) {
+                        whiteouts.push(name[WHITEOUT_PREFIX.len()..].to_vec());
+                    } else {
+                        names.push(name.to_vec());
+                        entries.push(OverlayDirEntry::Upper(entry));
+                    }
+                }
+                true
+            }
+            Err(ref err) if err.kind() == ErrorKind::NotFound => false,
+            Err(err) => return Err(err),
+        };
+
+        if !opaque && self.lower_visible(path)? {
+            match self.lower.read_dir(path) {
+                Ok(dir) => {
+                    for entry in dir {
+                        let entry = entry?;
+                        let name = entry.file_name().as_bytes();
+                        if !names.iter().any(|n| n == name)
+                            && !whiteouts.iter().any(|n| n == name)
+                        {
+                            entries.push(OverlayDirEntry::Lower(entry));
+                        }
+                    }
+                }
+                Err(ref err)
+                    if err.kind() == ErrorKind::NotFound && in_upper => {}
+                Err(err) => return Err(err),
+            }
+        } else if !in_upper {
+            return Err(ErrorKind::NotFound.into());
+        }
+
+        Ok(OverlayDir {
+            entries: entries.into_iter(),
+        })
+    }
+
+    fn set_permissions(
+        &mut self,
+        path: &Self::Path,
+        perm: Self::Permissions,
+    ) -> Result<(), Self::Error> {
+        self.copy_up(path)?;
+        self.upper.borrow_mut().set_permissions(path, perm)
+    }
+
+    fn set_permissions_nofollow(
+        &mut self,
+        path: &Self::Path,
+        perm: Self::Permissions,
+    ) -> Result<(), Self::Error> {
+        self.copy_up(path)?;
+        self.upper.borrow_mut().set_permissions_nofollow(path, perm)
+    }
+
+    fn set_times(
+        &mut self,
+        path: &Self::Path,
+        times: FileTimes,
+    ) -> Result<(), Self::Error> {
+        self.copy_up(path)?;
+        self.upper.borrow_mut().set_times(path, times)
+    }
+
+    fn available_space(&self, path: &Self::Path) -> Result<u64, Self::Error> {
+        let mut current = Some(path);
+        while let Some(path) = current {
+            match self.upper.borrow().available_space(path) {
+                Err(ref err) if err.kind() == ErrorKind::NotFound => {
+                    current = path.parent();
+                }
+                result => return result,
+            }
+        }
+        Err(ErrorKind::NotFound.into())
+    }
+
+    fn mount_id(&self, path: &Self::Path) -> Result<u64, Self::Error> {
+        // like the device numbers reported by Linux' overlayfs, the id is the
+        // one of the layer the entry currently lives in
+        match self.upper.borrow().mount_id(path) {
+            Err(ref err)
+                if err.kind() == ErrorKind::NotFound
+                    && self.lower_visible(path)? =>
+            {
+                self.lower.mount_id(path)
+            }
+            result => result,
+        }
+    }
+
+    fn sync(&self) -> Result<(), Self::Error> {
+        // the lower layer is never modified, so it has nothing to write out
+        self.upper.borrow().sync()
+    }
+
+    fn flush_all(&mut self) -> Result<(), Self::Error> {
+        self.upper.get_mut().flush_all()
+    }
+}
+
+fn exists<T, E: Error>(result: Result<T, E>) -> Result<bool, E> {
+    match result {
+        Ok(_) => Ok(true),
+        Err(ref err) if err.kind() == ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns the path of the entry called `name` in the directory `dir`.
+fn child<P, O>(dir: &P, name: &[u8]) -> Result<O, ErrorKind>
+where
+    P: ?Sized + Path,
+    O: PathBuf<P>,
+{
+    let mut path = O::from_path(dir)?;
+    path.push(P::from_bytes(name))?;
+    Ok(path)
+}
+
+/// Returns the path of the whiteout marker which masks `path`, if `path` has
+/// a final component.
+fn whiteout<P, O>(path: &P) -> Result<Option<O>, ErrorKind>
+where
+    P: ?Sized + Path,
+    O: PathBuf<P>,
+{
+    let name = match path.components().last() {
+        Some(Component::Normal(name)) => name,
+        _ => return Ok(None),
+    };
+    let mut marker = Vec::with_capacity(WHITEOUT_PREFIX.len() + name.len());
+    marker.extend_from_slice(WHITEOUT_PREFIX);
+    marker.extend_from_slice(name);
+    let parent = path.parent().unwrap_or_else(|| P::from_bytes(&[]));
+    child(parent, &marker).map(Some)
+}
+
+/// A file opened on an [`OverlayFs`], which lives in either of its layers.
+///
+/// [`OverlayFs`]: struct.OverlayFs.html
+#[derive(Debug)]
+pub enum OverlayFile<U, L> {
+    /// A file in the upper layer.
+    Upper(U),
+    /// A file in the lower layer, which is only ever opened for reading.
+    Lower(L),
+}
+
+impl<U: ErrorType, L: ErrorType<Error = U::Error>> ErrorType
+    for OverlayFile<U, L>
+{
+    type Error = U::Error;
+}
+
+impl<U: Read, L: Read<Error = U::Error>> Read for OverlayFile<U, L> {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        match *self {
+            OverlayFile::Upper(ref file) => file.read(buf),
+            OverlayFile::Lower(ref file) => file.read(buf),
+        }
+    }
+}
+
+impl<U: Write, L: Write<Error = U::Error>> Write for OverlayFile<U, L> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        match *self {
+            OverlayFile::Upper(ref mut file) => file.write(buf),
+            OverlayFile::Lower(ref mut file) => file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        match *self {
+            OverlayFile::Upper(ref mut file) => file.flush(),
+            OverlayFile::Lower(ref mut file) => file.flush(),
+        }
+    }
+
+    fn is_flush_noop(&self) -> bool {
+        match *self {
+            OverlayFile::Upper(ref file) => file.is_flush_noop(),
+            OverlayFile::Lower(ref file) => file.is_flush_noop(),
+        }
+    }
+
+    fn reserve(&mut self, additional: usize) -> Result<(), Self::Error> {
+        match *self {
+            OverlayFile::Upper(ref mut file) => file.reserve(additional),
+            OverlayFile::Lower(ref mut file) => file.reserve(additional),
+        }
+    }
+
+    fn write_zeros(&mut self, count: u64) -> Result<(), Self::Error> {
+        match *self {
+            OverlayFile::Upper(ref mut file) => file.write_zeros(count),
+            OverlayFile::Lower(ref mut file) => file.write_zeros(count),
+        }
+    }
+}
+
+impl<U: Seek, L: Seek<Error = U::Error>> Seek for OverlayFile<U, L> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        match *self {
+            OverlayFile::Upper(ref mut file) => file.seek(pos),
+            OverlayFile::Lower(ref mut file) => file.seek(pos),
+        }
+    }
+}
+
+impl<U: File, L: File<Error = U::Error>> File for OverlayFile<U, L> {
+    fn set_len(&mut self, size: u64) -> Result<(), Self::Error> {
+        match *self {
+            OverlayFile::Upper(ref mut file) => file.set_len(size),
+            OverlayFile::Lower(ref mut file) => file.set_len(size),
+        }
+    }
+
+    fn duplicate_to(&self, target: &mut Self) -> Result<(), Self::Error> {
+        match (self, target) {
+            (OverlayFile::Upper(file), OverlayFile::Upper(target)) => {
+                file.duplicate_to(target)
+            }
+            (OverlayFile::Lower(file), OverlayFile::Lower(target)) => {
+                file.duplicate_to(target)
+            }
+            _ => Err(ErrorKind::CrossesDevices.into()),
+        }
+    }
+
+    fn read_at(
+        &self,
+        buf: &mut [u8],
+        offset: u64,
+    ) -> Result<usize, Self::Error> {
+        match *self {
+            OverlayFile::Upper(ref file) => file.read_at(buf, offset),
+            OverlayFile::Lower(ref file) => file.read_at(buf, offset),
+        }
+    }
+
+    fn write_at(
+        &mut self,
+        buf: &[u8],
+        offset: u64,
+    ) -> Result<usize, Self::Error> {
+        match *self {
+            OverlayFile::Upper(ref mut file) => file.write_at(buf, offset),
+            OverlayFile::Lower(ref mut file) => file.write_at(buf, offset),
+        }
+    }
+
+    fn read_vectored_at(
+        &self,
+        bufs: &mut [IoSliceMut],
+        offset: u64,
+    ) -> Result<usize, Self::Error> {
+        match *self {
+            OverlayFile::Upper(ref file) => file.read_vectored_at(bufs, offset),
+            OverlayFile::Lower(ref file) => file.read_vectored_at(bufs, offset),
+        }
+    }
+
+    fn write_vectored_at(
+        &mut self,
+        bufs: &[IoSlice],
+        offset: u64,
+    ) -> Result<usize, Self::Error> {
+        match *self {
+            OverlayFile::Upper(ref mut file) => {
+                file.write_vectored_at(bufs, offset)
+            }
+            OverlayFile::Lower(ref mut file) => {
+                file.write_vectored_at(bufs, offset)
+            }
+        }
+    }
+}
+
+/// Iterator over the merged entries in a directory of an [`OverlayFs`].
+///
+/// The listings of both layers are read when the directory is opened.
+///
+/// [`OverlayFs`]: struct.OverlayFs.html
+#[derive(Debug)]
+pub struct OverlayDir<U, L> {
+    entries: vec::IntoIter<OverlayDirEntry<U, L>>,
+}
+
+impl<U: DirEntry, L: DirEntry> Iterator for OverlayDir<U, L> {
+    type Item = Result<OverlayDirEntry<U, L>, U::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(Ok)
+    }
+}
+
+impl<U, L> Dir<OverlayDirEntry<U, L>, U::Error> for OverlayDir<U, L>
+where
+    U: DirEntry,
+    L: DirEntry<
+        Path = U::Path,
+        PathOwned = U::PathOwned,
+        Metadata = U::Metadata,
+        FileType = U::FileType,
+        Error = U::Error,
+    >,
+{
+}
+
+/// An entry in a directory of an [`OverlayFs`], which lives in either of its
+/// layers.
+///
+/// [`OverlayFs`]: struct.OverlayFs.html
+#[derive(Debug, Clone)]
+pub enum OverlayDirEntry<U, L> {
+    /// An entry of the upper layer.
+    Upper(U),
+    /// An entry of the lower layer, which isn't masked by the upper layer.
+    Lower(L),
+}
+
+impl<U, L> DirEntry for OverlayDirEntry<U, L>
+where
+    U: DirEntry,
+    L: DirEntry<
+        Path = U::Path,
+        PathOwned = U::PathOwned,
+        Metadata = U::Metadata,
+        FileType = U::FileType,
+        Error = U::Error,
+    >,
+{
+    type Path = U::Path;
+    type PathOwned = U::PathOwned;
+    type Metadata = U::Metadata;
+    type FileType = U::FileType;
+    type Error = U::Error;
+
+    fn path(&self) -> Self::PathOwned {
+        match *self {
+            OverlayDirEntry::Upper(ref entry) => entry.path(),
+            OverlayDirEntry::Lower(ref entry) => entry.path(),
+        }
+    }
+
+    fn metadata(&self) -> Result<Self::Metadata, Self::Error> {
+        match *self {
+            OverlayDirEntry::Upper(ref entry) => entry.metadata(),
+            OverlayDirEntry::Lower(ref entry) => entry.metadata(),
+        }
+    }
+
+    fn file_type(&self) -> Result<Self::FileType, Self::Error> {
+        match *self {
+            OverlayDirEntry::Upper(ref entry) => entry.file_type(),
+            OverlayDirEntry::Lower(ref entry) => entry.file_type(),
+        }
+    }
+
+    fn file_name(&self) -> &Self::Path {
+        match *self {
+            OverlayDirEntry::Upper(ref entry) => entry.file_name(),
+            OverlayDirEntry::Lower(ref entry) => entry.file_name(),
+        }
+    }
+
+    fn sort_key(&self) -> Result<(bool, &[u8]), Self::Error> {
+        match *self {
+            OverlayDirEntry::Upper(ref entry) => entry.sort_key(),
+            OverlayDirEntry::Lower(ref entry) => entry.sort_key(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use mem::{MemFs, MemOp};
+    use mock::{read_file, write_file, MockFs};
+    use MountInfo;
+
+    fn overlay() -> OverlayFs<MemFs, MemFs> {
+        let mut lower = MemFs::new();
+        lower.create_dir(b"/dir", &DirOptions::new()).unwrap();
+        write_file(&lower, b"/dir/lower", b"lower").unwrap();
+        write_file(&lower, b"/file", b"lower").unwrap();
+        OverlayFs::new(MemFs::new(), lower)
+    }
+
+    fn names(fs: &OverlayFs<MemFs, MemFs>, path: &[u8]) -> Vec<Vec<u8>> {
+        let mut names: Vec<_> = fs
+            .read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_vec())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn reads_fall_through() {
+        let fs = overlay();
+        write_file(&*fs.upper(), b"/upper", b"upper").unwrap();
+        assert_eq!(read_file(&fs, b"/file").unwrap(), b"lower");
+        assert_eq!(read_file(&fs, b"/dir/lower").unwrap(), b"lower");
+        assert_eq!(read_file(&fs, b"/upper").unwrap(), b"upper");
+        assert_eq!(names(&fs, b"/"), [&b"dir"[..], b"file", b"upper"]);
+    }
+
+    #[test]
+    fn writes_copy_up() {
+        let fs = overlay();
+        let file = fs.open(b"/dir/lower", OpenOptions::new().append(true));
+        file.unwrap().write_all(b"+upper").unwrap();
+        assert_eq!(read_file(&fs, b"/dir/lower").unwrap(), b"lower+upper");
+        assert_eq!(
+            read_file(&*fs.upper(), b"/dir/lower").unwrap(),
+            b"lower+upper"
+        );
+        assert_eq!(read_file(fs.lower(), b"/dir/lower").unwrap(), b"lower");
+    }
+
+    #[test]
+    fn whiteouts_mask_lower_entries() {
+        let mut fs = overlay();
+        fs.remove_file(b"/file").unwrap();
+        fs.remove_dir_all(b"/dir").unwrap();
+        assert_eq!(read_file(&fs, b"/file"), Err(ErrorKind::NotFound));
+        assert_eq!(read_file(&fs, b"/dir/lower"), Err(ErrorKind::NotFound));
+        assert!(names(&fs, b"/").is_empty());
+        assert!(fs.upper().is_file(b"/.wh.file"));
+        assert!(fs.lower().is_file(b"/file"));
+
+        // a new directory in place of a removed one is opaque
+        fs.create_dir(b"/dir", &DirOptions::new()).unwrap();
+        assert!(names(&fs, b"/dir").is_empty());
+        write_file(&fs, b"/file", b"new").unwrap();
+        assert_eq!(read_file(&fs, b"/file").unwrap(), b"new");
+    }
+
+    #[test]
+    fn rename_onto_lower_dir() {
+        let mut fs = overlay();
+        fs.create_dir(b"/new", &DirOptions::new()).unwrap();
+        write_file(&fs, b"/new/upper", b"upper").unwrap();
+        assert_eq!(
+            fs.rename(b"/new", b"/dir"),
+            Err(ErrorKind::DirectoryNotEmpty)
+        );
+
+        fs.remove_file(b"/dir/lower").unwrap();
+        fs.rename(b"/new", b"/dir").unwrap();
+        assert_eq!(names(&fs, b"/dir"), [b"upper"]);
+        assert_eq!(read_file(&fs, b"/dir/lower"), Err(ErrorKind::NotFound));
+        assert!(!fs.exists(b"/new").unwrap());
+    }
+
+    #[test]
+    fn mount_id_follows_layer() {
+        fn mount(path: &[u8]) -> MountInfo<Vec<u8>> {
+            MountInfo {
+                path: path.to_vec(),
+                fs_type: "tmpfs".into(),
+                read_only: false,
+            }
+        }
+
+        let mut lower = MockFs {
+            mounts: vec![mount(b"/other"), mount(b"/")],
+            ..MockFs::default()
+        };
+        lower.create_dir(b"/dir", &DirOptions::new()).unwrap();
+        write_file(&lower, b"/dir/file", b"lower").unwrap();
+        let upper = MockFs {
+            mounts: vec![mount(b"/")],
+            ..MockFs::default()
+        };
+        let mut fs = OverlayFs::new(upper, lower);
+
+        assert_eq!(fs.mount_id(b"/dir/file").unwrap(), 2);
+        fs.open(b"/dir/file", OpenOptions::new().write(true))
+            .unwrap();
+        assert_eq!(fs.mount_id(b"/dir/file").unwrap(), 1);
+        assert_eq!(fs.mount_id(b"/"), Ok(1));
+
+        fs.remove_file(b"/dir/file").unwrap();
+        assert_eq!(fs.mount_id(b"/dir/file"), Err(ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn flush_all_reaches_upper() {
+        let mut fs = overlay();
+        fs.upper
+            .borrow_mut()
+            .fail_next(MemOp::FlushAll, ErrorKind::WriteZero);
+        assert_eq!(fs.flush_all(), Err(ErrorKind::WriteZero));
+        assert_eq!(fs.flush_all(), Ok(()));
+    }
+
+    #[test]
+    fn set_permissions_nofollow_copies_up_link() {
+        let mut fs = overlay();
+        fs.lower.symlink(b"/file", b"/link").unwrap();
+        fs.set_permissions_nofollow(b"/link", 0o700).unwrap();
+
+        let upper = fs.upper.borrow();
+        assert_eq!(upper.read_link(b"/link").unwrap(), b"/file");
+        assert_eq!(upper.symlink_metadata(b"/link").unwrap().mode(), 0o700);
+        assert_eq!(upper.metadata(b"/file").unwrap_err(), ErrorKind::NotFound);
+        drop(upper);
+        assert_eq!(fs.symlink_metadata(b"/link").unwrap().mode(), 0o700);
+        assert_ne!(fs.metadata(b"/link").unwrap().mode(), 0o700);
+    }
+}
@@ -0,0 +1,467 @@
+use core::borrow::Borrow;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use ErrorKind;
+
+/// A slice of a path on a filesystem.
+///
+/// Paths are treated as sequences of bytes separated by [`separator`], which
+/// is enough for the generic helpers in this crate to inspect and take apart
+/// paths without knowing the concrete type a filesystem uses.
+///
+/// [`separator`]: #method.separator
+pub trait Path {
+    /// Returns the raw bytes of this path.
+    fn as_bytes(&self) -> &[u8];
+
+    /// Converts a slice of bytes to a path slice.
+    ///
+    /// The bytes passed to this function are always either obtained from
+    /// [`as_bytes`] or are a sub-slice of it split at a separator.
+    ///
+    /// [`as_bytes`]: #method.as_bytes
+    fn from_bytes(bytes: &[u8]) -> &Self;
+
+    /// Returns the byte separating the components of a path.
+    ///
+    /// The default implementation returns `/`, as used on unix. Filesystems
+    /// with Windows-style paths would return `\` instead, though note that
+    /// Windows itself accepts both.
+    fn separator() -> u8 {
+        b'/'
+    }
+
+    /// Returns the root path, which all absolute paths start with.
+    ///
+    /// The default implementation returns the [`separator`] on its own, i.e.
+    /// `/` unless the separator is overridden. On Windows, this corresponds to
+    /// the root of the current drive, `\`, rather than a prefixed root like
+    /// `C:\`, which filesystems supporting drives need to handle themselves.
+    ///
+    /// [`separator`]: #method.separator
+    fn root<'a>() -> &'a Self {
+        let sep = Self::separator() as usize;
+        Self::from_bytes(&BYTE_VALUES[sep..=sep])
+    }
+
+    /// Returns `true` if the path is absolute, i.e. if it starts with the
+    /// [`root`].
+    ///
+    /// [`root`]: #method.root
+    fn is_absolute(&self) -> bool {
+        self.as_bytes().starts_with(Self::root().as_bytes())
+    }
+
+    /// Returns the path without its final component, if there is one.
+    ///
+    /// Returns `None` if the path terminates in a root or if it's the empty
+    /// path.
+    fn parent(&self) -> Option<&Self> {
+        let sep = Self::separator();
+        let bytes = trim_trailing_separators(self.as_bytes(), sep);
+        if bytes.is_empty() || bytes == Self::root().as_bytes() {
+            return None;
+        }
+
+        match bytes.iter().rposition(|&b| b == sep) {
+            Some(i) => Some(Self::from_bytes(trim_trailing_separators(
+                &bytes[..=i],
+                sep,
+            ))),
+            None => Some(Self::from_bytes(&[])),
+        }
+    }
+
+    /// Returns an iterator over the components of the path.
+    ///
+    /// Like `std::path::Path::components`, repeated separators and `.`
+    /// components are skipped, except for a `.` at the very beginning of a
+    /// relative path. `..` components are yielded as they are, since
+    /// resolving them would require following symbolic links.
+    fn components(&self) -> Components<'_> {
+        Components::new(
+            self.as_bytes(),
+            Self::separator(),
+            Self::root().as_bytes(),
+        )
+    }
+
+    /// Returns `true` if `base` is a prefix of the path.
+    ///
+    /// Only whole components are matched, so `/a/b` starts with `/a` but not
+    /// with `/ab`. On `[u8]` paths, the byte-wise inherent method of the slice
+    /// takes precedence with method call syntax, so call this one as
+    /// `Path::starts_with(path, base)` instead. The same applies to
+    /// [`strip_prefix`].
+    ///
+    /// [`strip_prefix`]: #method.strip_prefix
+    fn starts_with(&self, base: &Self) -> bool {
+        self.strip_prefix(base).is_some()
+    }
+
+    /// Returns the path with the prefix `base` removed, or `None` if `base`
+    /// is not a prefix of the path.
+    ///
+    /// Like [`starts_with`], this only matches whole components. The returned
+    /// path is relative, so stripping `/a` from `/a/b` results in `b`.
+    ///
+    /// [`starts_with`]: #method.starts_with
+    fn strip_prefix(&self, base: &Self) -> Option<&Self> {
+        let mut components = self.components();
+        for component in base.components() {
+            if components.next() != Some(component) {
+                return None;
+            }
+        }
+        if components.at_start {
+            return Some(self);
+        }
+        Some(Self::from_bytes(components.remaining()))
+    }
+}
+
+impl Path for [u8] {
+    fn as_bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn from_bytes(bytes: &[u8]) -> &Self {
+        bytes
+    }
+}
+
+/// An owned, mutable path.
+///
+/// Owned paths may be backed by a fixed-capacity buffer, which is why all
+/// operations that grow the path can fail.
+pub trait PathBuf<P: ?Sized + Path>: Borrow<P> + Sized {
+    /// Creates an owned path from a path slice.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if the path doesn't fit in the
+    /// underlying buffer.
+    fn from_path(path: &P) -> Result<Self, ErrorKind>;
+
+    /// Extends `self` with `path`.
+    ///
+    /// If `path` is absolute, it replaces the current path.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if the resulting path doesn't fit in
+    /// the underlying buffer.
+    fn push(&mut self, path: &P) -> Result<(), ErrorKind>;
+
+    /// Truncates `self` to its [`parent`].
+    ///
+    /// Returns `false`, leaving `self` unchanged, if there is no parent. The
+    /// default implementation copies the parent into a new path, owned paths
+    /// with a growable buffer should override it to truncate in place.
+    ///
+    /// [`parent`]: trait.Path.html#method.parent
+    fn pop(&mut self) -> bool {
+        let parent = match self.as_path().parent() {
+            Some(parent) => Self::from_path(parent),
+            None => return false,
+        };
+        // the parent is never longer than the path it was taken from
+        match parent {
+            Ok(parent) => {
+                *self = parent;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Borrows this path as a path slice.
+    fn as_path(&self) -> &P {
+        self.borrow()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PathBuf<[u8]> for Vec<u8> {
+    fn from_path(path: &[u8]) -> Result<Self, ErrorKind> {
+        Ok(path.to_vec())
+    }
+
+    fn push(&mut self, path: &[u8]) -> Result<(), ErrorKind> {
+        let sep = <[u8]>::separator();
+        if path.is_absolute() {
+            self.clear();
+        } else if !self.is_empty() && self.last() != Some(&sep) {
+            self.push(sep);
+        }
+        self.extend_from_slice(path);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> bool {
+        match self.parent() {
+            Some(parent) => {
+                let len = parent.len();
+                self.truncate(len);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A single component of a path.
+///
+/// It is yielded by the iterator returned from [`Path::components`].
+///
+/// [`Path::components`]: trait.Path.html#method.components
+#[derive(Copy, PartialEq, Eq, Clone, Debug, Hash)]
+pub enum Component<'a> {
+    /// The root directory at the start of an absolute path.
+    RootDir,
+    /// A `.` at the start of a relative path.
+    CurDir,
+    /// A `..` component, referring to the parent directory.
+    ParentDir,
+    /// Any other component, like a file or directory name.
+    Normal(&'a [u8]),
+}
+
+impl<'a> Component<'a> {
+    /// Returns the raw bytes of this component, as they would appear in a
+    /// path of type `P`.
+    ///
+    /// The root directory is spelled as [`Path::root`] of `P`, so it depends on
+    /// the path type the component came from.
+    ///
+    /// [`Path::root`]: trait.Path.html#method.root
+    pub fn as_bytes<P: ?Sized + Path + 'a>(self) -> &'a [u8] {
+        match self {
+            Component::RootDir => P::root().as_bytes(),
+            Component::CurDir => b".",
+            Component::ParentDir => b"..",
+            Component::Normal(name) => name,
+        }
+    }
+}
+
+/// An iterator over the components of a path.
+///
+/// It is returned by [`Path::components`].
+///
+/// [`Path::components`]: trait.Path.html#method.components
+#[derive(Clone, Debug)]
+pub struct Components<'a> {
+    rest: &'a [u8],
+    sep: u8,
+    has_root: bool,
+    at_start: bool,
+}
+
+impl<'a> Components<'a> {
+    fn new(path: &'a [u8], sep: u8, root: &[u8]) -> Self {
+        let has_root = !root.is_empty() && path.starts_with(root);
+        Components {
+            rest: if has_root { &path[root.len()..] } else { path },
+            sep,
+            has_root,
+            at_start: true,
+        }
+    }
+
+    /// Returns the rest of the path, without leading separators and `.`
+    /// components.
+    fn remaining(&self) -> &'a [u8] {
+        let mut rest = self.rest;
+        loop {
+            let start = rest.iter().position(|&b| b != self.sep);
+            rest = &rest[start.unwrap_or(rest.len())..];
+            let end = rest.iter().position(|&b| b == self.sep);
+            if &rest[..end.unwrap_or(rest.len())] != b"." {
+                return rest;
+            }
+            rest = &rest[1..];
+        }
+    }
+}
+
+impl<'a> Iterator for Components<'a> {
+    type Item = Component<'a>;
+
+    fn next(&mut self) -> Option<Component<'a>> {
+        let sep = self.sep;
+        if self.at_start {
+            self.at_start = false;
+            if self.has_root {
+                return Some(Component::RootDir);
+            }
+            let first = self.rest.split(|&b| b == sep).next();
+            if first == Some(b".") {
+                self.rest = &self.rest[1..];
+                return Some(Component::CurDir);
+            }
+        }
+
+        loop {
+            let start = self.rest.iter().position(|&b| b != sep)?;
+            let rest = &self.rest[start..];
+            let end = rest.iter().position(|&b| b == sep).unwrap_or(rest.len());
+            let (component, rest) = rest.split_at(end);
+            self.rest = rest;
+            match component {
+                b"." => continue,
+                b".." => return Some(Component::ParentDir),
+                name => return Some(Component::Normal(name)),
+            }
+        }
+    }
+}
+
+/// Every byte value at its own index, so that the default [`Path::root`] can
+/// borrow a single separator for any lifetime.
+///
+/// [`Path::root`]: trait.Path.html#method.root
+static BYTE_VALUES: [u8; 256] = byte_values();
+
+const fn byte_values() -> [u8; 256] {
+    let mut values = [0; 256];
+    let mut i = 0;
+    while i < values.len() {
+        values[i] = i as u8;
+        i += 1;
+    }
+    values
+}
+
+fn trim_trailing_separators(bytes: &[u8], sep: u8) -> &[u8] {
+    let mut end = bytes.len();
+    while end > 1 && bytes[end - 1] == sep {
+        end -= 1;
+    }
+    &bytes[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use mock::MockPath;
+
+    /// A path using Windows-style separators.
+    #[repr(transparent)]
+    struct BackslashPath([u8]);
+
+    impl Path for BackslashPath {
+        fn as_bytes(&self) -> &[u8] {
+            &self.0
+        }
+
+        fn from_bytes(bytes: &[u8]) -> &Self {
+            // sound since the type is a transparent wrapper of `[u8]`
+            unsafe { &*(bytes as *const [u8] as *const BackslashPath) }
+        }
+
+        fn separator() -> u8 {
+            b'\\'
+        }
+    }
+
+    #[test]
+    fn classifies_byte_paths() {
+        assert!(b"/".is_absolute());
+        assert!(b"/a/b".is_absolute());
+        assert!(!b"a/b".is_absolute());
+        assert!(!b"./a".is_absolute());
+        assert!(!b"".is_absolute());
+        assert_eq!(<[u8]>::root(), b"/");
+    }
+
+    #[test]
+    fn root_follows_separator() {
+        assert_eq!(BackslashPath::root().as_bytes(), b"\\");
+        assert!(BackslashPath::from_bytes(b"\\a").is_absolute());
+        assert!(!BackslashPath::from_bytes(b"/a").is_absolute());
+        let path = BackslashPath::from_bytes(b"\\a\\b");
+        assert_eq!(path.parent().map(Path::as_bytes), Some(&b"\\a"[..]));
+    }
+
+    #[test]
+    fn root_component_follows_separator() {
+        let root = BackslashPath::from_bytes(b"\\a").components().next();
+        assert_eq!(root, Some(Component::RootDir));
+        assert_eq!(Component::RootDir.as_bytes::<BackslashPath>(), b"\\");
+        assert_eq!(Component::RootDir.as_bytes::<[u8]>(), b"/");
+    }
+
+    #[test]
+    fn components_skip_cur_dir() {
+        let components: Vec<_> = b"/a/./b/../c".components().collect();
+        assert_eq!(
+            components,
+            [
+                Component::RootDir,
+                Component::Normal(b"a"),
+                Component::Normal(b"b"),
+                Component::ParentDir,
+                Component::Normal(b"c"),
+            ]
+        );
+
+        let components: Vec<_> = b"./a//b/".components().collect();
+        assert_eq!(
+            components,
+            [
+                Component::CurDir,
+                Component::Normal(b"a"),
+                Component::Normal(b"b"),
+            ]
+        );
+        assert_eq!(b"".components().next(), None);
+    }
+
+    #[test]
+    fn prefixes_match_whole_components() {
+        // the inherent slice methods would shadow the trait methods
+        let starts_with = <[u8] as Path>::starts_with;
+        let strip_prefix = <[u8] as Path>::strip_prefix;
+
+        assert!(starts_with(b"/a/b", b"/a"));
+        assert!(starts_with(b"/a/b", b"/a/"));
+        assert!(starts_with(b"/a/b", b"/"));
+        assert!(!starts_with(b"/ab", b"/a"));
+        assert!(!starts_with(b"/a", b"/a/b"));
+        assert!(!starts_with(b"a/b", b"/a"));
+
+        assert_eq!(strip_prefix(b"/a/b/c", b"/a"), Some(&b"b/c"[..]));
+        assert_eq!(strip_prefix(b"/a//b", b"/a/"), Some(&b"b"[..]));
+        assert_eq!(strip_prefix(b"/a", b"/a"), Some(&b""[..]));
+        assert_eq!(strip_prefix(b"a/b", b""), Some(&b"a/b"[..]));
+        assert_eq!(strip_prefix(b"/ab/c", b"/a"), None);
+        assert_eq!(strip_prefix(b"/x/b", b"/a"), None);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn pop_truncates_vec() {
+        let mut path = b"/a/b//".to_vec();
+        assert!(PathBuf::pop(&mut path));
+        assert_eq!(path, b"/a");
+        assert!(PathBuf::pop(&mut path));
+        assert_eq!(path, b"/");
+        assert!(!PathBuf::pop(&mut path));
+        assert_eq!(path, b"/");
+    }
+
+    #[test]
+    fn pop_copies_parent() {
+        let mut path = MockPath(b"a/b".to_vec());
+        assert!(path.pop());
+        assert_eq!(path.0, b"a");
+        assert!(path.pop());
+        assert_eq!(path.0, b"");
+        assert!(!path.pop());
+    }
+}
@@ -0,0 +1,249 @@
+use core::cell::Cell;
+use core::cmp;
+
+use {ErrorKind, ErrorType, Read, Write};
+
+/// A budget of bytes shared by any number of [`QuotaReader`]s and
+/// [`QuotaWriter`]s.
+///
+/// Every byte transferred through one of the wrappers is deducted from the
+/// quota. Once it is spent, further transfers fail with an error of kind
+/// [`ErrorKind::QuotaExceeded`]. A transfer which would overrun the quota is
+/// shortened, so everything up to the limit is still delivered.
+///
+/// [`QuotaReader`]: struct.QuotaReader.html
+/// [`QuotaWriter`]: struct.QuotaWriter.html
+/// [`ErrorKind::QuotaExceeded`]: enum.ErrorKind.html#variant.QuotaExceeded
+#[derive(Debug)]
+pub struct Quota {
+    remaining: Cell<u64>,
+}
+
+impl Quota {
+    /// Creates a quota allowing `limit` bytes to be transferred.
+    pub fn new(limit: u64) -> Self {
+        Quota {
+            remaining: Cell::new(limit),
+        }
+    }
+
+    /// Returns the number of bytes which can still be transferred.
+    pub fn remaining(&self) -> u64 {
+        self.remaining.get()
+    }
+
+    /// Wraps a reader, so that bytes read from it are deducted from this
+    /// quota.
+    pub fn reader<R>(&self, inner: R) -> QuotaReader<'_, R> {
+        QuotaReader { inner, quota: self }
+    }
+
+    /// Wraps a writer, so that bytes written to it are deducted from this
+    /// quota.
+    pub fn writer<W>(&self, inner: W) -> QuotaWriter<'_, W> {
+        QuotaWriter { inner, quota: self }
+    }
+
+    /// Returns the length `len` is shortened to, or `None` if the quota is
+    /// spent.
+    fn limit(&self, len: usize) -> Option<usize> {
+        match self.remaining.get() {
+            0 => None,
+            remaining => Some(cmp::min(len as u64, remaining) as usize),
+        }
+    }
+
+    fn consume(&self, n: usize) {
+        self.remaining.set(self.remaining.get() - n as u64);
+    }
+}
+
+/// A reader whose reads are deducted from a [`Quota`].
+///
+/// This struct is created by the [`reader`] method on [`Quota`]. Once the
+/// quota is spent, a read checks whether the wrapped reader has reached "end
+/// of file" by reading a single byte from it. If it hasn't, the byte is
+/// discarded and an error of kind [`ErrorKind::QuotaExceeded`] is returned,
+/// so that a stream of exactly the size of the quota can be read to its end.
+///
+/// [`Quota`]: struct.Quota.html
+/// [`reader`]: struct.Quota.html#method.reader
+/// [`ErrorKind::QuotaExceeded`]: enum.ErrorKind.html#variant.QuotaExceeded
+#[derive(Debug)]
+pub struct QuotaReader<'a, R> {
+    inner: R,
+    quota: &'a Quota,
+}
+
+impl<'a, R> QuotaReader<'a, R> {
+    /// Returns the quota this reader draws from.
+    pub fn quota(&self) -> &'a Quota {
+        self.quota
+    }
+
+    /// Gets a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the wrapped reader.
+    ///
+    /// Bytes read directly from the wrapped reader are not deducted from the
+    /// quota.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `QuotaReader`, returning the wrapped reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: ErrorType> ErrorType for QuotaReader<'_, R> {
+    type Error = R::Error;
+}
+
+impl<R: Read> Read for QuotaReader<'_, R> {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match self.quota.limit(buf.len()) {
+            Some(len) => {
+                let n = self.inner.read(&mut buf[..len])?;
+                self.quota.consume(n);
+                Ok(n)
+            }
+            None => match self.inner.read(&mut [0])? {
+                0 => Ok(0),
+                _ => Err(ErrorKind::QuotaExceeded.into()),
+            },
+        }
+    }
+}
+
+/// A writer whose writes are deducted from a [`Quota`].
+///
+/// This struct is created by the [`writer`] method on [`Quota`]. A write
+/// which would overrun the quota is shortened to the remaining budget, the
+/// following write then fails with an error of kind
+/// [`ErrorKind::QuotaExceeded`].
+///
+/// [`Quota`]: struct.Quota.html
+/// [`writer`]: struct.Quota.html#method.writer
+/// [`ErrorKind::QuotaExceeded`]: enum.ErrorKind.html#variant.QuotaExceeded
+#[derive(Debug)]
+pub struct QuotaWriter<'a, W> {
+    inner: W,
+    quota: &'a Quota,
+}
+
+impl<'a, W> QuotaWriter<'a, W> {
+    /// Returns the quota this writer draws from.
+    pub fn quota(&self) -> &'a Quota {
+        self.quota
+    }
+
+    /// Gets a reference to the wrapped writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the wrapped writer.
+    ///
+    /// Bytes written directly to the wrapped writer are not deducted from
+    /// the quota.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwraps this `QuotaWriter`, returning the wrapped writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: ErrorType> ErrorType for QuotaWriter<'_, W> {
+    type Error = W::Error;
+}
+
+impl<W: Write> Write for QuotaWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let len = self
+            .quota
+            .limit(buf.len())
+            .ok_or(ErrorKind::QuotaExceeded)?;
+        let n = self.inner.write(&buf[..len])?;
+        self.quota.consume(n);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+
+    fn is_flush_noop(&self) -> bool {
+        self.inner.is_flush_noop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock::Cursor;
+
+    #[test]
+    fn writes_under_at_and_over_quota() {
+        let quota = Quota::new(10);
+        let mut writer = quota.writer(Cursor::new(b""));
+        writer.write_all(b"123456789").unwrap();
+        assert_eq!(quota.remaining(), 1);
+        writer.write_all(b"0").unwrap();
+        assert_eq!(quota.remaining(), 0);
+        assert_eq!(writer.write_all(b"x"), Err(ErrorKind::QuotaExceeded));
+        assert_eq!(writer.get_ref().data(), b"1234567890");
+
+        // an overrunning write still delivers everything up to the limit
+        let quota = Quota::new(4);
+        let mut writer = quota.writer(Cursor::new(b""));
+        assert_eq!(writer.write_all(b"abcdef"), Err(ErrorKind::QuotaExceeded));
+        assert_eq!(writer.into_inner().data(), b"abcd");
+    }
+
+    #[test]
+    fn reads_under_at_and_over_quota() {
+        let mut buf = [0; 16];
+        let quota = Quota::new(6);
+        let reader = quota.reader(Cursor::new(b"abcde"));
+        assert_eq!(reader.read(&mut buf).unwrap(), 5);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        assert_eq!(quota.remaining(), 1);
+
+        // a stream of exactly the size of the quota is read to its end
+        let quota = Quota::new(5);
+        let reader = quota.reader(Cursor::new(b"abcde"));
+        assert_eq!(reader.read(&mut buf).unwrap(), 5);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+
+        let quota = Quota::new(4);
+        let reader = quota.reader(Cursor::new(b"abcde"));
+        assert_eq!(reader.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf[..4], b"abcd");
+        assert_eq!(reader.read(&mut buf), Err(ErrorKind::QuotaExceeded));
+    }
+
+    #[test]
+    fn readers_and_writers_share_quota() {
+        let quota = Quota::new(6);
+        let reader = quota.reader(Cursor::new(b"abcd"));
+        let mut writer = quota.writer(Cursor::new(b""));
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(writer.write(b"efgh").unwrap(), 2);
+        assert_eq!(writer.write(b"gh"), Err(ErrorKind::QuotaExceeded));
+    }
+}
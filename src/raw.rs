@@ -0,0 +1,86 @@
+/// Access to the native handle behind a file, like a unix file descriptor.
+///
+/// This is an escape hatch for filesystems which wrap real operating system
+/// files, letting advanced users call native APIs directly. Virtual
+/// filesystems aren't expected to implement it.
+///
+/// The handle is only borrowed: it stays owned by the implementing type and is
+/// closed when that is dropped. Operations performed directly on the handle
+/// bypass any state kept by the wrapper, like buffers or cached positions,
+/// which may become inconsistent as a result.
+pub trait AsRawHandle {
+    /// The type of the native handle, e.g. `i32` for unix file descriptors.
+    type RawHandle: Copy;
+
+    /// Extracts the native handle without taking ownership of it.
+    fn as_raw_handle(&self) -> Self::RawHandle;
+}
+
+/// Conversion of a file into its native handle, transferring ownership.
+pub trait IntoRawHandle: AsRawHandle {
+    /// Consumes this file, returning its native handle.
+    ///
+    /// The caller becomes responsible for closing the handle.
+    fn into_raw_handle(self) -> Self::RawHandle;
+}
+
+/// Construction of a file from a native handle, taking ownership of it.
+pub trait FromRawHandle: AsRawHandle {
+    /// Constructs a file from a native handle.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be a valid, open handle of the expected kind which isn't
+    /// owned by anything else. The returned file takes over ownership and
+    /// closes the handle when it is dropped.
+    unsafe fn from_raw_handle(handle: Self::RawHandle) -> Self;
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+    use std::io::Read;
+    use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+
+    use super::*;
+
+    struct OsFile(fs::File);
+
+    impl AsRawHandle for OsFile {
+        type RawHandle = RawFd;
+
+        fn as_raw_handle(&self) -> RawFd {
+            self.0.as_raw_fd()
+        }
+    }
+
+    impl IntoRawHandle for OsFile {
+        fn into_raw_handle(self) -> RawFd {
+            self.0.into_raw_fd()
+        }
+    }
+
+    impl FromRawHandle for OsFile {
+        unsafe fn from_raw_handle(handle: RawFd) -> Self {
+            OsFile(fs::File::from_raw_fd(handle))
+        }
+    }
+
+    #[test]
+    fn round_trips_real_descriptor() {
+        let path = std::env::temp_dir().join("genfs-raw-handle-test");
+        fs::write(&path, b"native").unwrap();
+        let file = OsFile(fs::File::open(&path).unwrap());
+        let fd = file.as_raw_handle();
+        assert_eq!(fd, file.0.as_raw_fd());
+
+        // the descriptor stays open across the ownership transfer
+        assert_eq!(file.into_raw_handle(), fd);
+        let file = unsafe { OsFile::from_raw_handle(fd) };
+        let mut data = std::vec::Vec::new();
+        (&file.0).read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"native");
+        assert_eq!(file.as_raw_handle(), fd);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,330 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use MountInfo;
+use {
+    DirOptions, ErrorKind, FileTimes, Fs, LinkKind, Metadata, OpenOptions,
+    SpecialKind,
+};
+
+/// A filesystem wrapper which rejects all operations that would modify the
+/// underlying filesystem.
+///
+/// Operations that only read are forwarded to the wrapped filesystem. Files
+/// can only be opened if none of the write, append, create, create-new or
+/// truncate options are set.
+///
+/// All mutating methods, and [`open`] with any of the options above, return an
+/// error of kind [`ErrorKind::PermissionDenied`] without calling the wrapped
+/// filesystem.
+///
+/// [`open`]: trait.Fs.html#method.open
+/// [`ErrorKind::PermissionDenied`]: enum.ErrorKind.html#variant.PermissionDenied
+#[derive(Debug, Clone, PartialEq, Eq, Default, Hash)]
+pub struct ReadOnlyFs<F> {
+    inner: F,
+}
+
+impl<F: Fs> ReadOnlyFs<F> {
+    /// Wraps a filesystem, making it read-only.
+    pub fn new(inner: F) -> Self {
+        ReadOnlyFs { inner }
+    }
+
+    /// Gets a reference to the underlying filesystem.
+    pub fn get_ref(&self) -> &F {
+        &self.inner
+    }
+
+    /// Unwraps this `ReadOnlyFs`, returning the underlying filesystem.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F: Fs> Fs for ReadOnlyFs<F> {
+    type Path = F::Path;
+    type PathOwned = F::PathOwned;
+    type File = F::File;
+    type Dir = F::Dir;
+    type DirEntry = F::DirEntry;
+    type Metadata = F::Metadata;
+    type Permissions = F::Permissions;
+    type Error = F::Error;
+
+    fn open(
+        &self,
+        path: &Self::Path,
+        options: &OpenOptions<Self::Permissions>,
+    ) -> Result<Self::File, Self::Error> {
+        if options.write
+            || options.append
+            || options.create
+            || options.create_new
+            || options.truncate
+        {
+            return Err(ErrorKind::PermissionDenied.into());
+        }
+        self.inner.open(path, options)
+    }
+
+    fn open_anonymous(
+        &mut self,
+        _dir: &Self::Path,
+        _options: &OpenOptions<Self::Permissions>,
+    ) -> Result<Self::File, Self::Error> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn remove_file(&mut self, _path: &Self::Path) -> Result<(), Self::Error> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn metadata(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::Metadata, Self::Error> {
+        self.inner.metadata(path)
+    }
+
+    fn symlink_metadata(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::Metadata, Self::Error> {
+        self.inner.symlink_metadata(path)
+    }
+
+    fn file_type(
+        &self,
+        path: &Self::Path,
+    ) -> Result<<Self::Metadata as Metadata>::FileType, Self::Error> {
+        self.inner.file_type(path)
+    }
+
+    fn symlink_file_type(
+        &self,
+        path: &Self::Path,
+    ) -> Result<<Self::Metadata as Metadata>::FileType, Self::Error> {
+        self.inner.symlink_file_type(path)
+    }
+
+    fn rename(
+        &mut self,
+        _from: &Self::Path,
+        _to: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn copy(
+        &mut self,
+        _from: &Self::Path,
+        _to: &Self::Path,
+    ) -> Result<u64, Self::Error> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn reflink(
+        &mut self,
+        _from: &Self::Path,
+        _to: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn hard_link(
+        &mut self,
+        _src: &Self::Path,
+        _dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn hard_link_checked(
+        &mut self,
+        _src: &Self::Path,
+        _dst: &Self::Path,
+    ) -> Result<LinkKind, Self::Error> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn symlink(
+        &mut self,
+        _src: &Self::Path,
+        _dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn symlink_file(
+        &mut self,
+        _src: &Self::Path,
+        _dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn symlink_dir(
+        &mut self,
+        _src: &Self::Path,
+        _dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn mknod(
+        &mut self,
+        _path: &Self::Path,
+        _mode: Self::Permissions,
+        _kind: SpecialKind,
+        _dev: u64,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn read_link(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::PathOwned, Self::Error> {
+        self.inner.read_link(path)
+    }
+
+    fn canonicalize(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::PathOwned, Self::Error> {
+        self.inner.canonicalize(path)
+    }
+
+    fn canonicalize_into(
+        &self,
+        path: &Self::Path,
+        out: &mut Self::PathOwned,
+    ) -> Result<(), Self::Error> {
+        self.inner.canonicalize_into(path, out)
+    }
+
+    fn create_dir(
+        &mut self,
+        _path: &Self::Path,
+        _options: &DirOptions<Self::Permissions>,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn remove_dir(&mut self, _path: &Self::Path) -> Result<(), Self::Error> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn remove_dir_all(
+        &mut self,
+        _path: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn read_dir(&self, path: &Self::Path) -> Result<Self::Dir, Self::Error> {
+        self.inner.read_dir(path)
+    }
+
+    fn set_permissions(
+        &mut self,
+        _path: &Self::Path,
+        _perm: Self::Permissions,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn set_permissions_nofollow(
+        &mut self,
+        _path: &Self::Path,
+        _perm: Self::Permissions,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn set_times(
+        &mut self,
+        _path: &Self::Path,
+        _times: FileTimes,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn current_dir(&self) -> Result<Self::PathOwned, Self::Error> {
+        self.inner.current_dir()
+    }
+
+    fn set_current_dir(
+        &mut self,
+        path: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.inner.set_current_dir(path)
+    }
+
+    fn available_space(&self, path: &Self::Path) -> Result<u64, Self::Error> {
+        self.inner.available_space(path)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn mount_points(
+        &self,
+    ) -> Result<Vec<MountInfo<Self::PathOwned>>, Self::Error> {
+        let mut mounts = self.inner.mount_points()?;
+        for mount in &mut mounts {
+            mount.read_only = true;
+        }
+        Ok(mounts)
+    }
+
+    fn mount_id(&self, path: &Self::Path) -> Result<u64, Self::Error> {
+        self.inner.mount_id(path)
+    }
+
+    fn sync(&self) -> Result<(), Self::Error> {
+        self.inner.sync()
+    }
+
+    fn flush_all(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush_all()
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use mem::MemFs;
+    use mock::{read_file, write_file};
+
+    #[test]
+    fn reads_pass_through() {
+        let inner = MemFs::new();
+        write_file(&inner, b"/file", b"data").unwrap();
+        let fs = ReadOnlyFs::new(inner);
+        assert_eq!(read_file(&fs, b"/file").unwrap(), b"data");
+        assert_eq!(fs.metadata(b"/file").unwrap().len(), 4);
+        assert_eq!(fs.read_dir(b"/").unwrap().count(), 1);
+    }
+
+    #[test]
+    fn writes_are_rejected() {
+        let inner = MemFs::new();
+        write_file(&inner, b"/file", b"data").unwrap();
+        let mode = inner.metadata(b"/file").unwrap().mode();
+        let mut fs = ReadOnlyFs::new(inner);
+        let denied = Err(ErrorKind::PermissionDenied);
+        assert_eq!(write_file(&fs, b"/file", b"x"), denied);
+        assert_eq!(
+            fs.open(b"/file", OpenOptions::new().read(true).append(true))
+                .err(),
+            Some(ErrorKind::PermissionDenied)
+        );
+        assert_eq!(fs.remove_file(b"/file"), denied);
+        assert_eq!(fs.rename(b"/file", b"/other"), denied);
+        assert_eq!(fs.create_dir(b"/dir", &DirOptions::new()), denied);
+        assert_eq!(fs.set_permissions(b"/file", 0o700), denied);
+        assert_eq!(fs.get_ref().metadata(b"/file").unwrap().mode(), mode);
+        assert_eq!(read_file(fs.get_ref(), b"/file").unwrap(), b"data");
+    }
+}
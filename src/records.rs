@@ -0,0 +1,89 @@
+use {Error, ErrorKind, Read};
+
+/// An iterator over fixed-size records of a reader.
+///
+/// This struct is created by the [`records`] method on [`Read`]. Each item is
+/// the next `N` bytes of the reader. Iteration ends when the reader reaches
+/// "end of file" at a record boundary. If it ends within a record, an error
+/// of kind [`ErrorKind::UnexpectedEof`] is yielded instead, which indicates a
+/// truncated source, and the partial record is discarded.
+///
+/// With `N == 0` the iterator is empty: it yields `None` right away, without
+/// reading, rather than endless empty records.
+///
+/// [`records`]: trait.Read.html#method.records
+/// [`Read`]: trait.Read.html
+/// [`ErrorKind::UnexpectedEof`]: enum.ErrorKind.html#variant.UnexpectedEof
+#[derive(Debug)]
+pub struct Records<R, const N: usize> {
+    inner: R,
+}
+
+impl<R, const N: usize> Records<R, N> {
+    pub(crate) fn new(inner: R) -> Self {
+        Records { inner }
+    }
+
+    /// Gets a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Unwraps this `Records`, returning the wrapped reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read, const N: usize> Iterator for Records<R, N> {
+    type Item = Result<[u8; N], R::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if N == 0 {
+            return None;
+        }
+        let mut record = [0u8; N];
+        let mut filled = 0;
+        while filled < N {
+            match self.inner.read(&mut record[filled..]) {
+                Ok(0) if filled == 0 => return None,
+                Ok(0) => return Some(Err(ErrorKind::UnexpectedEof.into())),
+                Ok(n) => filled += n,
+                Err(ref err) if err.is_interrupted() => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        Some(Ok(record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use mock::Cursor;
+    use {ErrorKind, Read};
+
+    #[test]
+    fn yields_whole_records() {
+        let records: Result<Vec<[u8; 2]>, _> =
+            Cursor::new(b"abcdef").chunked(1).records().collect();
+        assert_eq!(records.unwrap(), [*b"ab", *b"cd", *b"ef"]);
+    }
+
+    #[test]
+    fn reports_truncated_record() {
+        let mut records = Cursor::new(b"abcde").records::<2>();
+        assert_eq!(records.next(), Some(Ok(*b"ab")));
+        assert_eq!(records.next(), Some(Ok(*b"cd")));
+        assert_eq!(records.next(), Some(Err(ErrorKind::UnexpectedEof)));
+        assert_eq!(records.next(), None);
+    }
+
+    #[test]
+    fn zero_sized_records_are_empty() {
+        let mut records = Cursor::new(b"abc").records::<0>();
+        assert_eq!(records.next(), None);
+        assert_eq!(records.get_ref().reads.get(), 0);
+    }
+}
@@ -0,0 +1,476 @@
+use {
+    Dir, DirEntry, DirOptions, DirPos, ErrorKind, FileTimes, Fs, LinkKind,
+    Metadata, OpenOptions, Path, PathBuf, SpecialKind,
+};
+
+/// A filesystem wrapper which confines all paths to a subtree of the
+/// underlying filesystem.
+///
+/// Every path is normalized lexically and joined under a fixed base path
+/// before being passed to the wrapped filesystem, much like `chroot`.
+/// Absolute paths are interpreted relative to the base, so `/etc` refers to
+/// `<base>/etc`. A path whose `..` components would lead out of the base
+/// results in an error of kind [`ErrorKind::PermissionDenied`].
+///
+/// Paths returned by [`canonicalize`] and by directory entries are relative to
+/// the base again. Since normalization is purely lexical, symbolic links
+/// within the subtree may still point outside of it and are not confined.
+///
+/// [`ErrorKind::PermissionDenied`]: enum.ErrorKind.html#variant.PermissionDenied
+/// [`canonicalize`]: trait.Fs.html#method.canonicalize
+pub struct ScopedFs<F: Fs> {
+    inner: F,
+    base: F::PathOwned,
+}
+
+impl<F: Fs> ScopedFs<F> {
+    /// Wraps a filesystem, confining it to the subtree at `base`.
+    pub fn new(inner: F, base: F::PathOwned) -> Self {
+        ScopedFs { inner, base }
+    }
+
+    /// Returns the base path all paths are joined under.
+    pub fn base(&self) -> &F::Path {
+        self.base.as_path()
+    }
+
+    /// Gets a reference to the underlying filesystem.
+    pub fn get_ref(&self) -> &F {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying filesystem.
+    pub fn get_mut(&mut self) -> &mut F {
+        &mut self.inner
+    }
+
+    /// Unwraps this `ScopedFs`, returning the underlying filesystem.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    fn scope(&self, path: &F::Path) -> Result<F::PathOwned, F::Error> {
+        let sep = <F::Path as Path>::separator();
+        let components = || {
+            path.as_bytes()
+                .split(|&b| b == sep)
+                .filter(|c| !c.is_empty() && *c != b".")
+        };
+
+        let mut depth = 0usize;
+        for component in components() {
+            if component != b".." {
+                depth += 1;
+            } else if depth == 0 {
+                return Err(ErrorKind::PermissionDenied.into());
+            } else {
+                depth -= 1;
+            }
+        }
+
+        let mut scoped = F::PathOwned::from_path(self.base.as_path())?;
+        for (i, component) in components().enumerate() {
+            if component == b".." || is_cancelled(components().skip(i + 1)) {
+                continue;
+            }
+            scoped.push(<F::Path as Path>::from_bytes(component))?;
+        }
+        Ok(scoped)
+    }
+}
+
+/// Returns `true` if a component followed by `rest` is removed by one of the
+/// `..` components in `rest`.
+fn is_cancelled<'a, I: Iterator<Item = &'a [u8]>>(rest: I) -> bool {
+    let mut depth = 0usize;
+    for component in rest {
+        if component != b".." {
+            depth += 1;
+        } else if depth == 0 {
+            return true;
+        } else {
+            depth -= 1;
+        }
+    }
+    false
+}
+
+/// Strips the first `base_len` bytes from `path`, if it's within the base.
+fn unscope<P: ?Sized + Path, O: PathBuf<P>>(path: O, base_len: usize) -> O {
+    match path.as_path().as_bytes().get(base_len..) {
+        Some(rest) => O::from_path(P::from_bytes(rest)).unwrap_or(path),
+        None => path,
+    }
+}
+
+impl<F: Fs> Fs for ScopedFs<F> {
+    type Path = F::Path;
+    type PathOwned = F::PathOwned;
+    type File = F::File;
+    type Dir = ScopedDir<F::Dir>;
+    type DirEntry = ScopedDirEntry<F::DirEntry>;
+    type Metadata = F::Metadata;
+    type Permissions = F::Permissions;
+    type Error = F::Error;
+
+    fn open(
+        &self,
+        path: &Self::Path,
+        options: &OpenOptions<Self::Permissions>,
+    ) -> Result<Self::File, Self::Error> {
+        self.inner.open(self.scope(path)?.as_path(), options)
+    }
+
+    fn open_anonymous(
+        &mut self,
+        dir: &Self::Path,
+        options: &OpenOptions<Self::Permissions>,
+    ) -> Result<Self::File, Self::Error> {
+        let dir = self.scope(dir)?;
+        self.inner.open_anonymous(dir.as_path(), options)
+    }
+
+    fn remove_file(&mut self, path: &Self::Path) -> Result<(), Self::Error> {
+        let path = self.scope(path)?;
+        self.inner.remove_file(path.as_path())
+    }
+
+    fn metadata(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::Metadata, Self::Error> {
+        self.inner.metadata(self.scope(path)?.as_path())
+    }
+
+    fn symlink_metadata(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::Metadata, Self::Error> {
+        self.inner.symlink_metadata(self.scope(path)?.as_path())
+    }
+
+    fn file_type(
+        &self,
+        path: &Self::Path,
+    ) -> Result<<Self::Metadata as Metadata>::FileType, Self::Error> {
+        self.inner.file_type(self.scope(path)?.as_path())
+    }
+
+    fn symlink_file_type(
+        &self,
+        path: &Self::Path,
+    ) -> Result<<Self::Metadata as Metadata>::FileType, Self::Error> {
+        self.inner.symlink_file_type(self.scope(path)?.as_path())
+    }
+
+    fn rename(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        let from = self.scope(from)?;
+        let to = self.scope(to)?;
+        self.inner.rename(from.as_path(), to.as_path())
+    }
+
+    fn copy(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<u64, Self::Error> {
+        let from = self.scope(from)?;
+        let to = self.scope(to)?;
+        self.inner.copy(from.as_path(), to.as_path())
+    }
+
+    fn reflink(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        let from = self.scope(from)?;
+        let to = self.scope(to)?;
+        self.inner.reflink(from.as_path(), to.as_path())
+    }
+
+    fn hard_link(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        let src = self.scope(src)?;
+        let dst = self.scope(dst)?;
+        self.inner.hard_link(src.as_path(), dst.as_path())
+    }
+
+    fn hard_link_checked(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<LinkKind, Self::Error> {
+        let src = self.scope(src)?;
+        let dst = self.scope(dst)?;
+        self.inner.hard_link_checked(src.as_path(), dst.as_path())
+    }
+
+    fn symlink(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        let dst = self.scope(dst)?;
+        self.inner.symlink(src, dst.as_path())
+    }
+
+    fn symlink_file(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        let dst = self.scope(dst)?;
+        self.inner.symlink_file(src, dst.as_path())
+    }
+
+    fn symlink_dir(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        let dst = self.scope(dst)?;
+        self.inner.symlink_dir(src, dst.as_path())
+    }
+
+    fn mknod(
+        &mut self,
+        path: &Self::Path,
+        mode: Self::Permissions,
+        kind: SpecialKind,
+        dev: u64,
+    ) -> Result<(), Self::Error> {
+        let path = self.scope(path)?;
+        self.inner.mknod(path.as_path(), mode, kind, dev)
+    }
+
+    fn read_link(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::PathOwned, Self::Error> {
+        self.inner.read_link(self.scope(path)?.as_path())
+    }
+
+    fn canonicalize(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::PathOwned, Self::Error> {
+        let path = self.inner.canonicalize(self.scope(path)?.as_path())?;
+        let base = self.inner.canonicalize(self.base.as_path())?;
+        let base = base.as_path().as_bytes();
+        let bytes = path.as_path().as_bytes();
+        let sep = <F::Path as Path>::separator();
+        let within = bytes.starts_with(base)
+            && (base.last() == Some(&sep)
+                || bytes.len() == base.len()
+                || bytes[base.len()] == sep);
+        if !within {
+            return Err(ErrorKind::PermissionDenied.into());
+        }
+        let rest = match &bytes[base.len()..] {
+            b"" => <F::Path as Path>::root().as_bytes(),
+            rest => rest,
+        };
+        Ok(F::PathOwned::from_path(<F::Path as Path>::from_bytes(
+            rest,
+        ))?)
+    }
+
+    fn create_dir(
+        &mut self,
+        path: &Self::Path,
+        options: &DirOptions<Self::Permissions>,
+    ) -> Result<(), Self::Error> {
+        let path = self.scope(path)?;
+        self.inner.create_dir(path.as_path(), options)
+    }
+
+    fn remove_dir(&mut self, path: &Self::Path) -> Result<(), Self::Error> {
+        let path = self.scope(path)?;
+        self.inner.remove_dir(path.as_path())
+    }
+
+    fn remove_dir_all(&mut self, path: &Self::Path) -> Result<(), Self::Error> {
+        let path = self.scope(path)?;
+        self.inner.remove_dir_all(path.as_path())
+    }
+
+    fn read_dir(&self, path: &Self::Path) -> Result<Self::Dir, Self::Error> {
+        Ok(ScopedDir {
+            inner: self.inner.read_dir(self.scope(path)?.as_path())?,
+            base_len: self.base.as_path().as_bytes().len(),
+        })
+    }
+
+    fn set_permissions(
+        &mut self,
+        path: &Self::Path,
+        perm: Self::Permissions,
+    ) -> Result<(), Self::Error> {
+        let path = self.scope(path)?;
+        self.inner.set_permissions(path.as_path(), perm)
+    }
+
+    fn set_permissions_nofollow(
+        &mut self,
+        path: &Self::Path,
+        perm: Self::Permissions,
+    ) -> Result<(), Self::Error> {
+        let path = self.scope(path)?;
+        self.inner.set_permissions_nofollow(path.as_path(), perm)
+    }
+
+    fn set_times(
+        &mut self,
+        path: &Self::Path,
+        times: FileTimes,
+    ) -> Result<(), Self::Error> {
+        let path = self.scope(path)?;
+        self.inner.set_times(path.as_path(), times)
+    }
+
+    fn available_space(&self, path: &Self::Path) -> Result<u64, Self::Error> {
+        self.inner.available_space(self.scope(path)?.as_path())
+    }
+
+    fn mount_id(&self, path: &Self::Path) -> Result<u64, Self::Error> {
+        self.inner.mount_id(self.scope(path)?.as_path())
+    }
+
+    fn sync(&self) -> Result<(), Self::Error> {
+        self.inner.sync()
+    }
+
+    fn flush_all(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush_all()
+    }
+}
+
+/// Iterator over the entries in a directory of a [`ScopedFs`].
+///
+/// [`ScopedFs`]: struct.ScopedFs.html
+#[derive(Debug, Clone)]
+pub struct ScopedDir<D> {
+    inner: D,
+    base_len: usize,
+}
+
+impl<D, T, E> Iterator for ScopedDir<D>
+where
+    D: Iterator<Item = Result<T, E>>,
+{
+    type Item = Result<ScopedDirEntry<T>, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let base_len = self.base_len;
+        self.inner
+            .next()
+            .map(|entry| entry.map(|inner| ScopedDirEntry { inner, base_len }))
+    }
+}
+
+impl<D, T, E> Dir<ScopedDirEntry<T>, E> for ScopedDir<D>
+where
+    D: Dir<T, E>,
+    T: DirEntry,
+    E: ::Error,
+{
+    fn rewind(&mut self) -> Result<(), E> {
+        self.inner.rewind()
+    }
+
+    fn tell(&self) -> Result<DirPos, E> {
+        self.inner.tell()
+    }
+
+    fn seek(&mut self, pos: DirPos) -> Result<(), E> {
+        self.inner.seek(pos)
+    }
+}
+
+/// An entry in a directory of a [`ScopedFs`].
+///
+/// [`ScopedFs`]: struct.ScopedFs.html
+#[derive(Debug, Clone)]
+pub struct ScopedDirEntry<E> {
+    inner: E,
+    base_len: usize,
+}
+
+impl<E: DirEntry> DirEntry for ScopedDirEntry<E> {
+    type Path = E::Path;
+    type PathOwned = E::PathOwned;
+    type Metadata = E::Metadata;
+    type FileType = E::FileType;
+    type Error = E::Error;
+
+    fn path(&self) -> Self::PathOwned {
+        unscope(self.inner.path(), self.base_len)
+    }
+
+    fn metadata(&self) -> Result<Self::Metadata, Self::Error> {
+        self.inner.metadata()
+    }
+
+    fn file_type(&self) -> Result<Self::FileType, Self::Error> {
+        self.inner.file_type()
+    }
+
+    fn file_name(&self) -> &Self::Path {
+        self.inner.file_name()
+    }
+
+    fn sort_key(&self) -> Result<(bool, &[u8]), Self::Error> {
+        self.inner.sort_key()
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use mem::MemFs;
+    use mock::{read_file, write_file};
+
+    fn jail() -> ScopedFs<MemFs> {
+        let mut inner = MemFs::new();
+        inner.create_dir(b"/jail", &DirOptions::new()).unwrap();
+        write_file(&inner, b"/jail/file", b"inside").unwrap();
+        write_file(&inner, b"/secret", b"outside").unwrap();
+        ScopedFs::new(inner, b"/jail".to_vec())
+    }
+
+    #[test]
+    fn in_bounds_path() {
+        let fs = jail();
+        assert_eq!(read_file(&fs, b"file").unwrap(), b"inside");
+        assert_eq!(read_file(&fs, b"./a/../file").unwrap(), b"inside");
+        assert_eq!(fs.canonicalize(b"file").unwrap(), b"/file");
+    }
+
+    #[test]
+    fn parent_escape_is_denied() {
+        let fs = jail();
+        assert_eq!(
+            read_file(&fs, b"../secret"),
+            Err(ErrorKind::PermissionDenied)
+        );
+        assert_eq!(
+            read_file(&fs, b"a/../../secret"),
+            Err(ErrorKind::PermissionDenied)
+        );
+    }
+
+    #[test]
+    fn absolute_path_stays_inside() {
+        let fs = jail();
+        assert_eq!(read_file(&fs, b"/file").unwrap(), b"inside");
+        assert_eq!(read_file(&fs, b"/secret"), Err(ErrorKind::NotFound));
+    }
+}
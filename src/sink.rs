@@ -0,0 +1,62 @@
+use core::fmt;
+use core::marker::PhantomData;
+
+use {Error, ErrorType, Write};
+
+/// A writer which discards all data written to it.
+///
+/// Every write succeeds and reports the whole buffer as written, like writing
+/// to `/dev/null`. The error type `E` is never returned, it only allows
+/// combining a `Sink` with other writers, e.g. in a [`Tee`].
+///
+/// [`Tee`]: struct.Tee.html
+pub struct Sink<E> {
+    marker: PhantomData<fn() -> E>,
+}
+
+impl<E> Sink<E> {
+    /// Creates a writer which discards all data.
+    pub fn new() -> Self {
+        Sink {
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<E> Default for Sink<E> {
+    fn default() -> Self {
+        Sink::new()
+    }
+}
+
+impl<E> Clone for Sink<E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E> Copy for Sink<E> {}
+
+impl<E> fmt::Debug for Sink<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Sink")
+    }
+}
+
+impl<E: Error> ErrorType for Sink<E> {
+    type Error = E;
+}
+
+impl<E: Error> Write for Sink<E> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn is_flush_noop(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,116 @@
+use core::cell::Cell;
+use core::cmp;
+
+use {ErrorType, Read};
+
+/// A reader which reads at most a limited number of bytes from another
+/// reader.
+///
+/// This struct is created by the [`take`] method on [`Read`]. Once the limit
+/// is reached, it reports "end of file" without reading any further.
+///
+/// [`take`]: trait.Read.html#method.take
+/// [`Read`]: trait.Read.html
+#[derive(Debug)]
+pub struct Take<R> {
+    inner: R,
+    limit: Cell<u64>,
+    consumed: Cell<u64>,
+}
+
+impl<R> Take<R> {
+    pub(crate) fn new(inner: R, limit: u64) -> Self {
+        Take {
+            inner,
+            limit: Cell::new(limit),
+            consumed: Cell::new(0),
+        }
+    }
+
+    /// Returns the number of bytes which can still be read before the limit
+    /// is reached.
+    pub fn limit(&self) -> u64 {
+        self.limit.get()
+    }
+
+    /// Sets the number of bytes which can be read before the limit is
+    /// reached, without affecting [`bytes_consumed`].
+    ///
+    /// [`bytes_consumed`]: #method.bytes_consumed
+    pub fn set_limit(&mut self, limit: u64) {
+        self.limit.set(limit);
+    }
+
+    /// Returns the number of bytes read through this adapter so far.
+    ///
+    /// If the wrapped reader reaches "end of file" before the limit, this is
+    /// less than the original limit, which reveals e.g. a truncated frame.
+    pub fn bytes_consumed(&self) -> u64 {
+        self.consumed.get()
+    }
+
+    /// Gets a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the wrapped reader.
+    ///
+    /// Bytes read directly from the wrapped reader don't count towards the
+    /// limit.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `Take`, returning the wrapped reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: ErrorType> ErrorType for Take<R> {
+    type Error = R::Error;
+}
+
+impl<R: Read> Read for Take<R> {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let limit = self.limit.get();
+        if limit == 0 {
+            return Ok(0);
+        }
+        let len = cmp::min(buf.len() as u64, limit) as usize;
+        let n = self.inner.read(&mut buf[..len])?;
+        self.limit.set(limit - n as u64);
+        self.consumed.set(self.consumed.get() + n as u64);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mock::Cursor;
+    use Read;
+
+    #[test]
+    fn bytes_consumed_stops_at_eof() {
+        let reader = Cursor::new(b"abc").chunked(2).take(8);
+        let mut buf = [0; 8];
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        assert_eq!(reader.read(&mut buf).unwrap(), 1);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        assert_eq!(reader.bytes_consumed(), 3);
+        assert_eq!(reader.limit(), 5);
+    }
+
+    #[test]
+    fn bytes_consumed_survives_set_limit() {
+        let mut reader = Cursor::new(b"abcdef").take(2);
+        let mut buf = [0; 8];
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        reader.set_limit(3);
+        assert_eq!(reader.read(&mut buf).unwrap(), 3);
+        assert_eq!(reader.bytes_consumed(), 5);
+        assert_eq!(reader.limit(), 0);
+    }
+}
@@ -0,0 +1,222 @@
+use core::cell::RefCell;
+
+use {ErrorType, Read, Write};
+
+/// A writer which forwards every write to two writers.
+///
+/// This can be used to log data while writing it, or to write the same data
+/// to two files at once. Both writers must have the same error type.
+///
+/// Every call to [`write`] writes the whole buffer to the first writer and
+/// then to the second one. If the second writer fails after the first one
+/// accepted the data, the error is returned but the first write is not undone,
+/// so the contents of the two writers may diverge.
+///
+/// [`write`]: trait.Write.html#method.write
+#[derive(Debug, Default)]
+pub struct Tee<A, B> {
+    a: A,
+    b: B,
+}
+
+impl<A, B> Tee<A, B> {
+    /// Creates a writer which forwards all writes to `a` and `b`.
+    pub fn new(a: A, b: B) -> Self {
+        Tee { a, b }
+    }
+
+    /// Gets references to both wrapped writers.
+    pub fn get_ref(&self) -> (&A, &B) {
+        (&self.a, &self.b)
+    }
+
+    /// Gets mutable references to both wrapped writers.
+    ///
+    /// Data written directly to either writer is not forwarded to the other.
+    pub fn get_mut(&mut self) -> (&mut A, &mut B) {
+        (&mut self.a, &mut self.b)
+    }
+
+    /// Unwraps this `Tee`, returning both wrapped writers.
+    pub fn into_inner(self) -> (A, B) {
+        (self.a, self.b)
+    }
+}
+
+impl<A: ErrorType, B> ErrorType for Tee<A, B> {
+    type Error = A::Error;
+}
+
+impl<A, B> Write for Tee<A, B>
+where
+    A: Write,
+    B: Write<Error = A::Error>,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.a.write_all(buf)?;
+        self.b.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    /// Flushes both writers.
+    ///
+    /// The second writer is flushed even if flushing the first one fails, in
+    /// which case the first error is returned. A writer whose
+    /// [`is_flush_noop`] returns `true` is skipped.
+    ///
+    /// [`is_flush_noop`]: trait.Write.html#method.is_flush_noop
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        let a = if self.a.is_flush_noop() {
+            Ok(())
+        } else {
+            self.a.flush()
+        };
+        let b = if self.b.is_flush_noop() {
+            Ok(())
+        } else {
+            self.b.flush()
+        };
+        a.and(b)
+    }
+
+    fn is_flush_noop(&self) -> bool {
+        self.a.is_flush_noop() && self.b.is_flush_noop()
+    }
+
+    fn reserve(&mut self, additional: usize) -> Result<(), Self::Error> {
+        self.a.reserve(additional)?;
+        self.b.reserve(additional)
+    }
+}
+
+/// A reader which copies all bytes read through it into a writer.
+///
+/// Like `tee(1)`, this captures a stream while it is being consumed, e.g. to
+/// cache it. Only the bytes actually returned by successful reads are copied.
+///
+/// Every call to [`read`] reads from the wrapped reader and then writes the
+/// bytes read to the writer with [`write_all`] before returning them. If
+/// writing fails, the error is returned from [`read`] and the bytes are lost
+/// to the caller, though they have already been consumed from the reader.
+///
+/// [`read`]: trait.Read.html#method.read
+/// [`write_all`]: trait.Write.html#method.write_all
+#[derive(Debug)]
+pub struct ReadTee<R, W> {
+    inner: R,
+    mirror: RefCell<W>,
+}
+
+impl<R, W> ReadTee<R, W>
+where
+    R: Read,
+    W: Write<Error = R::Error>,
+{
+    /// Wraps `inner`, copying everything read from it into `mirror`.
+    pub fn new(inner: R, mirror: W) -> Self {
+        ReadTee {
+            inner,
+            mirror: RefCell::new(mirror),
+        }
+    }
+
+    /// Gets a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets mutable references to the wrapped reader and the writer.
+    ///
+    /// Bytes read directly from the wrapped reader are not copied.
+    pub fn get_mut(&mut self) -> (&mut R, &mut W) {
+        (&mut self.inner, self.mirror.get_mut())
+    }
+
+    /// Unwraps this `ReadTee`, returning the wrapped reader and the writer.
+    pub fn into_inner(self) -> (R, W) {
+        (self.inner, self.mirror.into_inner())
+    }
+}
+
+impl<R: ErrorType, W> ErrorType for ReadTee<R, W> {
+    type Error = R::Error;
+}
+
+impl<R, W> Read for ReadTee<R, W>
+where
+    R: Read,
+    W: Write<Error = R::Error>,
+{
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = self.inner.read(buf)?;
+        self.mirror.borrow_mut().write_all(&buf[..n])?;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock::Cursor;
+    use {ErrorKind, Sink};
+
+    #[test]
+    fn tee_writes_to_both() {
+        let mut tee = Tee::new(Cursor::new(b""), Cursor::new(b"").chunked(2));
+        assert_eq!(tee.write(b"hello ").unwrap(), 6);
+        tee.write_all(b"world").unwrap();
+        tee.flush().unwrap();
+        let (a, b) = tee.into_inner();
+        assert_eq!(a.data(), b"hello world");
+        assert_eq!(b.data(), a.data());
+        assert_eq!((a.flushes, b.flushes), (1, 1));
+    }
+
+    #[test]
+    fn tee_diverges_when_second_fails() {
+        let mut tee = Tee::new(Cursor::new(b""), Cursor::new(b""));
+        tee.get_ref().1.fail(Some(ErrorKind::Other));
+        assert_eq!(tee.write(b"data"), Err(ErrorKind::Other));
+        let (a, b) = tee.into_inner();
+        assert_eq!(a.data(), b"data");
+        assert_eq!(b.data(), b"");
+    }
+
+    #[test]
+    fn read_tee_captures_stream() {
+        let reader = Cursor::new(b"captured input").chunked(4);
+        let tee = ReadTee::new(reader, Cursor::new(b""));
+        let mut buf = [0; 14];
+        tee.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"captured input");
+        assert_eq!(tee.read(&mut buf).unwrap(), 0);
+        let (_, mirror) = tee.into_inner();
+        assert_eq!(mirror.data(), b"captured input");
+    }
+
+    #[test]
+    fn read_tee_propagates_mirror_error() {
+        let tee = ReadTee::new(Cursor::new(b"abc"), Cursor::new(b""));
+        let mut buf = [0; 3];
+        tee.mirror.borrow().fail(Some(ErrorKind::WriteZero));
+        assert_eq!(tee.read(&mut buf), Err(ErrorKind::WriteZero));
+        assert_eq!(tee.get_ref().position(), 3);
+    }
+
+    #[test]
+    fn tee_skips_noop_flushes() {
+        let mut noop = Cursor::new(b"");
+        noop.flush_noop = true;
+        let mut tee = Tee::new(noop, Cursor::new(b""));
+        assert!(!tee.is_flush_noop());
+        tee.flush().unwrap();
+        let (a, b) = tee.get_ref();
+        assert_eq!((a.flushes, b.flushes), (0, 1));
+
+        let mut tee = Tee::new(Sink::new(), Cursor::new(b""));
+        tee.write_all(b"data").unwrap();
+        tee.flush().unwrap();
+        assert_eq!(tee.get_ref().1.flushes, 1);
+        assert!(Tee::new(Sink::<ErrorKind>::new(), Sink::new()).is_flush_noop());
+    }
+}
@@ -0,0 +1,165 @@
+use core::cell::Cell;
+
+use {ErrorType, Read};
+
+/// A reader which translates `\r\n` line endings to `\n`.
+///
+/// This normalizes text authored on Windows while reading it. A `\r` which is
+/// not followed by `\n` is passed through unchanged. Line endings split
+/// between two reads from the wrapped reader are handled by holding back a
+/// trailing `\r` until the next byte is known.
+#[derive(Debug)]
+pub struct TextReader<R> {
+    inner: R,
+    /// A byte read from the wrapped reader, but not returned yet.
+    carry: Cell<Option<u8>>,
+}
+
+impl<R: Read> TextReader<R> {
+    /// Wraps `inner`, translating its line endings.
+    pub fn new(inner: R) -> Self {
+        TextReader {
+            inner,
+            carry: Cell::new(None),
+        }
+    }
+
+    /// Gets a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Unwraps this `TextReader`, returning the wrapped reader.
+    ///
+    /// A byte which was held back from the last read is lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Returns the held back `\r` in a single byte buffer, followed by `\n` if
+    /// the next byte in the wrapped reader is one.
+    fn read_carried_cr(&self, buf: &mut [u8]) -> Result<usize, R::Error> {
+        let mut next = [0];
+        let n = match self.inner.read(&mut next) {
+            Ok(n) => n,
+            Err(err) => {
+                self.carry.set(Some(b'\r'));
+                return Err(err);
+            }
+        };
+        if n == 1 && next[0] == b'\n' {
+            buf[0] = b'\n';
+        } else {
+            buf[0] = b'\r';
+            if n == 1 {
+                self.carry.set(Some(next[0]));
+            }
+        }
+        Ok(1)
+    }
+}
+
+impl<R: ErrorType> ErrorType for TextReader<R> {
+    type Error = R::Error;
+}
+
+impl<R: Read> Read for TextReader<R> {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let (len, eof) = match self.carry.take() {
+                Some(b'\r') if buf.len() == 1 => {
+                    return self.read_carried_cr(buf)
+                }
+                Some(b) if buf.len() == 1 => {
+                    buf[0] = b;
+                    return Ok(1);
+                }
+                Some(b) => {
+                    buf[0] = b;
+                    match self.inner.read(&mut buf[1..]) {
+                        Ok(n) => (n + 1, n == 0),
+                        Err(err) => {
+                            self.carry.set(Some(b));
+                            return Err(err);
+                        }
+                    }
+                }
+                None => match self.inner.read(buf)? {
+                    0 => return Ok(0),
+                    n => (n, false),
+                },
+            };
+
+            let mut read = 0;
+            let mut written = 0;
+            while read < len {
+                if buf[read] == b'\r'
+                    && read + 1 < len
+                    && buf[read + 1] == b'\n'
+                {
+                    read += 1;
+                }
+                buf[written] = buf[read];
+                read += 1;
+                written += 1;
+            }
+            // a trailing `\r` may be the first half of a split line ending
+            if !eof && buf[written - 1] == b'\r' {
+                self.carry.set(Some(b'\r'));
+                written -= 1;
+            }
+            if written > 0 {
+                return Ok(written);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use mock::Cursor;
+
+    fn read_all(data: &[u8], chunk: usize, buf_len: usize) -> Vec<u8> {
+        let reader = TextReader::new(Cursor::new(data).chunked(chunk));
+        let mut out = Vec::new();
+        let mut buf = [0; 8];
+        loop {
+            match reader.read(&mut buf[..buf_len]).unwrap() {
+                0 => return out,
+                n => out.extend_from_slice(&buf[..n]),
+            }
+        }
+    }
+
+    #[test]
+    fn translates_split_line_endings() {
+        // every chunk size splits some `\r\n` across two reads
+        let data = b"ab\r\ncd\r\n\r\nend";
+        for chunk in 1..6 {
+            for &buf_len in &[1, 2, 3, 8] {
+                assert_eq!(
+                    read_all(data, chunk, buf_len),
+                    b"ab\ncd\n\nend",
+                    "chunk {} buf {}",
+                    chunk,
+                    buf_len
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn passes_lone_cr_through() {
+        for chunk in 1..4 {
+            assert_eq!(read_all(b"a\rb\r\r\nc\r", chunk, 8), b"a\rb\r\nc\r");
+            assert_eq!(read_all(b"\r", chunk, 1), b"\r");
+        }
+    }
+}
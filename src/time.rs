@@ -0,0 +1,75 @@
+use core::time::Duration;
+
+/// A point in time, as used for the timestamps of files.
+///
+/// It mirrors `std::time::SystemTime`, but since there's no clock without the
+/// standard library, it can only be created from a duration since the unix
+/// epoch. Times before the epoch can't be represented.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SystemTime {
+    since_epoch: Duration,
+}
+
+impl SystemTime {
+    /// The unix epoch, 1970-01-01 00:00:00 UTC.
+    pub const UNIX_EPOCH: SystemTime = SystemTime {
+        since_epoch: Duration::from_secs(0),
+    };
+
+    /// Creates a point in time `since_epoch` after the unix epoch.
+    pub fn from_unix(since_epoch: Duration) -> Self {
+        SystemTime { since_epoch }
+    }
+
+    /// Returns the amount of time elapsed from the unix epoch to this point in
+    /// time.
+    pub fn to_unix(&self) -> Duration {
+        self.since_epoch
+    }
+}
+
+impl Default for SystemTime {
+    fn default() -> Self {
+        SystemTime::UNIX_EPOCH
+    }
+}
+
+/// A set of timestamps to apply to a file with [`Fs::set_times`].
+///
+/// Timestamps which aren't set are left unchanged.
+///
+/// [`Fs::set_times`]: trait.Fs.html#method.set_times
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default, Hash)]
+pub struct FileTimes {
+    accessed: Option<SystemTime>,
+    modified: Option<SystemTime>,
+}
+
+impl FileTimes {
+    /// Creates a new set of timestamps, with none of them set.
+    pub fn new() -> Self {
+        FileTimes::default()
+    }
+
+    /// Sets the last access time of the file.
+    pub fn set_accessed(mut self, time: SystemTime) -> Self {
+        self.accessed = Some(time);
+        self
+    }
+
+    /// Sets the last modification time of the file.
+    pub fn set_modified(mut self, time: SystemTime) -> Self {
+        self.modified = Some(time);
+        self
+    }
+
+    /// Returns the last access time to set, if any.
+    pub fn accessed(&self) -> Option<SystemTime> {
+        self.accessed
+    }
+
+    /// Returns the last modification time to set, if any.
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+}
@@ -0,0 +1,142 @@
+use core::cell::Cell;
+
+use {ErrorKind, ErrorType, Read, Seek, SeekFrom, Write};
+
+/// A wrapper around a non-seekable stream which tracks the logical position
+/// in it.
+///
+/// The position starts at zero and grows by the number of bytes actually read
+/// from or written to the wrapped stream. `Tracked<T>` implements [`Seek`], so
+/// code which only needs the current position works over pipes and other
+/// streams that can't seek. Only [`SeekFrom::Current`] with an offset of 0 is
+/// supported, which reports the position, any other seek fails.
+///
+/// [`Seek`]: trait.Seek.html
+/// [`SeekFrom::Current`]: enum.SeekFrom.html#variant.Current
+#[derive(Debug, Default)]
+pub struct Tracked<T> {
+    inner: T,
+    pos: Cell<u64>,
+}
+
+impl<T> Tracked<T> {
+    /// Wraps a stream, with the position starting at zero.
+    pub fn new(inner: T) -> Self {
+        Tracked {
+            inner,
+            pos: Cell::new(0),
+        }
+    }
+
+    /// Returns the number of bytes transferred through this wrapper.
+    pub fn position(&self) -> u64 {
+        self.pos.get()
+    }
+
+    /// Gets a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the wrapped stream.
+    ///
+    /// Bytes transferred directly through the wrapped stream are not tracked.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwraps this `Tracked`, returning the wrapped stream.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: ErrorType> ErrorType for Tracked<T> {
+    type Error = T::Error;
+}
+
+impl<T: Read> Read for Tracked<T> {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = self.inner.read(buf)?;
+        self.pos.set(self.pos.get() + n as u64);
+        Ok(n)
+    }
+}
+
+impl<T: Write> Write for Tracked<T> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let n = self.inner.write(buf)?;
+        self.pos.set(self.pos.get() + n as u64);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+
+    fn is_flush_noop(&self) -> bool {
+        self.inner.is_flush_noop()
+    }
+
+    fn reserve(&mut self, additional: usize) -> Result<(), Self::Error> {
+        self.inner.reserve(additional)
+    }
+}
+
+impl<T: ErrorType> Seek for Tracked<T> {
+    /// Returns the current position for `SeekFrom::Current(0)`.
+    ///
+    /// # Errors
+    ///
+    /// Any other seek returns an error of kind [`ErrorKind::Unsupported`].
+    ///
+    /// [`ErrorKind::Unsupported`]: enum.ErrorKind.html#variant.Unsupported
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        match pos {
+            SeekFrom::Current(0) => Ok(self.pos.get()),
+            _ => Err(ErrorKind::Unsupported.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock::Cursor;
+
+    #[test]
+    fn tracks_bytes_read() {
+        let mut reader = Tracked::new(Cursor::new(b"0123456789").chunked(3));
+        let mut buf = [0; 4];
+        assert_eq!(reader.read(&mut buf).unwrap(), 3);
+        assert_eq!(reader.position(), 3);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(reader.stream_position().unwrap(), 7);
+        assert_eq!(reader.read(&mut [0; 8]).unwrap(), 3);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        assert_eq!(reader.position(), 10);
+    }
+
+    #[test]
+    fn tracks_bytes_written() {
+        let mut writer = Tracked::new(Cursor::new(b"").chunked(2));
+        writer.write_all(b"hello").unwrap();
+        assert_eq!(writer.position(), 5);
+        writer.get_mut().fail(Some(ErrorKind::Other));
+        assert!(writer.write(b"x").is_err());
+        assert_eq!(writer.position(), 5);
+    }
+
+    #[test]
+    fn real_seeks_fail() {
+        let mut reader = Tracked::new(Cursor::new(b"0123"));
+        reader.read_exact(&mut [0; 2]).unwrap();
+        for &pos in
+            &[SeekFrom::Start(0), SeekFrom::End(0), SeekFrom::Current(1)]
+        {
+            assert_eq!(reader.seek(pos), Err(ErrorKind::Unsupported));
+        }
+        assert_eq!(reader.seek(SeekFrom::Current(0)), Ok(2));
+        assert_eq!(reader.get_ref().seeks, 0);
+    }
+}
@@ -0,0 +1,20 @@
+/// A node of a directory tree to be created by [`Fs::create_tree`].
+///
+/// [`Fs::create_tree`]: trait.Fs.html#method.create_tree
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum TreeNode<'a, P: ?Sized + 'a> {
+    /// An empty directory.
+    Dir,
+    /// A regular file with the given contents.
+    File(&'a [u8]),
+    /// A symbolic link pointing to the given target.
+    Symlink(&'a P),
+}
+
+impl<'a, P: ?Sized> Clone for TreeNode<'a, P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, P: ?Sized> Copy for TreeNode<'a, P> {}
@@ -0,0 +1,339 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use MountInfo;
+use {DirOptions, FileTimes, Fs, LinkKind, Metadata, OpenOptions, SpecialKind};
+
+/// The umask a [`UmaskFs`] is created with.
+///
+/// [`UmaskFs`]: struct.UmaskFs.html
+const DEFAULT_UMASK: u32 = 0o022;
+
+/// A filesystem wrapper which applies a umask to the mode of newly created
+/// files.
+///
+/// Like the umask of a unix process, the bits set in the umask are cleared
+/// from the mode passed to [`open`], [`open_anonymous`], [`create_dir`] and
+/// [`mknod`] before they are forwarded to the wrapped filesystem. With the
+/// default umask of `0o022`, a file created with mode `0o666` ends up with
+/// mode `0o644`. The mode passed to [`set_permissions`] is not masked.
+///
+/// [`open`]: trait.Fs.html#method.open
+/// [`open_anonymous`]: trait.Fs.html#method.open_anonymous
+/// [`create_dir`]: trait.Fs.html#method.create_dir
+/// [`mknod`]: trait.Fs.html#method.mknod
+/// [`set_permissions`]: trait.Fs.html#method.set_permissions
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UmaskFs<F> {
+    inner: F,
+    umask: u32,
+}
+
+impl<F: Fs<Permissions = u32>> UmaskFs<F> {
+    /// Wraps a filesystem, applying the default umask of `0o022`.
+    pub fn new(inner: F) -> Self {
+        UmaskFs {
+            inner,
+            umask: DEFAULT_UMASK,
+        }
+    }
+
+    /// Returns the current umask.
+    pub fn umask(&self) -> u32 {
+        self.umask
+    }
+
+    /// Sets the umask, returning the previous one.
+    pub fn set_umask(&mut self, umask: u32) -> u32 {
+        let previous = self.umask;
+        self.umask = umask;
+        previous
+    }
+
+    /// Gets a reference to the underlying filesystem.
+    pub fn get_ref(&self) -> &F {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying filesystem.
+    ///
+    /// Files created through this reference are not affected by the umask.
+    pub fn get_mut(&mut self) -> &mut F {
+        &mut self.inner
+    }
+
+    /// Unwraps this `UmaskFs`, returning the underlying filesystem.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    fn mask_open(&self, options: &OpenOptions<u32>) -> OpenOptions<u32> {
+        let mut options = options.clone();
+        options.mode &= !self.umask;
+        options
+    }
+}
+
+impl<F: Fs<Permissions = u32>> Fs for UmaskFs<F> {
+    type Path = F::Path;
+    type PathOwned = F::PathOwned;
+    type File = F::File;
+    type Dir = F::Dir;
+    type DirEntry = F::DirEntry;
+    type Metadata = F::Metadata;
+    type Permissions = u32;
+    type Error = F::Error;
+
+    fn open(
+        &self,
+        path: &Self::Path,
+        options: &OpenOptions<u32>,
+    ) -> Result<Self::File, Self::Error> {
+        self.inner.open(path, &self.mask_open(options))
+    }
+
+    fn open_anonymous(
+        &mut self,
+        dir: &Self::Path,
+        options: &OpenOptions<u32>,
+    ) -> Result<Self::File, Self::Error> {
+        let options = self.mask_open(options);
+        self.inner.open_anonymous(dir, &options)
+    }
+
+    fn remove_file(&mut self, path: &Self::Path) -> Result<(), Self::Error> {
+        self.inner.remove_file(path)
+    }
+
+    fn metadata(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::Metadata, Self::Error> {
+        self.inner.metadata(path)
+    }
+
+    fn symlink_metadata(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::Metadata, Self::Error> {
+        self.inner.symlink_metadata(path)
+    }
+
+    fn file_type(
+        &self,
+        path: &Self::Path,
+    ) -> Result<<Self::Metadata as Metadata>::FileType, Self::Error> {
+        self.inner.file_type(path)
+    }
+
+    fn symlink_file_type(
+        &self,
+        path: &Self::Path,
+    ) -> Result<<Self::Metadata as Metadata>::FileType, Self::Error> {
+        self.inner.symlink_file_type(path)
+    }
+
+    fn rename(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.inner.rename(from, to)
+    }
+
+    fn copy(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<u64, Self::Error> {
+        self.inner.copy(from, to)
+    }
+
+    fn reflink(
+        &mut self,
+        from: &Self::Path,
+        to: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.inner.reflink(from, to)
+    }
+
+    fn hard_link(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.inner.hard_link(src, dst)
+    }
+
+    fn hard_link_checked(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<LinkKind, Self::Error> {
+        self.inner.hard_link_checked(src, dst)
+    }
+
+    fn symlink(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.inner.symlink(src, dst)
+    }
+
+    fn symlink_file(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.inner.symlink_file(src, dst)
+    }
+
+    fn symlink_dir(
+        &mut self,
+        src: &Self::Path,
+        dst: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.inner.symlink_dir(src, dst)
+    }
+
+    fn mknod(
+        &mut self,
+        path: &Self::Path,
+        mode: u32,
+        kind: SpecialKind,
+        dev: u64,
+    ) -> Result<(), Self::Error> {
+        self.inner.mknod(path, mode & !self.umask, kind, dev)
+    }
+
+    fn read_link(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::PathOwned, Self::Error> {
+        self.inner.read_link(path)
+    }
+
+    fn canonicalize(
+        &self,
+        path: &Self::Path,
+    ) -> Result<Self::PathOwned, Self::Error> {
+        self.inner.canonicalize(path)
+    }
+
+    fn canonicalize_into(
+        &self,
+        path: &Self::Path,
+        out: &mut Self::PathOwned,
+    ) -> Result<(), Self::Error> {
+        self.inner.canonicalize_into(path, out)
+    }
+
+    fn create_dir(
+        &mut self,
+        path: &Self::Path,
+        options: &DirOptions<u32>,
+    ) -> Result<(), Self::Error> {
+        let mut options = options.clone();
+        options.mode &= !self.umask;
+        self.inner.create_dir(path, &options)
+    }
+
+    fn remove_dir(&mut self, path: &Self::Path) -> Result<(), Self::Error> {
+        self.inner.remove_dir(path)
+    }
+
+    fn remove_dir_all(&mut self, path: &Self::Path) -> Result<(), Self::Error> {
+        self.inner.remove_dir_all(path)
+    }
+
+    fn read_dir(&self, path: &Self::Path) -> Result<Self::Dir, Self::Error> {
+        self.inner.read_dir(path)
+    }
+
+    fn set_permissions(
+        &mut self,
+        path: &Self::Path,
+        perm: u32,
+    ) -> Result<(), Self::Error> {
+        self.inner.set_permissions(path, perm)
+    }
+
+    fn set_permissions_nofollow(
+        &mut self,
+        path: &Self::Path,
+        perm: u32,
+    ) -> Result<(), Self::Error> {
+        self.inner.set_permissions_nofollow(path, perm)
+    }
+
+    fn set_times(
+        &mut self,
+        path: &Self::Path,
+        times: FileTimes,
+    ) -> Result<(), Self::Error> {
+        self.inner.set_times(path, times)
+    }
+
+    fn current_dir(&self) -> Result<Self::PathOwned, Self::Error> {
+        self.inner.current_dir()
+    }
+
+    fn set_current_dir(
+        &mut self,
+        path: &Self::Path,
+    ) -> Result<(), Self::Error> {
+        self.inner.set_current_dir(path)
+    }
+
+    fn available_space(&self, path: &Self::Path) -> Result<u64, Self::Error> {
+        self.inner.available_space(path)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn mount_points(
+        &self,
+    ) -> Result<Vec<MountInfo<Self::PathOwned>>, Self::Error> {
+        self.inner.mount_points()
+    }
+
+    fn mount_id(&self, path: &Self::Path) -> Result<u64, Self::Error> {
+        self.inner.mount_id(path)
+    }
+
+    fn sync(&self) -> Result<(), Self::Error> {
+        self.inner.sync()
+    }
+
+    fn flush_all(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush_all()
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use mem::MemFs;
+
+    #[test]
+    fn clears_umask_bits() {
+        let mut fs = UmaskFs::new(MemFs::new());
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).mode(0o666);
+        fs.open(b"/file", &options).unwrap();
+        assert_eq!(fs.metadata(b"/file").unwrap().mode(), 0o644);
+
+        fs.create_dir(b"/dir", DirOptions::new().mode(0o777))
+            .unwrap();
+        assert_eq!(fs.metadata(b"/dir").unwrap().mode(), 0o755);
+
+        assert_eq!(fs.set_umask(0o077), 0o022);
+        fs.open(b"/private", &options).unwrap();
+        assert_eq!(fs.metadata(b"/private").unwrap().mode(), 0o600);
+
+        // existing files keep their mode, and set_permissions isn't masked
+        fs.set_permissions(b"/file", 0o666).unwrap();
+        assert_eq!(fs.metadata(b"/file").unwrap().mode(), 0o666);
+    }
+}
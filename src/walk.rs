@@ -0,0 +1,175 @@
+use alloc::vec::Vec;
+
+use {DirEntry, FileType, Fs, PathBuf};
+
+/// A recursive iterator over the entries of a directory tree.
+///
+/// This struct is created by the [`walk`] method on [`Fs`]. The tree is
+/// traversed depth-first and every directory is yielded before its contents.
+/// The directory the walk started at is not yielded itself. Symbolic links
+/// are yielded, but never followed.
+///
+/// [`walk`]: trait.Fs.html#method.walk
+/// [`Fs`]: trait.Fs.html
+pub struct WalkDir<'a, F: 'a + Fs> {
+    fs: &'a F,
+    stack: Vec<F::Dir>,
+    pending: Option<<F::DirEntry as DirEntry>::PathOwned>,
+}
+
+impl<'a, F: Fs> WalkDir<'a, F> {
+    pub(crate) fn new(fs: &'a F, root: &F::Path) -> Result<Self, F::Error> {
+        Ok(WalkDir {
+            fs,
+            stack: Vec::new(),
+            pending: Some(PathBuf::from_path(root)?),
+        })
+    }
+
+    /// Skips the contents of the directory which was yielded last.
+    ///
+    /// Does nothing if the last entry wasn't a directory.
+    pub fn skip_current_dir(&mut self) {
+        self.pending = None;
+    }
+
+    /// Returns an iterator which only yields entries matching `predicate`.
+    ///
+    /// Unlike [`Iterator::filter`], which only hides the rejected entries
+    /// themselves, rejecting a directory prunes its entire subtree, whose
+    /// contents are then never read. This allows skipping large directories,
+    /// such as `.git`, without paying for their traversal.
+    ///
+    /// [`Iterator::filter`]: https://doc.rust-lang.org/core/iter/trait.Iterator.html#method.filter
+    pub fn filter_entry<P>(self, predicate: P) -> FilterEntry<'a, F, P>
+    where
+        P: FnMut(&F::DirEntry) -> bool,
+    {
+        FilterEntry {
+            inner: self,
+            predicate,
+        }
+    }
+}
+
+impl<'a, F: Fs> Iterator for WalkDir<'a, F> {
+    type Item = Result<F::DirEntry, F::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(path) = self.pending.take() {
+            match self.fs.read_dir(path.as_path()) {
+                Ok(dir) => self.stack.push(dir),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+
+        loop {
+            let entry = match self.stack.last_mut()?.next() {
+                Some(Ok(entry)) => entry,
+                Some(Err(err)) => return Some(Err(err)),
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            match entry.file_type() {
+                Ok(ref file_type) if file_type.is_dir() => {
+                    self.pending = Some(entry.path());
+                }
+                Ok(_) => {}
+                Err(err) => return Some(Err(err)),
+            }
+            return Some(Ok(entry));
+        }
+    }
+}
+
+/// A recursive iterator over the entries of a directory tree, which prunes
+/// the entries rejected by a predicate.
+///
+/// This struct is created by the [`filter_entry`] method on [`WalkDir`].
+///
+/// [`filter_entry`]: struct.WalkDir.html#method.filter_entry
+/// [`WalkDir`]: struct.WalkDir.html
+pub struct FilterEntry<'a, F: 'a + Fs, P> {
+    inner: WalkDir<'a, F>,
+    predicate: P,
+}
+
+impl<'a, F: Fs, P> FilterEntry<'a, F, P> {
+    /// Skips the contents of the directory which was yielded last.
+    ///
+    /// Does nothing if the last entry wasn't a directory.
+    pub fn skip_current_dir(&mut self) {
+        self.inner.skip_current_dir();
+    }
+}
+
+impl<'a, F, P> Iterator for FilterEntry<'a, F, P>
+where
+    F: Fs,
+    P: FnMut(&F::DirEntry) -> bool,
+{
+    type Item = Result<F::DirEntry, F::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.inner.next()? {
+                Ok(ref entry) if !(self.predicate)(entry) => {
+                    self.inner.skip_current_dir();
+                }
+                result => return Some(result),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use mem::MemFs;
+    use mock::write_file;
+    use DirOptions;
+
+    fn tree() -> MemFs {
+        let mut fs = MemFs::new();
+        let mut options = DirOptions::new();
+        options.recursive(true);
+        fs.create_dir(b"/root/keep/deep", &options).unwrap();
+        fs.create_dir(b"/root/skip/deep", &options).unwrap();
+        write_file(&fs, b"/root/keep/deep/a", b"").unwrap();
+        write_file(&fs, b"/root/skip/deep/b", b"").unwrap();
+        write_file(&fs, b"/root/skip/c", b"").unwrap();
+        fs
+    }
+
+    #[test]
+    fn walk_visits_everything() {
+        let fs = tree();
+        let paths: Vec<_> = fs
+            .walk(b"/root")
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        assert_eq!(paths.len(), 7);
+        assert!(paths.contains(&b"/root/skip/deep/b".to_vec()));
+    }
+
+    #[test]
+    fn filter_entry_prunes_subtree() {
+        let fs = tree();
+        let paths: Vec<_> = fs
+            .walk(b"/root")
+            .unwrap()
+            .filter_entry(|entry| entry.file_name() != b"skip")
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(
+            paths,
+            [&b"/root/keep"[..], b"/root/keep/deep", b"/root/keep/deep/a"]
+        );
+        assert!(!paths.iter().any(|path| path.starts_with(b"/root/skip")));
+    }
+}